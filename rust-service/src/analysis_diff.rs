@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::git::{DeltaCommit, GitProcessor};
+use crate::AppState;
+
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 5000;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisDiffQuery {
+    /// Earlier job (default: the completed job before `to`)
+    pub from: Option<String>,
+    /// Later job (default: the latest completed job)
+    pub to: Option<String>,
+    /// Commits listed per side (default 500, max 5000); totals are always exact
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisDiff {
+    pub repository_id: String,
+    pub from_job_id: String,
+    pub to_job_id: String,
+    pub from_tip: String,
+    pub to_tip: String,
+    /// The earlier tip is gone from the clone (force push, then gc); nothing is listed
+    pub previous_tip_missing: bool,
+    /// Commits removed from the branch since the earlier run
+    pub force_pushed: bool,
+    pub added_total: usize,
+    pub removed_total: usize,
+    /// Commits on the branch now that were not at the earlier tip, newest first
+    pub added: Vec<DeltaCommit>,
+    /// Commits at the earlier tip that are no longer on the branch, newest first
+    pub removed: Vec<DeltaCommit>,
+}
+
+/// A completed job of the repository and the branch tip it analyzed
+async fn job_tip(
+    state: &AppState,
+    repository_id: &str,
+    job_id: Option<&str>,
+    before: Option<&str>,
+) -> Result<Option<(String, String)>, sqlx::Error> {
+    let mut query = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT id, tipSha FROM AnalysisJob WHERE status = 'COMPLETED' AND tipSha IS NOT NULL AND repositoryId = ",
+    );
+    query.push_bind(repository_id);
+    if let Some(job_id) = job_id {
+        query.push(" AND id = ").push_bind(job_id);
+    }
+    if let Some(before) = before {
+        query
+            .push(" AND completedAt < (SELECT completedAt FROM AnalysisJob WHERE id = ")
+            .push_bind(before)
+            .push(")");
+    }
+    query.push(" ORDER BY completedAt DESC LIMIT 1");
+    query
+        .build_query_as::<(String, String)>()
+        .fetch_optional(&state.db)
+        .await
+}
+
+/// Commits added to (and, after a force push, removed from) the analyzed branch
+/// between two completed analysis runs, read from the service's clone
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/analysis-diff",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Repository id"), AnalysisDiffQuery),
+    responses(
+        (status = 200, body = AnalysisDiff),
+        (status = 404, description = "Repository, runs to compare or local clone not found", body = String)
+    )
+)]
+pub async fn analysis_diff(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<AnalysisDiffQuery>,
+) -> Result<Json<AnalysisDiff>, (StatusCode, String)> {
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let url: Option<String> = sqlx::query_scalar("SELECT url FROM Repository WHERE id = ?")
+        .bind(&repository_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal)?;
+    let url = url.ok_or_else(|| (StatusCode::NOT_FOUND, "Repository not found".to_string()))?;
+
+    let (to_job_id, to_tip) = job_tip(&state, &repository_id, query.to.as_deref(), None)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "No completed single-branch analysis to compare".to_string(),
+            )
+        })?;
+    let before = query.from.is_none().then_some(to_job_id.as_str());
+    let (from_job_id, from_tip) = job_tip(&state, &repository_id, query.from.as_deref(), before)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "No earlier completed analysis to compare with".to_string(),
+            )
+        })?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let delta = {
+        let processor = GitProcessor::new(&state.work_dir);
+        let (from_tip, to_tip) = (from_tip.clone(), to_tip.clone());
+        state
+            .git_pool
+            .run(move || processor.tip_delta(&url, &from_tip, &to_tip, limit))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?
+    };
+    let delta = delta.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "The service has no clone of this repository".to_string(),
+        )
+    })?;
+
+    Ok(Json(AnalysisDiff {
+        repository_id,
+        from_job_id,
+        to_job_id,
+        from_tip,
+        to_tip,
+        previous_tip_missing: !delta.previous_tip_found,
+        force_pushed: delta.removed_total > 0,
+        added_total: delta.added_total,
+        removed_total: delta.removed_total,
+        added: delta.added,
+        removed: delta.removed,
+    }))
+}
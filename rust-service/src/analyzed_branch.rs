@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use crate::{env_flag, AppState};
+
+/// Whether a job walks a single branch, so its commits can be tied to that branch.
+/// All-branches and release-only jobs span several refs and are not tracked.
+pub fn is_tracked(all_branches: bool, release_only: bool) -> bool {
+    !all_branches && !release_only
+}
+
+/// Compare the job's branch with the one the repository was last analyzed on.
+///
+/// Incremental analysis skips SHAs already stored, so switching branches (e.g. after the
+/// remote's default branch moved) would quietly mix two histories. A change fails the job
+/// unless it was confirmed or `REQUIRE_BRANCH_CONFIRMATION` is off, in which case it is
+/// logged and recorded on the job.
+pub async fn check(
+    state: &AppState,
+    job_id: &str,
+    repository_id: &str,
+    branch: &str,
+    confirmed: bool,
+) -> Result<()> {
+    let previous: Option<String> =
+        sqlx::query_scalar("SELECT analyzedBranch FROM Repository WHERE id = ?")
+            .bind(repository_id)
+            .fetch_optional(&state.db)
+            .await?
+            .flatten();
+    let Some(previous) = previous.filter(|previous| previous != branch) else {
+        return Ok(());
+    };
+
+    if !confirmed && env_flag("REQUIRE_BRANCH_CONFIRMATION", true) {
+        anyhow::bail!(
+            "Repository was last analyzed on branch '{}' but this job requests '{}'; \
+             set confirmBranchChange to analyze it anyway",
+            previous,
+            branch
+        );
+    }
+    tracing::warn!(
+        "Analyzing branch '{}' although the repository was last analyzed on '{}'",
+        branch,
+        previous
+    );
+    sqlx::query("UPDATE AnalysisJob SET branchChangedFrom = ? WHERE id = ?")
+        .bind(&previous)
+        .bind(job_id)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+/// Remember the branch a completed job analyzed
+pub async fn record(state: &AppState, repository_id: &str, branch: &str) -> Result<()> {
+    sqlx::query("UPDATE Repository SET analyzedBranch = ? WHERE id = ?")
+        .bind(branch)
+        .bind(repository_id)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Hex digits of the hash kept in an anonymous id
+const ID_HEX_LEN: usize = 12;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonymizeQuery {
+    /// Replace author names and emails with stable anonymous ids (default `STATS_ANONYMIZE`)
+    pub anonymize: Option<bool>,
+}
+
+/// Replaces author identities in stats with ids derived from a salted hash of the
+/// email, so the same person keeps the same id across endpoints and requests and
+/// trends stay comparable without showing who is who.
+///
+/// Without `ANONYMIZE_SALT` the ids are plain email hashes, which anyone can
+/// reproduce for a guessed address; set a secret salt before sharing stats.
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    /// Anonymizer for a stats request; None when it asked for real identities
+    pub fn for_request(query: &AnonymizeQuery) -> Option<Self> {
+        query
+            .anonymize
+            .unwrap_or_else(|| crate::env_flag("STATS_ANONYMIZE", false))
+            .then(|| Self {
+                salt: std::env::var("ANONYMIZE_SALT").unwrap_or_default(),
+            })
+    }
+
+    /// Stable id for an email (`author-3f9a0c2be41d`); emails differing only in case match
+    pub fn id(&self, email: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update([0])
+            .chain_update(email.trim().to_lowercase().as_bytes())
+            .finalize();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("author-{}", &hex[..ID_HEX_LEN])
+    }
+
+    /// Overwrite an author's email and name with their anonymous id
+    pub fn author(&self, email: &mut String, name: &mut String) {
+        *email = self.id(email);
+        *name = email.clone();
+    }
+}
+
+/// Apply `anonymizer` to every author of a response, when there is one
+pub fn authors<'a, T: 'a>(
+    anonymizer: &Option<Anonymizer>,
+    items: impl IntoIterator<Item = &'a mut T>,
+    fields: impl Fn(&mut T) -> (&mut String, &mut String),
+) {
+    if let Some(anonymizer) = anonymizer {
+        for item in items {
+            let (email, name) = fields(item);
+            anonymizer.author(email, name);
+        }
+    }
+}
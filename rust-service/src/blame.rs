@@ -0,0 +1,268 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::anonymize::{self, AnonymizeQuery, Anonymizer};
+use crate::git::{BlameOutcome, FileBlame, GitProcessor};
+use crate::AppState;
+
+/// Recently computed blames, keyed by clone URL, path and branch tip; a new tip
+/// misses the cache, so entries never go stale
+#[derive(Clone)]
+pub struct BlameCache {
+    inner: Arc<Mutex<Entries>>,
+    max_entries: usize,
+}
+
+#[derive(Default)]
+struct Entries {
+    blames: HashMap<(String, String, String), Arc<FileBlame>>,
+    /// Keys in insertion order, for evicting the oldest
+    order: VecDeque<(String, String, String)>,
+}
+
+impl BlameCache {
+    /// Holds up to `BLAME_CACHE_ENTRIES` files (default 256)
+    pub fn from_env() -> Self {
+        let max_entries = std::env::var("BLAME_CACHE_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(256);
+        Self {
+            inner: Arc::new(Mutex::new(Entries::default())),
+            max_entries,
+        }
+    }
+
+    fn get(&self, url: &str, path: &str, tip: &str) -> Option<Arc<FileBlame>> {
+        let entries = self.inner.lock().ok()?;
+        entries
+            .blames
+            .get(&(url.to_string(), path.to_string(), tip.to_string()))
+            .cloned()
+    }
+
+    fn insert(&self, url: &str, path: &str, blame: Arc<FileBlame>) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let Ok(mut entries) = self.inner.lock() else {
+            return;
+        };
+        let key = (url.to_string(), path.to_string(), blame.commit.clone());
+        if entries.blames.insert(key.clone(), blame).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.max_entries {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.blames.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Largest file blamed, from `BLAME_MAX_FILE_BYTES` (default 1 MiB)
+fn max_file_bytes() -> u64 {
+    std::env::var("BLAME_MAX_FILE_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1024 * 1024)
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameQuery {
+    /// File path relative to the repository root
+    pub path: String,
+    /// Also return the commit and author of every line, as runs of consecutive lines
+    pub lines: Option<bool>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameAuthor {
+    pub author_email: String,
+    pub author_name: String,
+    /// Lines at the branch tip this author last changed
+    pub lines: usize,
+    /// Share of the file's lines (0-1)
+    pub share: f64,
+    pub last_commit_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameLines {
+    /// 1-based first line of the run
+    pub start_line: usize,
+    pub lines: usize,
+    pub sha: String,
+    pub author_email: String,
+    pub author_name: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOwnership {
+    pub repository_id: String,
+    pub path: String,
+    pub branch: String,
+    /// Branch tip the file was blamed at
+    pub commit: String,
+    pub total_lines: usize,
+    /// Authors by lines owned, most first
+    pub authors: Vec<BlameAuthor>,
+    /// Last change of every line; only with `lines=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<Vec<BlameLines>>,
+}
+
+/// Who last changed each line of a file at the tip of the analyzed branch, from the
+/// service's clone. Results are cached per branch tip.
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/blame",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), BlameQuery, AnonymizeQuery),
+    responses(
+        (status = 200, body = FileOwnership),
+        (status = 404, description = "Repository, local clone or file not found", body = String),
+        (status = 413, description = "File is larger than BLAME_MAX_FILE_BYTES", body = String),
+        (status = 422, description = "File is binary", body = String)
+    )
+)]
+pub async fn file_blame(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<BlameQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<FileOwnership>, (StatusCode, String)> {
+    let path = query.path.trim_matches('/').to_string();
+    if path.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "path must not be empty".to_string()));
+    }
+    let row: Option<(String, String)> = sqlx::query_as(
+        "SELECT url, COALESCE(analyzedBranch, branch) FROM Repository WHERE id = ?",
+    )
+    .bind(&repository_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (url, branch) =
+        row.ok_or_else(|| (StatusCode::NOT_FOUND, "Repository not found".to_string()))?;
+
+    let max_bytes = max_file_bytes();
+    let outcome = {
+        let processor = GitProcessor::new(&state.work_dir);
+        let cache = state.blame_cache.clone();
+        let (url, branch, path) = (url.clone(), branch.clone(), path.clone());
+        state
+            .git_pool
+            .run(move || {
+                let outcome = processor.blame_file(&url, &branch, &path, max_bytes, |tip| {
+                    cache.get(&url, &path, tip)
+                })?;
+                if let BlameOutcome::Blamed(blame) = &outcome {
+                    cache.insert(&url, &path, Arc::clone(blame));
+                }
+                Ok(outcome)
+            })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    };
+    let blame = match outcome {
+        BlameOutcome::Blamed(blame) => blame,
+        BlameOutcome::NoClone => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "The service has no clone of this repository".to_string(),
+            ))
+        }
+        BlameOutcome::NotFound => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("{} is not a file on {}", path, branch),
+            ))
+        }
+        BlameOutcome::TooLarge(size) => {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("{} is {} bytes, over the {} byte limit", path, size, max_bytes),
+            ))
+        }
+        BlameOutcome::Binary => {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("{} is a binary file", path),
+            ))
+        }
+    };
+
+    let total_lines: usize = blame.hunks.iter().map(|hunk| hunk.lines).sum();
+    let mut by_author: HashMap<&str, BlameAuthor> = HashMap::new();
+    for hunk in &blame.hunks {
+        let author = by_author
+            .entry(hunk.author_email.as_str())
+            .or_insert_with(|| BlameAuthor {
+                author_email: hunk.author_email.clone(),
+                author_name: hunk.author_name.clone(),
+                lines: 0,
+                share: 0.0,
+                last_commit_at: hunk.commit_date,
+            });
+        author.lines += hunk.lines;
+        if hunk.commit_date >= author.last_commit_at {
+            author.last_commit_at = hunk.commit_date;
+            author.author_name = hunk.author_name.clone();
+        }
+    }
+    let mut authors: Vec<BlameAuthor> = by_author.into_values().collect();
+    for author in &mut authors {
+        author.share = author.lines as f64 / total_lines.max(1) as f64;
+    }
+    authors.sort_by(|a, b| {
+        b.lines
+            .cmp(&a.lines)
+            .then_with(|| a.author_email.cmp(&b.author_email))
+    });
+
+    let mut lines = query.lines.unwrap_or(false).then(|| {
+        blame
+            .hunks
+            .iter()
+            .map(|hunk| BlameLines {
+                start_line: hunk.start_line,
+                lines: hunk.lines,
+                sha: hunk.sha.clone(),
+                author_email: hunk.author_email.clone(),
+                author_name: hunk.author_name.clone(),
+                commit_date: hunk.commit_date,
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let anonymizer = Anonymizer::for_request(&anonymize);
+    anonymize::authors(&anonymizer, &mut authors, |a| {
+        (&mut a.author_email, &mut a.author_name)
+    });
+    anonymize::authors(&anonymizer, lines.iter_mut().flatten(), |l| {
+        (&mut l.author_email, &mut l.author_name)
+    });
+
+    Ok(Json(FileOwnership {
+        repository_id,
+        path,
+        branch,
+        commit: blame.commit.clone(),
+        total_lines,
+        authors,
+        lines,
+    }))
+}
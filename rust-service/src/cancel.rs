@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Error a job stops with once it notices it was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Job was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether a job failed because it was cancelled rather than because of an error
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Flag a running job polls between units of work (commits walked, commits stored,
+/// clone/fetch progress callbacks) to stop early
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Err([`Cancelled`]) once the job was cancelled
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// Tokens of the jobs running in this process, by job id
+#[derive(Clone, Default)]
+pub struct CancelRegistry {
+    tokens: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl CancelRegistry {
+    /// Track a job until the returned registration is dropped
+    pub fn register(&self, job_id: &str) -> Registration {
+        let token = CancelToken::default();
+        self.tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(job_id.to_string(), token.clone());
+        Registration {
+            registry: self.clone(),
+            job_id: job_id.to_string(),
+            token,
+        }
+    }
+
+    /// Signal a running job to stop; false when it isn't running in this process
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        match tokens.get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A running job's entry in the [`CancelRegistry`], removed when dropped
+pub struct Registration {
+    registry: CancelRegistry,
+    job_id: String,
+    token: CancelToken,
+}
+
+impl Registration {
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry
+            .tokens
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.job_id);
+    }
+}
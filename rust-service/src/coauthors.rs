@@ -0,0 +1,37 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+fn co_author_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?im)^[ \t]*co-authored-by:[ \t]*(.*?)[ \t]*<([^<>\s]+)>[ \t]*$")
+            .expect("valid co-author regex")
+    })
+}
+
+/// Distinct `Co-authored-by: Name <email>` trailers in a commit message
+pub fn extract_co_authors(message: &str, lowercase_emails: bool) -> Vec<CoAuthor> {
+    let mut co_authors: Vec<CoAuthor> = Vec::new();
+    for caps in co_author_regex().captures_iter(message) {
+        let email = if lowercase_emails {
+            caps[2].to_lowercase()
+        } else {
+            caps[2].to_string()
+        };
+        if !co_authors.iter().any(|c| c.email == email) {
+            co_authors.push(CoAuthor {
+                name: caps[1].to_string(),
+                email,
+            });
+        }
+    }
+    co_authors
+}
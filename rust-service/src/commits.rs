@@ -0,0 +1,515 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::git::GitProcessor;
+use crate::metadata::{CommitMetadata, Trailer};
+use crate::AppState;
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitMessage {
+    pub id: String,
+    pub sha: String,
+    pub message: String,
+    /// True when the message was read from S3 rather than the truncated DB copy
+    pub from_s3: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct CommitRow {
+    id: String,
+    repository_id: String,
+    sha: String,
+    author_name: String,
+    author_email: String,
+    commit_date: chrono::DateTime<chrono::Utc>,
+    commit_date_offset: i32,
+    message_title: String,
+    no_message: bool,
+    files_changed: Option<i32>,
+    code_files_changed: Option<i32>,
+    insertions: Option<i32>,
+    deletions: Option<i32>,
+    changed_paths: Option<String>,
+    jira_key: Option<String>,
+    jira_url: Option<String>,
+    summary: Option<String>,
+    summary_status: String,
+    parent_shas: Option<String>,
+    largest_file_path: Option<String>,
+    largest_file_size: Option<i64>,
+    is_signed: bool,
+    signature_verified: Option<bool>,
+    signature_identity: Option<String>,
+    signature_key_id: Option<String>,
+    signature_fingerprint: Option<String>,
+    github_verified: Option<bool>,
+    github_verification_reason: Option<String>,
+    template_violation: Option<bool>,
+    identity_id: Option<String>,
+    metadata: Option<sqlx::types::Json<CommitMetadata>>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDetail {
+    pub id: String,
+    pub repository_id: String,
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+    pub commit_date_offset: i32,
+    pub message_title: String,
+    /// The message is empty; `messageTitle` holds the placeholder
+    pub no_message: bool,
+    pub files_changed: Option<i32>,
+    pub code_files_changed: Option<i32>,
+    /// Lines added; null when diffs were skipped or timed out
+    pub insertions: Option<i32>,
+    /// Lines removed; null when diffs were skipped or timed out
+    pub deletions: Option<i32>,
+    pub changed_paths: Option<String>,
+    /// Status and source path of each changed file, so history can follow renames
+    pub changes: Vec<ChangedFile>,
+    pub jira_key: Option<String>,
+    pub jira_url: Option<String>,
+    pub summary: Option<String>,
+    pub summary_status: String,
+    /// Parent SHAs in order; null for commits stored before parents were recorded
+    pub parent_shas: Option<Vec<String>>,
+    pub is_merge: bool,
+    /// Biggest file added or modified by the commit; null when diffs were skipped
+    pub largest_file_path: Option<String>,
+    pub largest_file_size: Option<i64>,
+    /// Signature details; null when the commit is unsigned
+    pub signature: Option<CommitSignature>,
+    /// The message doesn't match the commit template; null when it wasn't checked
+    pub template_violation: Option<bool>,
+    /// Canonical identity of the author from the identity service; null when unresolved
+    pub identity_id: Option<String>,
+    /// `Key: value` trailers from the end of the message (Signed-off-by, Reviewed-by, ...)
+    pub trailers: Vec<Trailer>,
+    /// Other extracted attributes, as stored
+    #[schema(value_type = Object)]
+    pub metadata: CommitMetadata,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct ChangedFile {
+    /// `A`, `M`, `D`, `R` (renamed), `C` (copied) or `T` (type changed); null for
+    /// commits stored before statuses were recorded
+    pub status: Option<String>,
+    pub path: String,
+    /// Path the file was renamed or copied from
+    pub old_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitSignature {
+    /// Null when the signature was not checked against a keyring
+    pub verified: Option<bool>,
+    /// Signer email/principal of a verified signature
+    pub identity: Option<String>,
+    /// OpenPGP issuer key id (16 hex digits)
+    pub key_id: Option<String>,
+    /// OpenPGP fingerprint (hex) or SSH key fingerprint (`SHA256:...`)
+    pub fingerprint: Option<String>,
+    /// GitHub's verdict; null unless GitHub verification ran for the job
+    pub github_verified: Option<bool>,
+    /// GitHub `verification.reason` (`valid`, `unknown_key`, `bad_email`, ...)
+    pub github_verification_reason: Option<String>,
+}
+
+/// A single commit, including its parents for history navigation
+#[utoipa::path(
+    get,
+    path = "/commits/{id}",
+    tag = "commits",
+    params(("id" = String, Path, description = "Commit id")),
+    responses(
+        (status = 200, body = CommitDetail),
+        (status = 404, description = "Commit not found", body = String)
+    )
+)]
+pub async fn get_commit(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CommitDetail>, (StatusCode, String)> {
+    let row: Option<CommitRow> = sqlx::query_as(
+        r#"
+        SELECT id, repositoryId, sha, authorName, authorEmail, commitDate, commitDateOffset,
+               messageTitle, noMessage, filesChanged, codeFilesChanged, insertions, deletions,
+               changedPaths, jiraKey,
+               jiraUrl, summary, summaryStatus, parentShas, largestFilePath, largestFileSize,
+               isSigned, signatureVerified, signatureIdentity, signatureKeyId,
+               signatureFingerprint, githubVerified, githubVerificationReason,
+               templateViolation, identityId, metadata
+        FROM Commit
+        WHERE id = ?
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let row = row.ok_or_else(|| (StatusCode::NOT_FOUND, "Commit not found".to_string()))?;
+    let changes: Vec<ChangedFile> = sqlx::query_as(
+        "SELECT status, path, oldPath FROM CommitFile WHERE commitId = ? ORDER BY path",
+    )
+    .bind(&row.id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let parent_shas: Option<Vec<String>> = row
+        .parent_shas
+        .map(|shas| shas.split_whitespace().map(String::from).collect());
+    let is_merge = parent_shas.as_ref().is_some_and(|p| p.len() > 1);
+    let metadata = row.metadata.map(|m| m.0).unwrap_or_default();
+    let signature = row.is_signed.then_some(CommitSignature {
+        verified: row.signature_verified,
+        identity: row.signature_identity,
+        key_id: row.signature_key_id,
+        fingerprint: row.signature_fingerprint,
+        github_verified: row.github_verified,
+        github_verification_reason: row.github_verification_reason,
+    });
+
+    Ok(Json(CommitDetail {
+        id: row.id,
+        repository_id: row.repository_id,
+        sha: row.sha,
+        author_name: row.author_name,
+        author_email: row.author_email,
+        commit_date: row.commit_date,
+        commit_date_offset: row.commit_date_offset,
+        message_title: row.message_title,
+        no_message: row.no_message,
+        files_changed: row.files_changed,
+        code_files_changed: row.code_files_changed,
+        insertions: row.insertions,
+        deletions: row.deletions,
+        changed_paths: row.changed_paths,
+        changes,
+        jira_key: row.jira_key,
+        jira_url: row.jira_url,
+        summary: row.summary,
+        summary_status: row.summary_status,
+        parent_shas,
+        is_merge,
+        largest_file_path: row.largest_file_path,
+        largest_file_size: row.largest_file_size,
+        signature,
+        template_violation: row.template_violation,
+        identity_id: row.identity_id,
+        trailers: metadata.trailers(),
+        metadata,
+    }))
+}
+
+/// Full commit message, resolving messages that were offloaded to S3
+#[utoipa::path(
+    get,
+    path = "/commits/{id}/message",
+    tag = "commits",
+    params(("id" = String, Path, description = "Commit id")),
+    responses(
+        (status = 200, body = CommitMessage),
+        (status = 404, description = "Commit not found", body = String)
+    )
+)]
+pub async fn full_message(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CommitMessage>, (StatusCode, String)> {
+    let row: Option<(String, String, Option<String>)> =
+        sqlx::query_as("SELECT sha, message, messageKey FROM Commit WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let (sha, message, message_key) =
+        row.ok_or_else(|| (StatusCode::NOT_FOUND, "Commit not found".to_string()))?;
+
+    let (message, from_s3) = match (message_key, &state.s3) {
+        (Some(key), Some(s3)) => {
+            let bytes = s3
+                .get_object(&key)
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+            (String::from_utf8_lossy(&bytes).into_owned(), true)
+        }
+        (Some(key), None) => {
+            tracing::warn!("Commit {} has message in S3 ({}) but S3 is not configured", id, key);
+            (message, false)
+        }
+        (None, _) => (message, false),
+    };
+
+    Ok(Json(CommitMessage {
+        id,
+        sha,
+        message,
+        from_s3,
+    }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredFileDiff {
+    pub path: String,
+    /// Unified diff of the file, cut to the job's per-file limit
+    pub patch: String,
+    /// Size of the full diff in bytes
+    pub bytes: i32,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitFileDiffs {
+    pub id: String,
+    pub diffs: Vec<StoredFileDiff>,
+}
+
+/// Per-file diffs stored when the commit was analyzed with `includeDiffs`; unlike
+/// `/commits/:id/diff` this works without the service's clone. Empty for commits
+/// analyzed without it.
+#[utoipa::path(
+    get,
+    path = "/commits/{id}/file-diffs",
+    tag = "commits",
+    params(("id" = String, Path, description = "Commit id")),
+    responses(
+        (status = 200, body = CommitFileDiffs),
+        (status = 404, description = "Commit not found", body = String)
+    )
+)]
+pub async fn file_diffs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CommitFileDiffs>, (StatusCode, String)> {
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let exists: Option<String> = sqlx::query_scalar("SELECT id FROM Commit WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal)?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Commit not found".to_string()));
+    }
+
+    let diffs: Vec<StoredFileDiff> = sqlx::query_as(
+        "SELECT path, patch, bytes, truncated FROM CommitDiff WHERE commitId = ? ORDER BY path",
+    )
+    .bind(&id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(CommitFileDiffs { id, diffs }))
+}
+
+/// Largest diff returned by `GET /commits/:id/diff`, from `COMMIT_DIFF_MAX_BYTES` (default 1 MiB)
+fn max_diff_bytes() -> usize {
+    std::env::var("COMMIT_DIFF_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1024 * 1024)
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffQuery {
+    /// Lower size limit in bytes than `COMMIT_DIFF_MAX_BYTES`
+    pub max_bytes: Option<usize>,
+    /// Cut an oversized diff with a marker (default) instead of answering 413
+    pub truncate: Option<bool>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDiff {
+    pub id: String,
+    pub sha: String,
+    /// Unified diff against the first parent
+    pub patch: String,
+    /// The diff exceeded `maxBytes`; `patch` ends with a truncation marker
+    pub truncated: bool,
+    pub max_bytes: usize,
+}
+
+/// Unified diff of a commit, read from the service's clone and bounded in size
+#[utoipa::path(
+    get,
+    path = "/commits/{id}/diff",
+    tag = "commits",
+    params(("id" = String, Path, description = "Commit id"), DiffQuery),
+    responses(
+        (status = 200, body = CommitDiff),
+        (status = 404, description = "Commit or local clone not found", body = String),
+        (status = 413, description = "Diff exceeds maxBytes and truncate=false", body = String)
+    )
+)]
+pub async fn commit_diff(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<CommitDiff>, (StatusCode, String)> {
+    let row: Option<(String, String)> = sqlx::query_as(
+        "SELECT c.sha, r.url FROM Commit c JOIN Repository r ON r.id = c.repositoryId WHERE c.id = ?",
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (sha, url) = row.ok_or_else(|| (StatusCode::NOT_FOUND, "Commit not found".to_string()))?;
+
+    let max_bytes = query
+        .max_bytes
+        .map_or(max_diff_bytes(), |requested| requested.min(max_diff_bytes()));
+    let patch = {
+        let processor = GitProcessor::new(&state.work_dir);
+        let sha = sha.clone();
+        state
+            .git_pool
+            .run(move || processor.commit_patch(&url, &sha, max_bytes))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    };
+    let mut patch = patch.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "Commit is not in the service's clone of the repository".to_string(),
+        )
+    })?;
+
+    if patch.truncated {
+        if !query.truncate.unwrap_or(true) {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Diff is larger than {} bytes", max_bytes),
+            ));
+        }
+        patch
+            .patch
+            .push_str(&format!("\n[diff truncated at {} bytes]\n", max_bytes));
+    }
+
+    Ok(Json(CommitDiff {
+        id,
+        sha,
+        patch: patch.patch,
+        truncated: patch.truncated,
+        max_bytes,
+    }))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct PageQuery {
+    /// 1-based page number (default 1)
+    pub page: Option<u32>,
+    /// Page size (default 50, max 500)
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct CommitListItem {
+    pub id: String,
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+    pub message_title: String,
+    pub files_changed: Option<i32>,
+    pub changed_paths: Option<String>,
+    pub jira_key: Option<String>,
+    pub jira_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCommitsPage {
+    pub job_id: String,
+    pub job_status: String,
+    /// False while the job is still running; more commits may appear
+    pub complete: bool,
+    pub processed_commits: i32,
+    pub total_commits: i32,
+    pub page: u32,
+    pub limit: u32,
+    pub commits: Vec<CommitListItem>,
+}
+
+/// Commits stored by a job so far; usable while the job is still running
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/commits",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Analysis job id"), PageQuery),
+    responses(
+        (status = 200, body = JobCommitsPage),
+        (status = 404, description = "Job not found", body = String)
+    )
+)]
+pub async fn list_job_commits(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<JobCommitsPage>, (StatusCode, String)> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let job: Option<(String, i32, i32)> = sqlx::query_as(
+        "SELECT status, processedCommits, totalCommits FROM AnalysisJob WHERE id = ?",
+    )
+    .bind(&job_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (job_status, processed_commits, total_commits) =
+        job.ok_or_else(|| (StatusCode::NOT_FOUND, "Job not found".to_string()))?;
+
+    let commits: Vec<CommitListItem> = sqlx::query_as(
+        r#"
+        SELECT id, sha, authorName, authorEmail, commitDate, messageTitle, filesChanged,
+               changedPaths, jiraKey, jiraUrl
+        FROM Commit
+        WHERE analysisJobId = ?
+        ORDER BY commitDate DESC, id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(&job_id)
+    .bind(limit)
+    .bind((page - 1) as u64 * limit as u64)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let complete = matches!(job_status.as_str(), "COMPLETED" | "FAILED" | "CANCELLED");
+
+    Ok(Json(JobCommitsPage {
+        job_id,
+        job_status,
+        complete,
+        processed_commits,
+        total_commits,
+        page,
+        limit,
+        commits,
+    }))
+}
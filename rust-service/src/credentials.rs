@@ -0,0 +1,170 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+
+use crate::AppState;
+
+/// Prefix marking the storage format, so the scheme can change later
+const FORMAT_PREFIX: &str = "v1:";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM for credential tokens stored at rest
+#[derive(Clone)]
+pub struct Cipher {
+    cipher: Aes256Gcm,
+}
+
+impl Cipher {
+    /// Key from `CREDENTIAL_ENCRYPTION_KEY` (base64 of 32 bytes).
+    /// Returns None when unset; an invalid key is an error so it is not silently ignored.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(encoded) = std::env::var("CREDENTIAL_ENCRYPTION_KEY") else {
+            return Ok(None);
+        };
+        if encoded.trim().is_empty() {
+            return Ok(None);
+        }
+        let key = STANDARD
+            .decode(encoded.trim())
+            .context("CREDENTIAL_ENCRYPTION_KEY is not valid base64")?;
+        if key.len() != 32 {
+            anyhow::bail!("CREDENTIAL_ENCRYPTION_KEY must decode to 32 bytes");
+        }
+        Ok(Some(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        }))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt credential"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", FORMAT_PREFIX, STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let encoded = stored
+            .strip_prefix(FORMAT_PREFIX)
+            .context("Unknown credential format")?;
+        let payload = STANDARD
+            .decode(encoded)
+            .context("Stored credential is not valid base64")?;
+        if payload.len() <= NONCE_LEN {
+            anyhow::bail!("Stored credential is truncated");
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt credential (wrong key?)"))?;
+        String::from_utf8(plaintext).context("Stored credential is not UTF-8")
+    }
+}
+
+/// Decrypted token stored for a repository, if any
+pub async fn stored_token(state: &AppState, repository_id: &str) -> Result<Option<String>> {
+    let Some(cipher) = &state.credential_cipher else {
+        return Ok(None);
+    };
+    let stored: Option<Option<String>> =
+        sqlx::query_scalar("SELECT encryptedToken FROM Repository WHERE id = ?")
+            .bind(repository_id)
+            .fetch_optional(&state.db)
+            .await?;
+    stored
+        .flatten()
+        .map(|token| cipher.decrypt(&token))
+        .transpose()
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct CredentialRequest {
+    /// Access token used for clone/fetch; stored encrypted and never returned
+    pub token: String,
+}
+
+/// Store an encrypted access token for a repository so later jobs can omit it
+#[utoipa::path(
+    put,
+    path = "/repositories/{id}/credential",
+    tag = "repositories",
+    params(("id" = String, Path, description = "Repository id")),
+    request_body = CredentialRequest,
+    responses(
+        (status = 204, description = "Credential stored"),
+        (status = 404, description = "Repository not found", body = String),
+        (status = 503, description = "CREDENTIAL_ENCRYPTION_KEY is not configured", body = String)
+    )
+)]
+pub async fn set_credential(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Json(request): Json<CredentialRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let cipher = state.credential_cipher.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Credential storage is not configured (set CREDENTIAL_ENCRYPTION_KEY)".to_string(),
+        )
+    })?;
+    if request.token.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Token must not be empty".to_string(),
+        ));
+    }
+    let encrypted = cipher
+        .encrypt(request.token.trim())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let result = sqlx::query("UPDATE Repository SET encryptedToken = ? WHERE id = ?")
+        .bind(encrypted)
+        .bind(&repository_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Repository not found".to_string()));
+    }
+
+    tracing::info!("Stored credential for repository {}", repository_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Forget the stored access token of a repository
+#[utoipa::path(
+    delete,
+    path = "/repositories/{id}/credential",
+    tag = "repositories",
+    params(("id" = String, Path, description = "Repository id")),
+    responses(
+        (status = 204, description = "Credential removed"),
+        (status = 404, description = "Repository not found", body = String)
+    )
+)]
+pub async fn delete_credential(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query("UPDATE Repository SET encryptedToken = NULL WHERE id = ?")
+        .bind(&repository_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Repository not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
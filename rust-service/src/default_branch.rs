@@ -0,0 +1,69 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::git::GitProcessor;
+use crate::AppState;
+
+/// Whether the request asks for the remote's default branch instead of a named one
+pub fn is_requested(branch: &str) -> bool {
+    let branch = branch.trim();
+    branch.is_empty() || branch == "HEAD"
+}
+
+/// How long a detected default branch is trusted before asking the remote again
+fn cache_ttl() -> chrono::Duration {
+    let hours = std::env::var("DEFAULT_BRANCH_CACHE_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(24);
+    chrono::Duration::hours(hours)
+}
+
+/// Default branch for a repository, from the Repository row while fresh, otherwise from the remote
+pub async fn resolve(
+    state: &AppState,
+    processor: &GitProcessor,
+    repository_id: &str,
+    url: &str,
+    token: Option<&str>,
+) -> Result<String> {
+    let cached: Option<(Option<String>, Option<DateTime<Utc>>)> = sqlx::query_as(
+        "SELECT defaultBranch, defaultBranchCheckedAt FROM Repository WHERE id = ?",
+    )
+    .bind(repository_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    if let Some((Some(branch), Some(checked_at))) = cached {
+        if Utc::now() - checked_at < cache_ttl() {
+            tracing::info!("Using cached default branch '{}'", branch);
+            return Ok(branch);
+        }
+    }
+
+    let branch = {
+        let processor = processor.clone();
+        let url = url.to_string();
+        let token = token.map(str::to_string);
+        state
+            .git_pool
+            .run(move || processor.detect_default_branch(&url, token.as_deref()))
+            .await?
+    };
+    tracing::info!("Detected default branch '{}' from remote", branch);
+    record(state, repository_id, &branch).await?;
+
+    Ok(branch)
+}
+
+/// Cache the default branch on the Repository row
+pub async fn record(state: &AppState, repository_id: &str, branch: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE Repository SET defaultBranch = ?, defaultBranchCheckedAt = NOW() WHERE id = ?",
+    )
+    .bind(branch)
+    .bind(repository_id)
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
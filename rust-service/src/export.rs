@@ -0,0 +1,901 @@
+use anyhow::Result;
+use arrow_array::{
+    ArrayRef, BooleanArray, Int32Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use tokio::io::AsyncReadExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::s3::{MultipartUpload, UploadedPart};
+use crate::AppState;
+
+/// Caps how many exports run at once; further ones wait (still PENDING) in a bounded queue
+#[derive(Clone)]
+pub struct ExportLimiter {
+    slots: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    max_queued: usize,
+}
+
+/// A place in the export limiter: either a running slot or a spot in the queue
+enum Reservation {
+    Running(OwnedSemaphorePermit),
+    Queued,
+}
+
+impl ExportLimiter {
+    /// `MAX_CONCURRENT_EXPORTS` running (default 2) and `EXPORT_QUEUE_LIMIT` waiting (default 10)
+    pub fn from_env() -> Self {
+        let env_usize = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            slots: Arc::new(Semaphore::new(env_usize("MAX_CONCURRENT_EXPORTS", 2).max(1))),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queued: env_usize("EXPORT_QUEUE_LIMIT", 10),
+        }
+    }
+
+    /// Take a slot or a queue spot; None when both are full
+    fn reserve(&self) -> Option<Reservation> {
+        if let Ok(permit) = Arc::clone(&self.slots).try_acquire_owned() {
+            return Some(Reservation::Running(permit));
+        }
+        self.queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.max_queued).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Reservation::Queued)
+    }
+
+    /// Queue regardless of the queue limit (exports resumed after a restart)
+    fn enqueue(&self) -> Reservation {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        Reservation::Queued
+    }
+
+    /// Wait for a running slot
+    async fn acquire(&self, reservation: Reservation) -> OwnedSemaphorePermit {
+        match reservation {
+            Reservation::Running(permit) => permit,
+            Reservation::Queued => {
+                let permit = Arc::clone(&self.slots)
+                    .acquire_owned()
+                    .await
+                    .expect("export semaphore is never closed");
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+                permit
+            }
+        }
+    }
+}
+
+/// Size of each multipart chunk held in memory while exporting (`EXPORT_PART_SIZE_MB`)
+fn export_part_size() -> usize {
+    std::env::var("EXPORT_PART_SIZE_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(8)
+        * 1024
+        * 1024
+}
+
+/// Rows buffered per Parquet row group (`EXPORT_PARQUET_ROW_GROUP`)
+fn parquet_row_group() -> usize {
+    std::env::var("EXPORT_PARQUET_ROW_GROUP")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(10_000)
+        .max(1)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// One JSON object per line, streamed into S3 as rows are read
+    #[default]
+    Ndjson,
+    /// Typed columns (timestamps, integers) for loading into a warehouse; written to a
+    /// local file first and uploaded once complete
+    Parquet,
+}
+
+impl ExportFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "parquet" => Self::Parquet,
+            _ => Self::Ndjson,
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub repo_ids: Option<Vec<String>>,
+    pub author_emails: Option<Vec<String>>,
+    /// Inclusive start date (YYYY-MM-DD)
+    pub start_date: Option<String>,
+    /// Inclusive end date (YYYY-MM-DD)
+    pub end_date: Option<String>,
+    /// Bucket to write the export to instead of `S3_BUCKET` (must be in `EXPORT_S3_TARGETS`)
+    pub bucket: Option<String>,
+    /// Key prefix instead of `S3_EXPORT_PREFIX` (must be allowed by `EXPORT_S3_TARGETS`)
+    pub prefix: Option<String>,
+    /// `ndjson` (default) or `parquet`
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResponse {
+    pub export_id: String,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct ExportStatus {
+    pub id: String,
+    pub status: String,
+    pub file_name: Option<String>,
+    /// Bucket holding the file; null means the default `S3_BUCKET`
+    pub bucket: Option<String>,
+    /// `ndjson` or `parquet`
+    pub format: String,
+    pub file_key: Option<String>,
+    pub file_size: Option<i32>,
+    pub row_count: Option<i32>,
+    pub progress: i32,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One commit of the export: an NDJSON line or a Parquet row
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+struct ExportRow {
+    id: String,
+    repository_id: String,
+    repository_name: String,
+    sha: String,
+    author_name: String,
+    author_email: String,
+    commit_date: chrono::DateTime<chrono::Utc>,
+    /// Committer's UTC offset in minutes
+    commit_date_offset: i32,
+    message_title: String,
+    message: String,
+    #[serde(skip)]
+    message_key: Option<String>,
+    files_changed: Option<i32>,
+    code_files_changed: Option<i32>,
+    changed_paths: Option<String>,
+    jira_key: Option<String>,
+    jira_url: Option<String>,
+    is_signed: bool,
+    /// Signing key details; left out of the file when `EXPORT_SIGNATURE_DETAILS` is off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_key_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_fingerprint: Option<String>,
+}
+
+fn parse_date(value: &str) -> Result<chrono::NaiveDate, (StatusCode, String)> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid date '{}', expected YYYY-MM-DD", value),
+        )
+    })
+}
+
+fn default_export_prefix() -> String {
+    std::env::var("S3_EXPORT_PREFIX").unwrap_or_else(|_| "exports".into())
+}
+
+/// Check a requested bucket/prefix override against `EXPORT_S3_TARGETS`, a comma-separated
+/// list of `bucket` or `bucket/prefix` entries. An entry with a prefix allows that prefix
+/// and anything below it; a bare bucket allows any prefix.
+fn validate_target(
+    default_bucket: &str,
+    bucket: Option<&str>,
+    prefix: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    if let Some(prefix) = prefix {
+        let valid = !prefix.is_empty()
+            && !prefix.starts_with('/')
+            && prefix.split('/').all(|segment| segment != ".." && segment != ".")
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!-_.*'()/".contains(c));
+        if !valid {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid export prefix '{}'", prefix),
+            ));
+        }
+    }
+    if bucket.is_none() && prefix.is_none() {
+        return Ok(());
+    }
+
+    let bucket = bucket.unwrap_or(default_bucket);
+    let prefix = prefix
+        .map(String::from)
+        .unwrap_or_else(default_export_prefix);
+    let prefix = prefix.trim_end_matches('/');
+    let targets = std::env::var("EXPORT_S3_TARGETS").unwrap_or_default();
+    let allowed = targets
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .any(|target| match target.split_once('/') {
+            Some((allowed_bucket, allowed_prefix)) => {
+                let allowed_prefix = allowed_prefix.trim_end_matches('/');
+                allowed_bucket == bucket
+                    && (prefix == allowed_prefix
+                        || prefix.starts_with(&format!("{}/", allowed_prefix)))
+            }
+            None => target == bucket,
+        });
+    if !allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Export target s3://{}/{} is not allowed", bucket, prefix),
+        ));
+    }
+    Ok(())
+}
+
+/// Append the request's filters to a query that selects from `Commit c`
+fn push_filters(
+    builder: &mut sqlx::QueryBuilder<'_, sqlx::MySql>,
+    request: &ExportRequest,
+    start: Option<chrono::NaiveDateTime>,
+    end: Option<chrono::NaiveDateTime>,
+) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(repo_ids) = request.repo_ids.as_ref().filter(|ids| !ids.is_empty()) {
+        builder.push(" AND c.repositoryId IN (");
+        let mut ids = builder.separated(", ");
+        for id in repo_ids {
+            ids.push_bind(id.clone());
+        }
+        ids.push_unseparated(")");
+    }
+    if let Some(emails) = request.author_emails.as_ref().filter(|e| !e.is_empty()) {
+        builder.push(" AND c.authorEmail IN (");
+        let mut list = builder.separated(", ");
+        for email in emails {
+            list.push_bind(email.clone());
+        }
+        list.push_unseparated(")");
+    }
+    if let Some(start) = start {
+        builder.push(" AND c.commitDate >= ").push_bind(start);
+    }
+    if let Some(end) = end {
+        builder.push(" AND c.commitDate <= ").push_bind(end);
+    }
+}
+
+/// Start an export of commit rows into S3, as NDJSON (streamed) or Parquet
+#[utoipa::path(
+    post,
+    path = "/exports",
+    tag = "exports",
+    request_body = ExportRequest,
+    responses(
+        (status = 200, description = "Export started in background", body = ExportResponse),
+        (status = 400, description = "Invalid filters or prefix", body = String),
+        (status = 403, description = "Bucket/prefix not in EXPORT_S3_TARGETS", body = String),
+        (status = 503, description = "S3 is not configured, or too many exports are running and queued", body = String)
+    )
+)]
+pub async fn create_export(
+    State(state): State<AppState>,
+    Json(request): Json<ExportRequest>,
+) -> Result<Json<ExportResponse>, (StatusCode, String)> {
+    if state.s3.is_none() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "S3 is not configured (set S3_BUCKET)".to_string(),
+        ));
+    }
+    let start = request
+        .start_date
+        .as_deref()
+        .map(parse_date)
+        .transpose()?
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap());
+    let end = request
+        .end_date
+        .as_deref()
+        .map(parse_date)
+        .transpose()?
+        .map(|d| d.and_hms_opt(23, 59, 59).unwrap());
+
+    let default_bucket = state.s3.as_ref().map(|s3| s3.bucket()).unwrap_or_default();
+    validate_target(
+        default_bucket,
+        request.bucket.as_deref(),
+        request.prefix.as_deref(),
+    )?;
+
+    let reservation = state.exports.reserve().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many exports in progress, try again later".to_string(),
+        )
+    })?;
+
+    let export_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO ExportJob (id, status, startDate, endDate, authorEmail, repoIds, bucket,
+            keyPrefix, format, progress)
+        VALUES (?, 'PENDING', ?, ?, ?, ?, ?, ?, ?, 0)
+        "#,
+    )
+    .bind(&export_id)
+    .bind(start)
+    .bind(end)
+    .bind(request.author_emails.as_ref().map(|e| e.join(",")))
+    .bind(
+        request
+            .repo_ids
+            .as_ref()
+            .map(|ids| serde_json::to_string(ids).unwrap_or_default()),
+    )
+    .bind(&request.bucket)
+    .bind(&request.prefix)
+    .bind(request.format.unwrap_or_default().as_str())
+    .execute(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    spawn_export(state, reservation, export_id.clone(), request, start, end);
+
+    Ok(Json(ExportResponse {
+        export_id,
+        status: "PENDING".to_string(),
+    }))
+}
+
+/// Run an export in the background, marking it FAILED if it errors
+fn spawn_export(
+    state: AppState,
+    reservation: Reservation,
+    export_id: String,
+    request: ExportRequest,
+    start: Option<chrono::NaiveDateTime>,
+    end: Option<chrono::NaiveDateTime>,
+) {
+    tokio::spawn(async move {
+        if matches!(reservation, Reservation::Queued) {
+            tracing::info!("Export {} queued until a slot frees up", export_id);
+        }
+        let _permit = state.exports.acquire(reservation).await;
+        if let Err(e) = run_export(&state, &export_id, &request, start, end).await {
+            tracing::error!("Export {} failed: {}", export_id, e);
+            let _ = sqlx::query("UPDATE ExportJob SET status = 'FAILED', error = ? WHERE id = ?")
+                .bind(e.to_string())
+                .bind(&export_id)
+                .execute(&state.db)
+                .await;
+        }
+    });
+}
+
+/// Multipart upload state saved after every uploaded part so a restart can resume
+#[derive(Debug, sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct SavedUpload {
+    file_key: String,
+    file_name: String,
+    upload_id: String,
+    part_size: i32,
+    parts: sqlx::types::Json<Vec<UploadedPart>>,
+    bytes_uploaded: i64,
+    rows_written: i32,
+    last_commit_date: Option<chrono::DateTime<chrono::Utc>>,
+    last_commit_id: Option<String>,
+}
+
+async fn save_checkpoint(
+    state: &AppState,
+    export_id: &str,
+    upload: &MultipartUpload,
+    rows_written: i64,
+    last_row: &ExportRow,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE ExportUpload
+        SET parts = ?, bytesUploaded = ?, rowsWritten = ?, lastCommitDate = ?, lastCommitId = ?,
+            updatedAt = NOW()
+        WHERE exportId = ?
+        "#,
+    )
+    .bind(sqlx::types::Json(upload.parts()))
+    .bind(upload.bytes_uploaded() as i64)
+    .bind(rows_written as i32)
+    .bind(last_row.commit_date)
+    .bind(&last_row.id)
+    .bind(export_id)
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
+
+async fn run_export(
+    state: &AppState,
+    export_id: &str,
+    request: &ExportRequest,
+    start: Option<chrono::NaiveDateTime>,
+    end: Option<chrono::NaiveDateTime>,
+) -> Result<()> {
+    let s3 = state
+        .s3
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("S3 is not configured"))?;
+    // The export file may go to a per-request bucket; offloaded messages stay in the default one
+    let target = match &request.bucket {
+        Some(bucket) => s3.with_bucket(bucket),
+        None => s3.clone(),
+    };
+
+    sqlx::query("UPDATE ExportJob SET status = 'PROCESSING' WHERE id = ?")
+        .bind(export_id)
+        .execute(&state.db)
+        .await?;
+
+    // Total for progress reporting only
+    let mut count_query = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM Commit c");
+    push_filters(&mut count_query, request, start, end);
+    let (total,): (i64,) = count_query.build_query_as().fetch_one(&state.db).await?;
+
+    let format = request.format.unwrap_or_default();
+    let saved: Option<SavedUpload> = sqlx::query_as(
+        r#"
+        SELECT fileKey, fileName, uploadId, partSize, parts, bytesUploaded, rowsWritten,
+               lastCommitDate, lastCommitId
+        FROM ExportUpload
+        WHERE exportId = ?
+        "#,
+    )
+    .bind(export_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let (mut upload, key, file_name, mut written, cursor) = match saved {
+        Some(saved) => {
+            tracing::info!(
+                "Resuming export {} after {} rows ({} parts uploaded)",
+                export_id,
+                saved.rows_written,
+                saved.parts.0.len()
+            );
+            let upload = target.resume_multipart(
+                &saved.file_key,
+                &saved.upload_id,
+                saved.part_size as usize,
+                saved.parts.0,
+                saved.bytes_uploaded as u64,
+            );
+            let cursor = saved.last_commit_date.zip(saved.last_commit_id);
+            (upload, saved.file_key, saved.file_name, saved.rows_written as i64, cursor)
+        }
+        None => {
+            let prefix = request.prefix.clone().unwrap_or_else(default_export_prefix);
+            let file_name = format!(
+                "commits-{}.{}",
+                chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+                format.as_str()
+            );
+            let key = format!("{}/{}/{}", prefix.trim_end_matches('/'), export_id, file_name);
+            let upload = target
+                .start_multipart(&key, format.content_type(), export_part_size())
+                .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO ExportUpload (exportId, fileKey, fileName, uploadId, partSize, parts,
+                    bytesUploaded, rowsWritten, updatedAt)
+                VALUES (?, ?, ?, ?, ?, ?, 0, 0, NOW())
+                "#,
+            )
+            .bind(export_id)
+            .bind(&key)
+            .bind(&file_name)
+            .bind(upload.upload_id())
+            .bind(upload.part_size() as i32)
+            .bind(sqlx::types::Json(upload.parts()))
+            .execute(&state.db)
+            .await?;
+            (upload, key, file_name, 0, None)
+        }
+    };
+
+    let mut query = sqlx::QueryBuilder::new(
+        r#"
+        SELECT c.id, c.repositoryId, r.name AS repositoryName, c.sha, c.authorName,
+               c.authorEmail, c.commitDate, c.commitDateOffset, c.messageTitle, c.message, c.messageKey,
+               c.filesChanged, c.codeFilesChanged, c.changedPaths, c.jiraKey, c.jiraUrl,
+               c.isSigned, c.signatureKeyId, c.signatureFingerprint
+        FROM Commit c
+        JOIN Repository r ON r.id = c.repositoryId
+        "#,
+    );
+    push_filters(&mut query, request, start, end);
+    // Skip rows already in uploaded parts (parts always end on a row boundary)
+    if let Some((last_date, last_id)) = &cursor {
+        query
+            .push(" AND (c.commitDate > ")
+            .push_bind(*last_date)
+            .push(" OR (c.commitDate = ")
+            .push_bind(*last_date)
+            .push(" AND c.id > ")
+            .push_bind(last_id.clone())
+            .push("))");
+    }
+    query.push(" ORDER BY c.commitDate, c.id");
+
+    let signature_details = crate::env_flag("EXPORT_SIGNATURE_DETAILS", true);
+    // Parquet writes its footer last, so nothing is uploaded (or checkpointed) until the
+    // local file is complete; an interrupted Parquet export starts over
+    let mut parquet = match format {
+        ExportFormat::Parquet => Some(ParquetFile::create(export_id, signature_details)?),
+        ExportFormat::Ndjson => None,
+    };
+
+    // Stream rows so neither the result set nor the file is ever fully in memory
+    let result: Result<()> = async {
+        let mut rows = query.build_query_as::<ExportRow>().fetch(&state.db);
+        let mut last_progress = 0;
+
+        while let Some(mut row) = rows.try_next().await? {
+            // Offloaded messages are exported in full
+            if let Some(message_key) = &row.message_key {
+                match s3.get_object(message_key).await {
+                    Ok(bytes) => row.message = String::from_utf8_lossy(&bytes).into_owned(),
+                    Err(e) => tracing::warn!("Using truncated message for {}: {}", row.sha, e),
+                }
+            }
+
+            if !signature_details {
+                row.signature_key_id = None;
+                row.signature_fingerprint = None;
+            }
+
+            written += 1;
+            if let Some(parquet) = &mut parquet {
+                parquet.push(row)?;
+            } else {
+                let mut line = serde_json::to_vec(&row)?;
+                line.push(b'\n');
+                if upload.write(&line).await? {
+                    save_checkpoint(state, export_id, &upload, written, &row).await?;
+                }
+            }
+
+            let progress = if total > 0 { (written * 100 / total) as i32 } else { 100 };
+            if progress >= last_progress + 5 {
+                last_progress = progress;
+                sqlx::query("UPDATE ExportJob SET progress = ? WHERE id = ?")
+                    .bind(progress.min(99))
+                    .bind(export_id)
+                    .execute(&state.db)
+                    .await?;
+            }
+        }
+
+        if let Some(parquet) = parquet.take() {
+            let path = parquet.finish()?;
+            let uploaded = upload_file(&mut upload, &path).await;
+            let _ = tokio::fs::remove_file(&path).await;
+            uploaded?;
+        }
+        Ok(())
+    }
+    .await;
+    if let Some(parquet) = parquet {
+        let _ = std::fs::remove_file(&parquet.path);
+    }
+
+    let clear_saved = sqlx::query("DELETE FROM ExportUpload WHERE exportId = ?").bind(export_id);
+    if let Err(e) = result {
+        upload.abort().await;
+        let _ = clear_saved.execute(&state.db).await;
+        return Err(e);
+    }
+    let row_count = written;
+    let file_size = upload.finish().await?;
+    clear_saved.execute(&state.db).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE ExportJob
+        SET status = 'COMPLETED', progress = 100, fileName = ?, fileKey = ?, fileSize = ?,
+            rowCount = ?, completedAt = NOW()
+        WHERE id = ?
+        "#,
+    )
+    .bind(&file_name)
+    .bind(&key)
+    .bind(file_size.min(i32::MAX as u64) as i32)
+    .bind(row_count as i32)
+    .bind(export_id)
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!("Export {} completed: {} rows, {} bytes", export_id, row_count, file_size);
+    Ok(())
+}
+
+/// Copy a finished local file into a multipart upload
+async fn upload_file(upload: &mut MultipartUpload, path: &std::path::Path) -> Result<()> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut chunk = vec![0; upload.part_size()];
+    loop {
+        let read = file.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        upload.write(&chunk[..read]).await?;
+    }
+}
+
+/// Export rows written as Parquet row groups to a local temporary file
+struct ParquetFile {
+    path: PathBuf,
+    writer: ArrowWriter<std::fs::File>,
+    schema: SchemaRef,
+    rows: Vec<ExportRow>,
+    row_group: usize,
+    signature_details: bool,
+}
+
+impl ParquetFile {
+    fn create(export_id: &str, signature_details: bool) -> Result<Self> {
+        let text = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+        let mut fields = vec![
+            text("id", false),
+            text("repositoryId", false),
+            text("repositoryName", false),
+            text("sha", false),
+            text("authorName", false),
+            text("authorEmail", false),
+            Field::new(
+                "commitDate",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                false,
+            ),
+            Field::new("commitDateOffset", DataType::Int32, false),
+            text("messageTitle", false),
+            text("message", false),
+            Field::new("filesChanged", DataType::Int32, true),
+            Field::new("codeFilesChanged", DataType::Int32, true),
+            text("changedPaths", true),
+            text("jiraKey", true),
+            text("jiraUrl", true),
+            Field::new("isSigned", DataType::Boolean, false),
+        ];
+        if signature_details {
+            fields.push(text("signatureKeyId", true));
+            fields.push(text("signatureFingerprint", true));
+        }
+        let schema = Arc::new(Schema::new(fields));
+
+        let path = std::env::temp_dir().join(format!("export-{}.parquet", export_id));
+        let file = std::fs::File::create(&path)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(properties))?;
+        Ok(Self {
+            path,
+            writer,
+            schema,
+            rows: Vec::new(),
+            row_group: parquet_row_group(),
+            signature_details,
+        })
+    }
+
+    fn push(&mut self, row: ExportRow) -> Result<()> {
+        self.rows.push(row);
+        if self.rows.len() >= self.row_group {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let text = |value: fn(&ExportRow) -> &str| -> ArrayRef {
+            Arc::new(StringArray::from_iter_values(rows.iter().map(value)))
+        };
+        let optional_text = |value: fn(&ExportRow) -> Option<&str>| -> ArrayRef {
+            Arc::new(StringArray::from_iter(rows.iter().map(value)))
+        };
+        let int = |value: fn(&ExportRow) -> Option<i32>| -> ArrayRef {
+            Arc::new(Int32Array::from_iter(rows.iter().map(value)))
+        };
+
+        let mut columns = vec![
+            text(|r| &r.id),
+            text(|r| &r.repository_id),
+            text(|r| &r.repository_name),
+            text(|r| &r.sha),
+            text(|r| &r.author_name),
+            text(|r| &r.author_email),
+            Arc::new(
+                TimestampMicrosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.commit_date.timestamp_micros()),
+                )
+                .with_timezone("UTC"),
+            ),
+            int(|r| Some(r.commit_date_offset)),
+            text(|r| &r.message_title),
+            text(|r| &r.message),
+            int(|r| r.files_changed),
+            int(|r| r.code_files_changed),
+            optional_text(|r| r.changed_paths.as_deref()),
+            optional_text(|r| r.jira_key.as_deref()),
+            optional_text(|r| r.jira_url.as_deref()),
+            Arc::new(BooleanArray::from_iter(rows.iter().map(|r| Some(r.is_signed)))),
+        ];
+        if self.signature_details {
+            columns.push(optional_text(|r| r.signature_key_id.as_deref()));
+            columns.push(optional_text(|r| r.signature_fingerprint.as_deref()));
+        }
+
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), columns)?;
+        self.writer.write(&batch)?;
+        // Close the row group so memory stays bounded by `row_group` rows
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the remaining rows and the footer; returns the file's path
+    fn finish(mut self) -> Result<PathBuf> {
+        self.write_row_group()?;
+        self.writer.close()?;
+        Ok(self.path)
+    }
+}
+
+/// Restart exports interrupted by a shutdown; those with saved upload state resume mid-file
+pub async fn resume_interrupted(state: AppState) {
+    if state.s3.is_none() {
+        return;
+    }
+    let interrupted: Vec<InterruptedExport> = match sqlx::query_as(
+        r#"
+        SELECT id, startDate, endDate, authorEmail, repoIds, bucket, keyPrefix, format
+        FROM ExportJob
+        WHERE status IN ('PENDING', 'PROCESSING')
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Could not look up interrupted exports: {}", e);
+            return;
+        }
+    };
+
+    for export in interrupted {
+        tracing::info!("Restarting interrupted export {}", export.id);
+        let request = ExportRequest {
+            repo_ids: export
+                .repo_ids
+                .as_deref()
+                .and_then(|ids| serde_json::from_str(ids).ok()),
+            author_emails: export
+                .author_email
+                .map(|emails| emails.split(',').map(String::from).collect()),
+            start_date: None,
+            end_date: None,
+            bucket: export.bucket,
+            prefix: export.key_prefix,
+            format: Some(ExportFormat::parse(&export.format)),
+        };
+        spawn_export(
+            state.clone(),
+            state.exports.enqueue(),
+            export.id,
+            request,
+            export.start_date,
+            export.end_date,
+        );
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct InterruptedExport {
+    id: String,
+    start_date: Option<chrono::NaiveDateTime>,
+    end_date: Option<chrono::NaiveDateTime>,
+    author_email: Option<String>,
+    repo_ids: Option<String>,
+    bucket: Option<String>,
+    key_prefix: Option<String>,
+    format: String,
+}
+
+/// Status of an export job
+#[utoipa::path(
+    get,
+    path = "/exports/{id}",
+    tag = "exports",
+    params(("id" = String, Path, description = "Export job id")),
+    responses(
+        (status = 200, body = ExportStatus),
+        (status = 404, description = "Export not found", body = String)
+    )
+)]
+pub async fn get_export(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ExportStatus>, (StatusCode, String)> {
+    let export: Option<ExportStatus> = sqlx::query_as(
+        r#"
+        SELECT id, status, fileName, bucket, format, fileKey, fileSize, rowCount, progress, error,
+               createdAt, completedAt
+        FROM ExportJob
+        WHERE id = ?
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    export
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Export not found".to_string()))
+}
@@ -3,17 +3,430 @@ use chrono::{TimeZone, Utc};
 use git2::{Cred, DiffOptions, FetchOptions, RemoteCallbacks, Repository};
 use std::path::{Path, PathBuf};
 
-use crate::models::ParsedCommit;
+use crate::cancel::CancelToken;
+use crate::coauthors;
+use crate::known_shas::KnownShas;
+use crate::metadata::{self, CommitMetadata};
+use crate::models::{ChangeStatus, FileChange, FileDiff, ParsedCommit};
+use crate::signature::{self, Keyring};
 
+/// Characters of a commit id shown in logs
+const SHORT_SHA_LEN: usize = 8;
+
+/// Commits walked per known-SHA lookup in `parse_commits`
+const KNOWN_SHA_CHUNK: usize = 500;
+
+/// Consecutive commits older than the start date that end a date-bounded walk
+/// (git's `--since` uses the same slop)
+const DATE_BREAK_SLOP: usize = 5;
+
+/// Longest wait before retrying a rate-limited clone/fetch
+const MAX_RATE_LIMIT_DELAY: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Extensions counted as source code when `CODE_EXTENSIONS` is not set
+const DEFAULT_CODE_EXTENSIONS: &[&str] = &[
+    "rs", "ts", "tsx", "js", "jsx", "mjs", "cjs", "py", "go", "java", "kt", "kts", "scala",
+    "swift", "c", "h", "cc", "cpp", "hpp", "cs", "rb", "php", "sql", "sh", "vue", "svelte",
+    "dart", "m", "mm", "lua", "ex", "exs", "erl", "hs", "clj",
+];
+
+/// Normalize a list of extensions (`.RS`, ` rs ` -> `rs`)
+pub fn normalize_extensions<S: AsRef<str>>(extensions: &[S]) -> Vec<String> {
+    extensions
+        .iter()
+        .map(|e| e.as_ref().trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Code extensions from `CODE_EXTENSIONS` (comma-separated), or the built-in list
+pub fn default_code_extensions() -> Vec<String> {
+    match std::env::var("CODE_EXTENSIONS") {
+        Ok(list) if !list.trim().is_empty() => {
+            normalize_extensions(&list.split(',').collect::<Vec<_>>())
+        }
+        _ => normalize_extensions(DEFAULT_CODE_EXTENSIONS),
+    }
+}
+
+#[derive(Clone)]
 pub struct GitProcessor {
     work_dir: PathBuf,
+    /// Layout of clone directories under `work_dir` (`WORK_DIR_TEMPLATE`)
+    dir_template: String,
+    /// Drop remote-tracking refs of branches deleted upstream on fetch (`FETCH_PRUNE`)
+    prune: bool,
+    /// Resolve and TCP-connect to the remote host before cloning/fetching, with this
+    /// timeout (`PRECLONE_CHECK`, `PRECLONE_CHECK_TIMEOUT_MS`)
+    connectivity_check: Option<std::time::Duration>,
+    /// Retries of a clone/fetch the host rejected as rate limited (`RATE_LIMIT_RETRIES`)
+    rate_limit_retries: u32,
+    /// First backoff delay, doubled per retry (`RATE_LIMIT_BACKOFF_MS`)
+    rate_limit_backoff: std::time::Duration,
+    /// Refresh the clone's commit-graph file after each clone/fetch (`COMMIT_GRAPH`)
+    commit_graph: bool,
+    /// Cancellation of the job this processor works for; polled during clones, fetches
+    /// and walks
+    cancel: CancelToken,
+    /// Private key offered to SSH remotes
+    ssh_key: Option<SshKey>,
+}
+
+/// Private key for SSH remotes (`git@host:owner/repo.git`, `ssh://...`)
+#[derive(Clone)]
+pub struct SshKey {
+    pub source: SshKeySource,
+    pub passphrase: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum SshKeySource {
+    /// Key file on the service host
+    Path(PathBuf),
+    /// PEM / OpenSSH key text
+    Pem(String),
+}
+
+impl SshKey {
+    fn credential(&self, username: &str) -> std::result::Result<Cred, git2::Error> {
+        let passphrase = self.passphrase.as_deref();
+        match &self.source {
+            SshKeySource::Path(path) => Cred::ssh_key(username, None, path, passphrase),
+            SshKeySource::Pem(pem) => Cred::ssh_key_from_memory(username, None, pem, passphrase),
+        }
+    }
+}
+
+/// How the root commit (no parents) counts toward churn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RootCommitPolicy {
+    /// Diff against the empty tree: every file in the root commit counts as added
+    #[default]
+    EmptyTree,
+    /// Record zero changed files, keeping a bulk initial import out of churn stats
+    Exclude,
+}
+
+impl RootCommitPolicy {
+    /// `ROOT_COMMIT_POLICY` (`emptyTree` | `exclude`), defaulting to `emptyTree`
+    pub fn from_env() -> Self {
+        match std::env::var("ROOT_COMMIT_POLICY")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "exclude" => Self::Exclude,
+            "" | "emptytree" | "empty_tree" => Self::EmptyTree,
+            other => {
+                tracing::warn!("Unknown ROOT_COMMIT_POLICY {:?}, using emptyTree", other);
+                Self::EmptyTree
+            }
+        }
+    }
+}
+
+/// Which changes a merge commit (more than one parent) is credited with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeDiffStrategy {
+    /// Diff against the first parent: everything the merged branch brought in
+    #[default]
+    FirstParent,
+    /// Like `git diff --cc`: only files that differ from every parent, i.e. conflict
+    /// resolutions and edits made in the merge itself
+    Combined,
+    /// Record zero changed files for merges, leaving the changes to the merged commits
+    Skip,
+}
+
+impl MergeDiffStrategy {
+    /// `MERGE_DIFF_STRATEGY` (`firstParent` | `combined` | `skip`), defaulting to `firstParent`
+    pub fn from_env() -> Self {
+        match std::env::var("MERGE_DIFF_STRATEGY")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "combined" => Self::Combined,
+            "skip" => Self::Skip,
+            "" | "firstparent" | "first_parent" => Self::FirstParent,
+            other => {
+                tracing::warn!("Unknown MERGE_DIFF_STRATEGY {:?}, using firstParent", other);
+                Self::FirstParent
+            }
+        }
+    }
+}
+
+/// What happens to commits dated in the future (a committer's clock was wrong)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FutureCommitPolicy {
+    /// Analyze them under their recorded date, flagged with `futureDated` metadata
+    #[default]
+    Keep,
+    /// Leave them out of the analysis
+    Skip,
+}
+
+impl FutureCommitPolicy {
+    /// `FUTURE_COMMIT_POLICY` (`keep` | `skip`), defaulting to `keep`
+    pub fn from_env() -> Self {
+        match std::env::var("FUTURE_COMMIT_POLICY")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "skip" => Self::Skip,
+            "" | "keep" => Self::Keep,
+            other => {
+                tracing::warn!("Unknown FUTURE_COMMIT_POLICY {:?}, using keep", other);
+                Self::Keep
+            }
+        }
+    }
+}
+
+/// Rename/copy detection applied to each commit's diff (`git diff -M -C`)
+#[derive(Debug, Clone, Copy)]
+pub struct RenameDetection {
+    /// Similarity (0-100) at which a delete + add pair counts as a rename or copy
+    pub threshold: u16,
+    /// Also pair added files with a modified file they were copied from
+    pub copies: bool,
+}
+
+/// Options controlling which commits `parse_commits` returns and how they are shaped
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Repository the commits belong to; seeds the deterministic commit ids
+    pub repository_id: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub author_filter: Option<String>,
+    pub all_branches: bool,
+    /// Rewrite `\r\n` and lone `\r` in commit messages to `\n`
+    pub normalize_line_endings: bool,
+    /// Lowercase extensions (without dot) counted toward `code_files_changed`
+    pub code_extensions: Vec<String>,
+    /// Only keep authors whose email is in one of these domains (subdomains included)
+    pub email_domains: Vec<String>,
+    /// Drop authors whose email is in one of these domains
+    pub exclude_email_domains: Vec<String>,
+    /// Verify signatures against this keyring and flag signer/author mismatches
+    pub signature_keyring: Option<Keyring>,
+    /// Store metadata only; leave file counts, paths and patch-id empty
+    pub skip_diffs: bool,
+    /// Lowercase author emails before filtering and storage (the original is kept)
+    pub lowercase_author_emails: bool,
+    /// Leave `linguist-generated`/`linguist-vendored` files out of file counts
+    pub exclude_linguist_paths: bool,
+    /// Per-commit budget for content diffing (patch-id); None means unlimited
+    pub diff_timeout: Option<std::time::Duration>,
+    /// Keep each changed file's unified diff text within these limits; None keeps none
+    pub diff_text: Option<DiffTextLimits>,
+    /// Only keep commits changing at least this many (counted) files
+    pub min_files: Option<usize>,
+    /// Only keep commits changing at most this many (counted) files
+    pub max_files: Option<usize>,
+    /// Only keep signed (true) or unsigned (false) commits; None keeps both
+    pub signed: Option<bool>,
+    /// Store at most this many changed paths per commit (counts still cover them all)
+    pub max_stored_paths: Option<usize>,
+    /// Whether the root commit is diffed against the empty tree or counted as zero churn
+    pub root_commit_policy: RootCommitPolicy,
+    /// Which changes merge commits are credited with
+    pub merge_diffs: MergeDiffStrategy,
+    /// Handling of commits dated more than `future_tolerance` after the analysis runs
+    pub future_commits: FutureCommitPolicy,
+    /// Clock skew tolerated in both directions: ahead of now before a commit counts as
+    /// future-dated, and behind `start_date` before it can end the walk
+    pub future_tolerance: chrono::Duration,
+    /// Collapse renamed (and optionally copied) files into one change; None reports
+    /// a rename as a delete plus an add
+    pub renames: Option<RenameDetection>,
+    /// Unchanged lines around each hunk; None uses git's default of 3. Patch-ids are
+    /// only comparable between jobs using the same value.
+    pub context_lines: Option<u32>,
+    /// Walk only commits reachable from semver release tags, instead of a branch
+    pub release_only: bool,
+    /// Keep only squash-merge commits: single-parent commits on the branch's first-parent
+    /// line whose message names a pull/merge request (or `git merge --squash` output)
+    pub squash_merges_only: bool,
+    /// Follow only first parents (`git log --first-parent`): the mainline's own commits
+    /// and merge commits, without the commits of merged branches
+    pub first_parent: bool,
+    /// Only return commits touching a path matching one of these git pathspecs
+    /// (a directory like `services/api` or a glob like `services/*/src/*.rs`); their
+    /// stored paths and line counts are limited to the matching files too. Empty
+    /// keeps every commit.
+    pub path_filters: Vec<String>,
+    /// Title stored for commits with an empty message
+    pub empty_title_placeholder: String,
+    /// Commit message convention; non-merge commits whose message doesn't match are
+    /// flagged as template violations. None leaves commits unchecked.
+    pub commit_template: Option<regex::Regex>,
+    /// Branch tip analyzed by an earlier run; its history is hidden from the walk so only
+    /// newer commits are visited. Ignored unless it is an ancestor of the current tip
+    /// (a force push or a different branch falls back to a full walk).
+    pub since_sha: Option<String>,
+}
+
+impl ParseOptions {
+    /// Whether any option leaves commits of the walked history out, so a run can't
+    /// vouch for everything behind its tip (and incremental runs must not skip it)
+    pub fn excludes_commits(&self) -> bool {
+        self.start_date.is_some()
+            || self.end_date.is_some()
+            || self
+                .author_filter
+                .as_deref()
+                .is_some_and(|f| !f.trim().is_empty())
+            || !self.email_domains.is_empty()
+            || !self.exclude_email_domains.is_empty()
+            || self.signed.is_some()
+            || self.min_files.is_some()
+            || self.max_files.is_some()
+            || self.future_commits == FutureCommitPolicy::Skip
+            || self.squash_merges_only
+            || self.first_parent
+            || !self.path_filters.is_empty()
+    }
+}
+
+/// Clone directory layout from `WORK_DIR_TEMPLATE` (default `{hash}`)
+fn work_dir_template() -> String {
+    std::env::var("WORK_DIR_TEMPLATE")
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| "{hash}".to_string())
+}
+
+/// Refuse a `WORK_DIR_TEMPLATE` that can give two repositories the same clone
+/// directory (one without `{hash}` or `{repo}`), which would fetch one into the other
+pub fn validate_work_dir_template() -> Result<()> {
+    let template = work_dir_template();
+    if !template.contains("{hash}") && !template.contains("{repo}") {
+        anyhow::bail!(
+            "WORK_DIR_TEMPLATE {:?} must contain {{hash}} or {{repo}} so each repository gets its own directory",
+            template
+        );
+    }
+    Ok(())
 }
 
 impl GitProcessor {
     pub fn new(work_dir: &str) -> Self {
         Self {
             work_dir: PathBuf::from(work_dir),
+            dir_template: work_dir_template(),
+            prune: crate::env_flag("FETCH_PRUNE", true),
+            connectivity_check: crate::env_flag("PRECLONE_CHECK", false).then(|| {
+                let ms = std::env::var("PRECLONE_CHECK_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(3000);
+                std::time::Duration::from_millis(ms)
+            }),
+            rate_limit_retries: std::env::var("RATE_LIMIT_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            rate_limit_backoff: std::time::Duration::from_millis(
+                std::env::var("RATE_LIMIT_BACKOFF_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(5000),
+            ),
+            commit_graph: crate::env_flag("COMMIT_GRAPH", false),
+            cancel: CancelToken::default(),
+            ssh_key: None,
+        }
+    }
+
+    /// Authenticate to SSH remotes with `key`
+    pub fn with_ssh_key(mut self, key: Option<SshKey>) -> Self {
+        self.ssh_key = key;
+        self
+    }
+
+    /// Callbacks answering the remote's credential requests: the SSH key when the
+    /// transport asks for one, otherwise the token as a userpass credential
+    fn auth_callbacks(&self, token: Option<&str>) -> RemoteCallbacks<'static> {
+        let mut callbacks = RemoteCallbacks::new();
+        if token.is_none() && self.ssh_key.is_none() {
+            return callbacks;
+        }
+        let token = token.map(String::from);
+        let ssh_key = self.ssh_key.clone();
+        let mut ssh_attempts = 0;
+        callbacks.credentials(move |_url, username_from_url, allowed_types| {
+            let username = username_from_url.unwrap_or("git");
+            if allowed_types.contains(git2::CredentialType::USERNAME) {
+                return Cred::username(username);
+            }
+            if let Some(key) = ssh_key
+                .as_ref()
+                .filter(|_| allowed_types.contains(git2::CredentialType::SSH_KEY))
+            {
+                // libgit2 asks again after a rejected key; stop instead of looping
+                ssh_attempts += 1;
+                if ssh_attempts > 1 {
+                    return Err(git2::Error::from_str("SSH key was rejected by the remote"));
+                }
+                return key.credential(username);
+            }
+            match &token {
+                // For GitHub PATs, use "x-access-token" as username and token as password
+                // This works for both classic PATs and fine-grained tokens
+                Some(token) => Cred::userpass_plaintext("x-access-token", token),
+                None => Err(git2::Error::from_str("No credentials for this remote")),
+            }
+        });
+        callbacks
+    }
+
+    /// Stop clones, fetches and walks once `cancel` is cancelled
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Token that stops this processor's clones, fetches and walks
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    /// Err([`crate::cancel::Cancelled`]) once the job was cancelled
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.cancel.check()
+    }
+
+    /// Clone directory for a URL, rendered from the work-dir template
+    fn repo_dir(&self, url: &str) -> PathBuf {
+        let hash = format!("{:x}", md5::compute(url));
+        let (host, org, repo) = url_parts(url);
+
+        let rendered = self
+            .dir_template
+            .replace("{host}", &host)
+            .replace("{org}", &org)
+            .replace("{repo}", &repo)
+            .replace("{hash}", &hash);
+
+        // Sanitize every segment so nothing can escape the work dir
+        let mut path = self.work_dir.clone();
+        for segment in rendered.split('/').filter(|s| !s.is_empty()) {
+            path.push(sanitize_path_component(segment));
         }
+        if path == self.work_dir {
+            path.push(hash);
+        }
+        path
     }
 
     /// Clone a repository or fetch updates if already cloned
@@ -24,21 +437,364 @@ impl GitProcessor {
         token: Option<&str>,
         all_branches: bool,
     ) -> Result<PathBuf> {
-        // Generate a unique directory name from URL
-        let repo_hash = format!("{:x}", md5::compute(url));
-        let repo_path = self.work_dir.join(&repo_hash);
+        let repo_path = self.repo_dir(url);
+        if let Some(timeout) = self.connectivity_check {
+            check_connectivity(url, timeout)?;
+        }
+
+        let mut attempt = 0;
+        loop {
+            let existed = repo_path.exists();
+            let result = if existed {
+                tracing::info!("Repository exists, fetching updates: {}", url);
+                self.fetch_updates(&repo_path, branch, token, all_branches)
+            } else {
+                tracing::info!("Cloning repository: {}", url);
+                self.clone_repo(url, &repo_path, branch, token)
+            };
+            match result {
+                // Aborted from the transfer progress callback
+                Err(_) if self.cancel.is_cancelled() => {
+                    if !existed && repo_path.exists() {
+                        let _ = std::fs::remove_dir_all(&repo_path);
+                    }
+                    return Err(crate::cancel::Cancelled.into());
+                }
+                Err(e) if attempt < self.rate_limit_retries && is_rate_limited(&e) => {
+                    let delay = retry_after(&e)
+                        .unwrap_or(self.rate_limit_backoff.saturating_mul(1 << attempt.min(16)))
+                        .min(MAX_RATE_LIMIT_DELAY);
+                    attempt += 1;
+                    tracing::warn!(
+                        "Host rate limited the {} ({}); retry {}/{} in {:?}",
+                        if existed { "fetch" } else { "clone" },
+                        e,
+                        attempt,
+                        self.rate_limit_retries,
+                        delay
+                    );
+                    // Don't leave a half-written clone to be "fetched" next time
+                    if !existed && repo_path.exists() {
+                        let _ = std::fs::remove_dir_all(&repo_path);
+                    }
+                    // Runs on a git pool thread, so sleeping blocks no async work
+                    std::thread::sleep(delay);
+                }
+                result => {
+                    result?;
+                    if self.commit_graph {
+                        // Only speeds up later walks; a clone without one still works
+                        if let Err(e) = write_commit_graph(&repo_path) {
+                            tracing::warn!("Could not write commit-graph: {:#}", e);
+                        }
+                    }
+                    return Ok(repo_path);
+                }
+            }
+        }
+    }
+
+    /// Ask the remote which branch its HEAD points at (e.g. `main`)
+    pub fn detect_default_branch(&self, url: &str, token: Option<&str>) -> Result<String> {
+        if let Some(timeout) = self.connectivity_check {
+            check_connectivity(url, timeout)?;
+        }
+        let callbacks = self.auth_callbacks(token);
+
+        let mut remote = git2::Remote::create_detached(url)?;
+        let connection = remote
+            .connect_auth(git2::Direction::Fetch, Some(callbacks), None)
+            .context("Failed to connect to remote")?;
+        let head = connection
+            .default_branch()
+            .context("Remote did not advertise a default branch")?;
+        let head = head.as_str().context("Default branch name is not UTF-8")?;
+
+        Ok(head.trim_start_matches("refs/heads/").to_string())
+    }
+
+    /// Default branch recorded in a local clone (`refs/remotes/origin/HEAD`)
+    pub fn cloned_default_branch(&self, repo_path: &Path) -> Result<Option<String>> {
+        let repo = open_repository(repo_path)?;
+        let Ok(reference) = repo.find_reference("refs/remotes/origin/HEAD") else {
+            return Ok(None);
+        };
+        Ok(reference
+            .symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/remotes/origin/"))
+            .map(String::from))
+    }
+
+    /// Throw away the local clone of `url` and clone it again from scratch
+    pub fn reclone(&self, url: &str, branch: &str, token: Option<&str>) -> Result<PathBuf> {
+        let repo_path = self.repo_dir(url);
 
         if repo_path.exists() {
-            tracing::info!("Repository exists, fetching updates: {}", url);
-            self.fetch_updates(&repo_path, branch, token, all_branches)?;
-        } else {
-            tracing::info!("Cloning repository: {}", url);
-            self.clone_repo(url, &repo_path, branch, token)?;
+            std::fs::remove_dir_all(&repo_path)
+                .context("Failed to remove corrupt clone directory")?;
         }
+        tracing::info!("Re-cloning repository: {}", url);
+        self.clone_repo(url, &repo_path, branch, token)?;
 
         Ok(repo_path)
     }
 
+    /// Clone a repository from a `git bundle` file into a job-specific directory
+    pub fn clone_from_bundle(&self, bundle_path: &Path, job_id: &str) -> Result<PathBuf> {
+        let repo_path = self
+            .work_dir
+            .join(format!("bundle-{}", sanitize_path_component(job_id)));
+        if repo_path.exists() {
+            std::fs::remove_dir_all(&repo_path)
+                .context("Failed to remove stale bundle clone directory")?;
+        }
+
+        // libgit2 cannot read bundles, so defer to the git CLI
+        tracing::info!("Cloning from bundle: {:?}", bundle_path);
+        let output = std::process::Command::new("git")
+            .arg("clone")
+            .arg("--quiet")
+            .arg(bundle_path)
+            .arg(&repo_path)
+            .output()
+            .context("Failed to run git clone (is git installed?)")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to clone from bundle: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(repo_path)
+    }
+
+    /// Unified diff of a commit against its first parent from the local clone of `url`,
+    /// cut at a line boundary once it would exceed `max_bytes`. None when there is no
+    /// clone or the commit is not in it.
+    pub fn commit_patch(
+        &self,
+        url: &str,
+        sha: &str,
+        max_bytes: usize,
+    ) -> Result<Option<CommitPatch>> {
+        let repo_path = self.repo_dir(url);
+        if !repo_path.exists() {
+            return Ok(None);
+        }
+        let repo = open_repository(&repo_path)?;
+        let Ok(commit) = git2::Oid::from_str(sha).and_then(|oid| repo.find_commit(oid)) else {
+            return Ok(None);
+        };
+        let tree = commit.tree()?;
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+        let mut patch = Vec::new();
+        let mut truncated = false;
+        let printed = diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+            let prefix = matches!(line.origin(), '+' | '-' | ' ') as usize;
+            if patch.len() + prefix + line.content().len() > max_bytes {
+                truncated = true;
+                return false;
+            }
+            if prefix == 1 {
+                patch.push(line.origin() as u8);
+            }
+            patch.extend_from_slice(line.content());
+            true
+        });
+        // Stopping early surfaces as a user-abort error
+        if !truncated {
+            printed?;
+        }
+
+        Ok(Some(CommitPatch {
+            patch: String::from_utf8_lossy(&patch).into_owned(),
+            truncated,
+        }))
+    }
+
+    /// Line-by-line authorship of `path` at the tip of `branch` in the local clone of
+    /// `url`. Files over `max_bytes` are refused before blaming, which reads the file's
+    /// whole history. `cached` is consulted once the tip is known, so results for an
+    /// unchanged branch are reused.
+    pub fn blame_file(
+        &self,
+        url: &str,
+        branch: &str,
+        path: &str,
+        max_bytes: u64,
+        cached: impl FnOnce(&str) -> Option<std::sync::Arc<FileBlame>>,
+    ) -> Result<BlameOutcome> {
+        let repo_path = self.repo_dir(url);
+        if !repo_path.exists() {
+            return Ok(BlameOutcome::NoClone);
+        }
+        let repo = open_repository(&repo_path)?;
+        let tip = branch_tip(&repo, branch)?.peel_to_commit()?;
+        let tip_sha = tip.id().to_string();
+        let Ok(entry) = tip.tree()?.get_path(Path::new(path)) else {
+            return Ok(BlameOutcome::NotFound);
+        };
+        let Ok(blob) = repo.find_blob(entry.id()) else {
+            return Ok(BlameOutcome::NotFound);
+        };
+        if blob.size() as u64 > max_bytes {
+            return Ok(BlameOutcome::TooLarge(blob.size() as u64));
+        }
+        if blob.is_binary() {
+            return Ok(BlameOutcome::Binary);
+        }
+        if let Some(blame) = cached(&tip_sha) {
+            return Ok(BlameOutcome::Blamed(blame));
+        }
+
+        let mut options = git2::BlameOptions::new();
+        options.newest_commit(tip.id());
+        let blame = repo.blame_file(Path::new(path), Some(&mut options))?;
+        let lowercase_emails = crate::env_flag("NORMALIZE_AUTHOR_EMAILS", true);
+        let hunks = blame
+            .iter()
+            .map(|hunk| {
+                let signature = hunk.final_signature();
+                let email = signature.email().unwrap_or("");
+                BlameHunk {
+                    start_line: hunk.final_start_line(),
+                    lines: hunk.lines_in_hunk(),
+                    sha: hunk.final_commit_id().to_string(),
+                    author_name: signature.name().unwrap_or("").to_string(),
+                    author_email: if lowercase_emails {
+                        email.to_lowercase()
+                    } else {
+                        email.to_string()
+                    },
+                    commit_date: Utc.timestamp_opt(signature.when().seconds(), 0).unwrap(),
+                }
+            })
+            .collect();
+
+        Ok(BlameOutcome::Blamed(std::sync::Arc::new(FileBlame {
+            commit: tip_sha,
+            hunks,
+        })))
+    }
+
+    /// Commits between two analyzed tips in the local clone of `url`: those reachable
+    /// from `to` but not `from` (added), and the reverse (removed by a force push),
+    /// newest first and at most `limit` of each. None when there is no clone.
+    pub fn tip_delta(
+        &self,
+        url: &str,
+        from: &str,
+        to: &str,
+        limit: usize,
+    ) -> Result<Option<TipDelta>> {
+        let repo_path = self.repo_dir(url);
+        if !repo_path.exists() {
+            return Ok(None);
+        }
+        let repo = open_repository(&repo_path)?;
+        let to = git2::Oid::from_str(to)?;
+        repo.find_commit(to)
+            .context("The current tip is no longer in the clone")?;
+        // A force push can leave the old tip unreachable, and a later gc removes it
+        let from = git2::Oid::from_str(from)
+            .ok()
+            .filter(|oid| repo.find_commit(*oid).is_ok());
+
+        let mut delta = TipDelta {
+            previous_tip_found: from.is_some(),
+            ..Default::default()
+        };
+        let Some(from) = from else {
+            return Ok(Some(delta));
+        };
+        delta.added = commits_between(&repo, to, from, limit, &mut delta.added_total)?;
+        delta.removed = commits_between(&repo, from, to, limit, &mut delta.removed_total)?;
+        Ok(Some(delta))
+    }
+
+    /// Directory a job's tarball is extracted into (removed as a whole afterwards)
+    pub fn tarball_dir(&self, job_id: &str) -> PathBuf {
+        // Job ids come from the caller; keep them from naming a path outside the work dir
+        self.work_dir
+            .join(format!("tarball-{}", sanitize_path_component(job_id)))
+    }
+
+    /// Unpack a `.tar.gz` of a repository (working copy with `.git`, or bare) into
+    /// [`Self::tarball_dir`] and return the repository root inside it
+    pub fn extract_tarball(
+        &self,
+        tarball_path: &Path,
+        job_id: &str,
+        max_bytes: u64,
+    ) -> Result<PathBuf> {
+        let extract_dir = self.tarball_dir(job_id);
+        if extract_dir.exists() {
+            std::fs::remove_dir_all(&extract_dir)
+                .context("Failed to remove stale tarball directory")?;
+        }
+        std::fs::create_dir_all(&extract_dir).context("Failed to create tarball directory")?;
+
+        tracing::info!("Extracting tarball: {:?}", tarball_path);
+        let file = std::fs::File::open(tarball_path).context("Failed to open tarball")?;
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        archive.set_preserve_permissions(false);
+        let mut extracted = 0u64;
+        for entry in archive.entries().context("Not a gzip-compressed tar archive")? {
+            let mut entry = entry.context("Corrupt tarball entry")?;
+            // A link could point `.git` or `objects` at another repository in the work dir
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                anyhow::bail!(
+                    "Tarball entry {} is a link; links are not accepted",
+                    entry.path().map(|p| p.display().to_string()).unwrap_or_default()
+                );
+            }
+            extracted += entry.header().size().unwrap_or(0);
+            if extracted > max_bytes {
+                anyhow::bail!("Tarball expands to more than {} bytes", max_bytes);
+            }
+            // unpack_in refuses entries that would land outside the directory
+            entry
+                .unpack_in(&extract_dir)
+                .context("Failed to extract tarball entry")?;
+        }
+
+        // Archives usually wrap the repository in one top-level directory
+        let mut candidates = vec![extract_dir.clone()];
+        let children: Vec<PathBuf> = std::fs::read_dir(&extract_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        if let [only] = children.as_slice() {
+            candidates.push(only.clone());
+        }
+        let root = extract_dir
+            .canonicalize()
+            .context("Failed to resolve tarball directory")?;
+        for candidate in candidates {
+            let dot_git = std::fs::symlink_metadata(candidate.join(".git")).ok();
+            // A gitfile (`gitdir: <path>`) would redirect to a repository outside the archive
+            if dot_git.as_ref().is_some_and(|meta| !meta.is_dir()) {
+                anyhow::bail!("Tarball repository has a .git file instead of a .git directory");
+            }
+            let bare = candidate.join("HEAD").is_file() && candidate.join("objects").is_dir();
+            if dot_git.is_some() || bare {
+                let candidate = candidate
+                    .canonicalize()
+                    .context("Failed to resolve tarball repository")?;
+                if !candidate.starts_with(&root) {
+                    anyhow::bail!("Tarball repository resolves outside its extraction directory");
+                }
+                open_repository(&candidate)?;
+                return Ok(candidate);
+            }
+        }
+        anyhow::bail!("Tarball does not contain a git repository")
+    }
+
     fn clone_repo(
         &self,
         url: &str,
@@ -46,19 +802,20 @@ impl GitProcessor {
         branch: &str,
         token: Option<&str>,
     ) -> Result<()> {
-        let mut callbacks = RemoteCallbacks::new();
-
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create clone directory")?;
+        }
+        if self.ssh_key.is_some() {
+            tracing::info!("Using SSH key for authentication");
+        }
         if let Some(token) = token {
             tracing::info!("Using token for authentication (length: {})", token.len());
-            let token = token.to_string();
-            callbacks.credentials(move |_url, _username_from_url, _allowed_types| {
-                // For GitHub PATs, use "x-access-token" as username and token as password
-                // This works for both classic PATs and fine-grained tokens
-                Cred::userpass_plaintext("x-access-token", &token)
-            });
-        } else {
+        } else if self.ssh_key.is_none() {
             tracing::warn!("No token provided, cloning without authentication");
         }
+        let mut callbacks = self.auth_callbacks(token);
+        let cancel = self.cancel.clone();
+        callbacks.transfer_progress(move |_| !cancel.is_cancelled());
 
         let mut fetch_options = FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
@@ -80,18 +837,20 @@ impl GitProcessor {
     }
 
     fn fetch_updates(&self, path: &Path, branch: &str, token: Option<&str>, all_branches: bool) -> Result<()> {
-        let repo = Repository::open(path).context("Failed to open repository")?;
+        let repo = open_repository(path)?;
 
-        let mut callbacks = RemoteCallbacks::new();
-        if let Some(token) = token {
-            let token = token.to_string();
-            callbacks.credentials(move |_url, _username_from_url, _allowed_types| {
-                Cred::userpass_plaintext("x-access-token", &token)
-            });
-        }
+        let mut callbacks = self.auth_callbacks(token);
+        let cancel = self.cancel.clone();
+        callbacks.transfer_progress(move |_| !cancel.is_cancelled());
 
         let mut fetch_options = FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
+        // Release-only analysis walks from tags, so keep all of them current
+        fetch_options.download_tags(git2::AutotagOption::All);
+        // Otherwise all-branches walks keep counting branches that were deleted upstream
+        if self.prune {
+            fetch_options.prune(git2::FetchPrune::On);
+        }
 
         let mut remote = repo.find_remote("origin").context("Failed to find remote")?;
         
@@ -120,6 +879,18 @@ impl GitProcessor {
             ))?;
         }
 
+        // Keep origin/HEAD on the remote's current default branch (still known after the fetch)
+        if let Some(head) = remote.default_branch().ok().and_then(|h| h.as_str().map(String::from)) {
+            if let Some(name) = head.strip_prefix("refs/heads/") {
+                let _ = repo.reference_symbolic(
+                    "refs/remotes/origin/HEAD",
+                    &format!("refs/remotes/origin/{}", name),
+                    true,
+                    "Update remote HEAD",
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -128,107 +899,387 @@ impl GitProcessor {
         &self,
         repo_path: &Path,
         branch: &str,
-        start_date: Option<&str>,
-        end_date: Option<&str>,
-        author_filter: Option<&str>,
-        all_branches: bool,
-    ) -> Result<Vec<ParsedCommit>> {
-        let repo = Repository::open(repo_path).context("Failed to open repository")?;
+        options: &ParseOptions,
+        known_shas: Option<&KnownShas>,
+    ) -> Result<ParsedCommits> {
+        let repo = open_repository(repo_path)?;
         
         // Find the branch reference
         let mut revwalk = repo.revwalk()?;
-        
-        if all_branches {
+        // Commit -> first release tag containing it, in release-only mode
+        let mut release_of: std::collections::HashMap<git2::Oid, String> =
+            std::collections::HashMap::new();
+        // Branch tip walked from, when a single branch is analyzed
+        let mut tip = None;
+
+        if options.release_only {
+            let releases = release_tags(&repo)?;
+            if releases.is_empty() {
+                anyhow::bail!("No semver release tags (e.g. v1.2.3) found to walk from");
+            }
+            for (_, _, oid) in &releases {
+                revwalk.push(*oid)?;
+            }
+            release_of = first_releases(&repo, &releases)?;
+            tracing::info!("Walking commits reachable from {} release tags", releases.len());
+        } else if options.all_branches {
             // Walk all branches (local and remote)
             revwalk.push_glob("refs/heads/*")?;
             revwalk.push_glob("refs/remotes/origin/*")?;
             tracing::info!("Walking commits from all branches");
         } else {
-            // Try to find the branch in remote refs first (origin/branch), then local
-            let branch_ref = format!("refs/remotes/origin/{}", branch);
-            let local_ref = format!("refs/heads/{}", branch);
-            
-            if let Ok(reference) = repo.find_reference(&branch_ref) {
-                let oid = reference.target().context("Failed to get branch target")?;
-                revwalk.push(oid)?;
-                tracing::info!("Walking commits from remote branch: {}", branch_ref);
-            } else if let Ok(reference) = repo.find_reference(&local_ref) {
-                let oid = reference.target().context("Failed to get branch target")?;
+            // Try to find the branch in remote refs first (origin/branch), then local, then
+            // a tag; annotated tags point at a tag object, so peel to the commit either way
+            let candidates = [
+                format!("refs/remotes/origin/{}", branch),
+                format!("refs/heads/{}", branch),
+                format!("refs/tags/{}", branch),
+            ];
+            let found = candidates.iter().find_map(|name| {
+                repo.find_reference(name)
+                    .ok()
+                    .map(|reference| (name, reference))
+            });
+
+            if let Some((name, reference)) = found {
+                let oid = reference
+                    .peel_to_commit()
+                    .with_context(|| format!("{} does not point to a commit", name))?
+                    .id();
                 revwalk.push(oid)?;
-                tracing::info!("Walking commits from local branch: {}", local_ref);
+                tip = Some(oid);
+                tracing::info!("Walking commits from {}", name);
             } else {
                 // Fallback to HEAD
                 tracing::warn!("Branch '{}' not found, falling back to HEAD", branch);
                 revwalk.push_head()?;
+                tip = repo
+                    .head()
+                    .ok()
+                    .and_then(|head| head.peel_to_commit().ok())
+                    .map(|commit| commit.id());
             }
         }
+
+        // Squash merges land on the mainline; commits only reachable through merged
+        // branches are feature work even when their titles mention a PR
+        let mainline = match (options.squash_merges_only, tip) {
+            (true, Some(tip)) => Some(first_parent_line(&repo, tip)?),
+            _ => None,
+        };
+
+        if let (Some(since), Some(tip)) = (options.since_sha.as_deref(), tip) {
+            hide_analyzed(&repo, &mut revwalk, since, tip)?;
+        }
+        if options.first_parent {
+            revwalk.simplify_first_parent()?;
+            tracing::info!("Following first parents only");
+        }
         
         revwalk.set_sorting(git2::Sort::TIME)?;
 
-        let start_ts = start_date
+        let start_ts = options
+            .start_date
+            .as_deref()
             .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
             .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp());
 
-        let end_ts = end_date
+        let end_ts = options
+            .end_date
+            .as_deref()
             .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
             .map(|d| d.and_hms_opt(23, 59, 59).unwrap().and_utc().timestamp());
 
+        let future_after = (Utc::now() + options.future_tolerance).timestamp();
+
         let mut commits = Vec::new();
+        let mut skipped_known = 0usize;
+        let mut in_range = 0usize;
+        let mut author_matched = 0usize;
+        let mut future_dated = 0usize;
+        // Clock skew tolerated before a commit counts as older than the start date
+        let skew = options.future_tolerance.num_seconds();
+        let mut older_in_a_row = 0usize;
 
-        for oid in revwalk.flatten() {
-            let commit = repo.find_commit(oid)?;
-            let time = commit.time().seconds();
+        // The walk is newest first, but only approximately: a commit from a skewed clock
+        // can sit next to commits months apart. So like `git log --since`, the walk only
+        // ends after a run of commits older than the start date (beyond the skew
+        // tolerance); single out-of-order or future-dated commits are just skipped.
+        // Commits are taken in chunks so known SHAs are looked up once per chunk (one
+        // query for bloom filter hits) before any of them is diffed.
+        let mut oids = revwalk.flatten();
+        'walk: loop {
+            let chunk: Vec<git2::Oid> = oids.by_ref().take(KNOWN_SHA_CHUNK).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            let stored = match known_shas {
+                Some(known) => known.stored(&chunk)?,
+                None => std::collections::HashSet::new(),
+            };
+            for oid in chunk {
+                self.cancel.check()?;
+                let commit = repo.find_commit(oid)?;
+                let time = commit.time().seconds();
+
+                if let Some(start) = start_ts {
+                    if time < start - skew {
+                        older_in_a_row += 1;
+                        if older_in_a_row >= DATE_BREAK_SLOP {
+                            break 'walk;
+                        }
+                    } else {
+                        older_in_a_row = 0;
+                    }
+                }
+
+                let is_future = time > future_after;
+                if is_future {
+                    future_dated += 1;
+                    if options.future_commits == FutureCommitPolicy::Skip {
+                        continue;
+                    }
+                }
 
-            // Filter by date range
-            if let Some(start) = start_ts {
-                if time < start {
+                // Filter by date range
+                if start_ts.is_some_and(|start| time < start) || end_ts.is_some_and(|end| time > end) {
                     continue;
                 }
-            }
-            if let Some(end) = end_ts {
-                if time > end {
-                    break; // Commits are sorted by time, so we can break early
+                in_range += 1;
+
+                // Filter by author (supports comma-separated list)
+                let author = commit.author();
+                let author_email_original = author.email().unwrap_or("");
+                let author_name = author.name().unwrap_or("");
+                // Emails are case-insensitive by convention; fold them into one author bucket
+                let author_email = if options.lowercase_author_emails {
+                    author_email_original.to_lowercase()
+                } else {
+                    author_email_original.to_string()
+                };
+                let author_email = author_email.as_str();
+
+                if let Some(filter) = options.author_filter.as_deref() {
+                    // Split by comma for multiple authors
+                    let filters: Vec<String> = filter
+                        .split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| {
+                            if options.lowercase_author_emails {
+                                s.to_lowercase()
+                            } else {
+                                s.to_string()
+                            }
+                        })
+                        .collect();
+                    if !filters.is_empty() {
+                        let matches = filters.iter().any(|f| {
+                            author_email == f || author_email.contains(f.as_str()) || author_name.contains(f.as_str())
+                        });
+                        if !matches {
+                            continue;
+                        }
+                    }
                 }
-            }
 
-            // Filter by author (supports comma-separated list)
-            let author = commit.author();
-            let author_email = author.email().unwrap_or("");
-            let author_name = author.name().unwrap_or("");
+                // Filter by author email domain (e.g. only @company.com, or everyone but contractors)
+                if !options.email_domains.is_empty()
+                    && !email_in_domains(author_email, &options.email_domains)
+                {
+                    continue;
+                }
+                if email_in_domains(author_email, &options.exclude_email_domains) {
+                    continue;
+                }
+                author_matched += 1;
+
+                if options
+                    .signed
+                    .is_some_and(|signed| signature::is_signed(&repo, oid) != signed)
+                {
+                    continue;
+                }
+
+                // Already stored: skip before the expensive diff
+                if stored.contains(&oid) {
+                    skipped_known += 1;
+                    continue;
+                }
+
+                if !options.path_filters.is_empty()
+                    && !touches_paths(&repo, &commit, &options.path_filters)?
+                {
+                    continue;
+                }
+
+                let mut message = commit.message().unwrap_or("").to_string();
+                if options.normalize_line_endings {
+                    message = normalize_line_endings(&message);
+                }
+                // Like git's subject: the first non-blank line
+                let no_message = message.trim().is_empty();
+                let message_title = match message.lines().map(str::trim).find(|l| !l.is_empty()) {
+                    Some(title) => title.to_string(),
+                    None => options.empty_title_placeholder.clone(),
+                };
+
+                let squash_merge = if options.squash_merges_only {
+                    let on_mainline = mainline.as_ref().is_none_or(|line| line.contains(&oid));
+                    match metadata::squash_merge(&message_title, &message) {
+                        Some(squash) if on_mainline && commit.parent_count() == 1 => Some(squash),
+                        _ => continue,
+                    }
+                } else {
+                    None
+                };
+
+                // Get changed file paths (simple list, no diffs)
+                let root_excluded = commit.parent_count() == 0
+                    && options.root_commit_policy == RootCommitPolicy::Exclude;
+                let merge_skipped =
+                    commit.parent_count() > 1 && options.merge_diffs == MergeDiffStrategy::Skip;
+                let diff_summary = if options.skip_diffs {
+                    DiffSummary::default()
+                } else if root_excluded || merge_skipped {
+                    DiffSummary::zero_churn()
+                } else {
+                    self.get_changed_paths(&repo, &commit, options)?
+                };
 
-            if let Some(filter) = author_filter {
-                // Split by comma for multiple authors
-                let filters: Vec<&str> = filter.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
-                if !filters.is_empty() {
-                    let matches = filters.iter().any(|f| {
-                        author_email == *f || author_email.contains(f) || author_name.contains(f)
-                    });
-                    if !matches {
+                // Size filters need the diff; commits with unknown counts (skipped diffs) pass
+                if let Some(files) = diff_summary.files_changed {
+                    if options.min_files.is_some_and(|min| files < min)
+                        || options.max_files.is_some_and(|max| files > max)
+                    {
                         continue;
                     }
                 }
-            }
 
-            let message = commit.message().unwrap_or("").to_string();
-            let message_title = message.lines().next().unwrap_or("").to_string();
+                let signature =
+                    signature::inspect(&repo, oid, author_email, options.signature_keyring.as_ref());
 
-            // Get changed file paths (simple list, no diffs)
-            let (files_changed, changed_paths) = self.get_changed_paths(&repo, &commit)?;
+                let co_authors =
+                    coauthors::extract_co_authors(&message, options.lowercase_author_emails);
 
-            commits.push(ParsedCommit {
-                id: uuid::Uuid::new_v4().to_string(),
-                sha: oid.to_string(),
-                author_name: author_name.to_string(),
-                author_email: author_email.to_string(),
-                commit_date: Utc.timestamp_opt(time, 0).unwrap(),
-                message,
-                message_title,
-                files_changed,
-                changed_paths,
-            });
+                let mut commit_metadata = CommitMetadata::default();
+                let trailers = metadata::extract_trailers(&message);
+                if !trailers.is_empty() {
+                    commit_metadata.set(metadata::TRAILERS, &trailers);
+                }
+                if !diff_summary.excluded_paths.is_empty() {
+                    commit_metadata.set(metadata::EXCLUDED_PATHS, &diff_summary.excluded_paths);
+                }
+                if let Some(release) = release_of.get(&oid) {
+                    commit_metadata.set(metadata::RELEASE, release);
+                }
+                if root_excluded && !options.skip_diffs {
+                    commit_metadata.set(metadata::ROOT_COMMIT_EXCLUDED, &true);
+                }
+                if merge_skipped && !options.skip_diffs {
+                    commit_metadata.set(metadata::MERGE_DIFF_SKIPPED, &true);
+                }
+                if let Some(pull_request) = squash_merge.and_then(|squash| squash.pull_request) {
+                    commit_metadata.set(metadata::PULL_REQUEST, &pull_request);
+                }
+                if is_future {
+                    commit_metadata.set(metadata::FUTURE_DATED, &true);
+                }
+                if let Some(total) = diff_summary.truncated_from {
+                    commit_metadata.set(metadata::CHANGED_PATHS_TOTAL, &total);
+                }
+
+                // Merge commits carry git's generated message, so they are never checked
+                let template_violation = options
+                    .commit_template
+                    .as_ref()
+                    .filter(|_| commit.parent_count() <= 1)
+                    .map(|template| !template.is_match(&message));
+
+                commits.push(ParsedCommit {
+                    id: ParsedCommit::stable_id(&options.repository_id, &oid.to_string()),
+                    repository_id: options.repository_id.clone(),
+                    sha: oid.to_string(),
+                    author_name: author_name.to_string(),
+                    author_email: author_email.to_string(),
+                    author_email_original: author_email_original.to_string(),
+                    commit_date: Utc.timestamp_opt(time, 0).unwrap(),
+                    commit_date_offset: commit.time().offset_minutes(),
+                    message,
+                    message_title,
+                    no_message,
+                    files_changed: diff_summary.files_changed,
+                    code_files_changed: diff_summary.code_files_changed,
+                    insertions: diff_summary.insertions,
+                    deletions: diff_summary.deletions,
+                    changed_paths: diff_summary.changed_paths,
+                    file_changes: diff_summary.file_changes,
+                    patch_id: diff_summary.patch_id,
+                    diff_timeout: diff_summary.diff_timeout,
+                    largest_file_path: diff_summary.largest_file.as_ref().map(|(p, _)| p.clone()),
+                    largest_file_size: diff_summary.largest_file.as_ref().map(|(_, s)| *s),
+                    is_signed: signature.signed,
+                    signature_verified: signature.verified,
+                    signature_identity: signature.identity,
+                    signature_mismatch: signature.mismatch,
+                    signature_key_id: signature.key_id,
+                    signature_fingerprint: signature.fingerprint,
+                    template_violation,
+                    parent_shas: commit.parent_ids().map(|id| id.to_string()).collect(),
+                    co_authors,
+                    metadata: commit_metadata,
+                    file_diffs: diff_summary.file_diffs,
+                });
+            }
+        }
+
+        if skipped_known > 0 {
+            tracing::info!("Skipped {} already-analyzed commits during walk", skipped_known);
+        }
+        if future_dated > 0 {
+            tracing::warn!(
+                "{} commits are dated in the future (clock skew); {}",
+                future_dated,
+                match options.future_commits {
+                    FutureCommitPolicy::Keep => "analyzed under their recorded dates",
+                    FutureCommitPolicy::Skip => "left out",
+                }
+            );
         }
 
-        Ok(commits)
+        Ok(ParsedCommits {
+            commits,
+            tip: tip.map(|oid| oid.to_string()),
+            in_range,
+            author_matched,
+        })
+    }
+
+    /// Count files (blobs, including submodule links) in the tree at the tip of the analyzed branch
+    pub fn count_head_files(
+        &self,
+        repo_path: &Path,
+        branch: &str,
+        exclude_linguist: bool,
+    ) -> Result<usize> {
+        let repo = open_repository(repo_path)?;
+        let tree = branch_tip(&repo, branch)?.peel_to_tree()?;
+
+        let mut files = 0;
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if matches!(
+                entry.kind(),
+                Some(git2::ObjectType::Blob) | Some(git2::ObjectType::Commit)
+            ) {
+                let path = format!("{}{}", dir, entry.name().unwrap_or(""));
+                if !(exclude_linguist && is_linguist_excluded(&repo, &path)) {
+                    files += 1;
+                }
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+
+        Ok(files)
     }
 
     /// Get list of changed file paths for a commit
@@ -236,20 +1287,52 @@ impl GitProcessor {
         &self,
         repo: &Repository,
         commit: &git2::Commit,
-    ) -> Result<(usize, String)> {
+        options: &ParseOptions,
+    ) -> Result<DiffSummary> {
         let tree = commit.tree()?;
         let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
 
         let mut opts = DiffOptions::new();
         opts.include_untracked(false);
+        if let Some(lines) = options.context_lines {
+            opts.context_lines(lines);
+        }
+        // A combined diff is the first-parent diff limited to the files every parent
+        // disagrees with
+        if commit.parent_count() > 1 && options.merge_diffs == MergeDiffStrategy::Combined {
+            let paths = combined_diff_paths(repo, commit, &tree, &options.path_filters)?;
+            if paths.is_empty() {
+                return Ok(DiffSummary::zero_churn());
+            }
+            for path in &paths {
+                opts.pathspec(path);
+            }
+            opts.disable_pathspec_match(true);
+        } else {
+            for pattern in &options.path_filters {
+                opts.pathspec(pattern);
+            }
+        }
 
-        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))?;
-
-        let stats = diff.stats()?;
-        let files_changed = stats.files_changed();
+        let mut diff =
+            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))?;
+        if let Some(renames) = options.renames {
+            let mut find = git2::DiffFindOptions::new();
+            find.renames(true)
+                .rename_threshold(renames.threshold)
+                .copies(renames.copies)
+                .copy_threshold(renames.threshold);
+            diff.find_similar(Some(&mut find))?;
+        }
 
-        // Collect file paths
+        // Collect file paths; generated/vendored ones are kept but not counted
         let mut paths: Vec<String> = Vec::new();
+        let mut file_changes: Vec<FileChange> = Vec::new();
+        let mut excluded_paths: Vec<String> = Vec::new();
+        let mut files_changed = 0;
+        let mut code_files_changed = 0;
+        let mut largest_file: Option<(String, u64)> = None;
+        let odb = repo.odb()?;
 
         diff.foreach(
             &mut |delta, _progress| {
@@ -260,6 +1343,37 @@ impl GitProcessor {
                     .map(|p| p.to_string_lossy().to_string())
                     .unwrap_or_else(|| "unknown".to_string());
 
+                if options.exclude_linguist_paths && is_linguist_excluded(repo, &path) {
+                    excluded_paths.push(path.clone());
+                } else {
+                    files_changed += 1;
+                    if is_code_path(&path, &options.code_extensions) {
+                        code_files_changed += 1;
+                    }
+                }
+                // Blob sizes come from the object header, without inflating the content
+                let new_file = delta.new_file();
+                if delta.status() != git2::Delta::Deleted
+                    && new_file.mode() != git2::FileMode::Commit
+                    && !new_file.id().is_zero()
+                {
+                    if let Ok((size, _)) = odb.read_header(new_file.id()) {
+                        let size = size as u64;
+                        if largest_file.as_ref().is_none_or(|(_, largest)| size > *largest) {
+                            largest_file = Some((path.clone(), size));
+                        }
+                    }
+                }
+                let status = ChangeStatus::from_delta(delta.status());
+                let old_path = matches!(status, ChangeStatus::Renamed | ChangeStatus::Copied)
+                    .then(|| delta.old_file().path())
+                    .flatten()
+                    .map(|p| p.to_string_lossy().to_string());
+                file_changes.push(FileChange {
+                    status,
+                    path: path.clone(),
+                    old_path,
+                });
                 paths.push(path);
                 true
             },
@@ -268,10 +1382,930 @@ impl GitProcessor {
             None,
         )?;
 
+        // Generating content patches is the expensive part; give up on it for this commit
+        // once the budget is spent and keep only the name-level counts above. One print
+        // pass yields line counts, diff text and the patch-id, with the deadline checked
+        // on every line (a single file's diff still runs to completion once started)
+        let deadline = options
+            .diff_timeout
+            .map(|budget| std::time::Instant::now() + budget);
+        let mut diff_timeout = false;
+        let excluded: std::collections::HashSet<&str> =
+            excluded_paths.iter().map(String::as_str).collect();
+        let (mut insertions, mut deletions) = (0, 0);
+        let mut file_diffs = Vec::new();
+        let mut diff_text_left = options.diff_text.map_or(0, |limits| limits.per_commit);
+        let mut patch_id = PatchIdHasher::default();
+        // Path, excluded flag and diff text of the file being printed
+        let mut current: Option<(String, bool, PendingDiff)> = None;
+        let keep_text = options.diff_text.is_some();
+        let finish_file = |current: Option<(String, bool, PendingDiff)>,
+                           diff_text_left: &mut usize,
+                           file_diffs: &mut Vec<FileDiff>| {
+            if let Some((path, false, pending)) = current {
+                if keep_text {
+                    *diff_text_left -= pending.max.min(pending.bytes);
+                    file_diffs.push(pending.into_file_diff(path));
+                }
+            }
+        };
+        let printed = diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
+            if deadline.is_some_and(|deadline| std::time::Instant::now() > deadline) {
+                diff_timeout = true;
+                return false;
+            }
+            if line.origin() == 'F' {
+                finish_file(current.take(), &mut diff_text_left, &mut file_diffs);
+                let path = delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                // Generated/vendored files stay out of line counts as they do file counts
+                let is_excluded = excluded.contains(path.as_str());
+                let max = options
+                    .diff_text
+                    .map_or(0, |limits| limits.per_file.min(diff_text_left));
+                current = Some((path, is_excluded, PendingDiff::new(max)));
+            }
+            patch_id.line(&line);
+            if let Some((_, false, pending)) = current.as_mut() {
+                match line.origin() {
+                    '+' => insertions += 1,
+                    '-' => deletions += 1,
+                    _ => {}
+                }
+                if keep_text {
+                    pending.push(&line);
+                }
+            }
+            true
+        });
+        if let Err(e) = printed {
+            if !diff_timeout {
+                return Err(e.into());
+            }
+        }
+        finish_file(current.take(), &mut diff_text_left, &mut file_diffs);
+        if diff_timeout {
+            file_diffs.clear();
+            tracing::warn!(
+                "Diff of commit {} exceeded {:?}; storing name-only counts",
+                commit.id(),
+                options.diff_timeout.unwrap_or_default()
+            );
+        }
+
+        // Bulk imports can touch tens of thousands of files; keep the first ones only
+        let total_paths = paths.len();
+        let truncated_from = match options.max_stored_paths {
+            Some(max) if total_paths > max => {
+                paths.truncate(max);
+                file_changes.truncate(max);
+                excluded_paths.truncate(max);
+                Some(total_paths)
+            }
+            _ => None,
+        };
+
         // Join paths with newline for storage
         let changed_paths = paths.join("\n");
 
-        Ok((files_changed, changed_paths))
+        // An empty diff has no meaningful identity to deduplicate on
+        let patch_id = (total_paths > 0 && !diff_timeout).then(|| patch_id.finish().to_string());
+
+        Ok(DiffSummary {
+            files_changed: Some(files_changed),
+            code_files_changed: Some(code_files_changed),
+            changed_paths: Some(changed_paths),
+            file_changes,
+            truncated_from,
+            excluded_paths,
+            insertions: (!diff_timeout).then_some(insertions),
+            deletions: (!diff_timeout).then_some(deletions),
+            patch_id,
+            diff_timeout,
+            largest_file,
+            file_diffs,
+        })
+    }
+}
+
+/// Paths a merge commit changed relative to each of its parents (the files
+/// `git diff --cc` shows), limited to `path_filters`; name-only diffs, so cheap even
+/// for large merges
+fn combined_diff_paths(
+    repo: &Repository,
+    commit: &git2::Commit,
+    tree: &git2::Tree,
+    path_filters: &[String],
+) -> Result<Vec<String>> {
+    let mut common: Option<std::collections::HashSet<String>> = None;
+    for parent in commit.parents() {
+        let mut opts = pathspec_options(path_filters);
+        let diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(tree), Some(&mut opts))?;
+        let changed: std::collections::HashSet<String> = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        common = Some(match common {
+            Some(common) => common.intersection(&changed).cloned().collect(),
+            None => changed,
+        });
+    }
+    let mut paths: Vec<String> = common.unwrap_or_default().into_iter().collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Diff options limited to `patterns` (git pathspecs); no patterns match every path
+fn pathspec_options(patterns: &[String]) -> DiffOptions {
+    let mut opts = DiffOptions::new();
+    for pattern in patterns {
+        opts.pathspec(pattern);
+    }
+    opts
+}
+
+/// Whether a commit changes a path matching `patterns` relative to its first parent
+/// (root commits: relative to the empty tree). Name-only, so it stays cheap when
+/// most commits of a monorepo are filtered out.
+fn touches_paths(repo: &Repository, commit: &git2::Commit, patterns: &[String]) -> Result<bool> {
+    let tree = commit.tree()?;
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let mut opts = pathspec_options(patterns);
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))?;
+    Ok(diff.deltas().len() > 0)
+}
+
+/// Check that a file is a self-contained git bundle we can clone from.
+/// Returns the refs advertised by the bundle.
+pub fn validate_bundle(bundle_path: &Path) -> Result<Vec<String>> {
+    use std::io::{BufRead, BufReader};
+
+    let file = std::fs::File::open(bundle_path).context("Failed to open bundle")?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+
+    reader.read_until(b'\n', &mut line)?;
+    let signature = String::from_utf8_lossy(&line);
+    if signature != "# v2 git bundle\n" && signature != "# v3 git bundle\n" {
+        anyhow::bail!("Not a git bundle (missing v2/v3 bundle signature)");
+    }
+
+    // Header lines run until the first blank line; the packfile follows
+    let mut refs = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            anyhow::bail!("Truncated bundle header");
+        }
+        let header = String::from_utf8_lossy(&line);
+        let header = header.trim_end_matches('\n');
+        if header.is_empty() {
+            break;
+        }
+        if let Some(capability) = header.strip_prefix('@') {
+            // v3 capability line; only the object format matters here
+            if let Some(name) = capability.strip_prefix("object-format=") {
+                let format = ObjectFormat::from_name(name)?;
+                if format != ObjectFormat::Sha1 {
+                    anyhow::bail!(
+                        "Bundle uses {:?} object ids, which the bundled libgit2 cannot read yet",
+                        format
+                    );
+                }
+            }
+            continue;
+        }
+        if header.starts_with('-') {
+            anyhow::bail!("Bundle has prerequisite commits; only complete bundles can be analyzed");
+        }
+        if let Some((_oid, refname)) = header.split_once(' ') {
+            refs.push(refname.to_string());
+        }
+    }
+
+    if refs.is_empty() {
+        anyhow::bail!("Bundle does not contain any refs");
+    }
+
+    Ok(refs)
+}
+
+/// `major.minor.patch` with an optional pre-release, parsed from a tag like `v1.2.3-rc.1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseVersion {
+    core: (u64, u64, u64),
+    pre: Option<String>,
+}
+
+impl ReleaseVersion {
+    pub fn parse(tag: &str) -> Option<Self> {
+        let version = tag.strip_prefix('v').unwrap_or(tag);
+        // Build metadata does not affect precedence
+        let version = version.split('+').next()?;
+        let (core, pre) = match version.split_once('-') {
+            Some((_, pre)) if pre.split('.').any(str::is_empty) => return None,
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (version, None),
+        };
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let major = parts.next()??;
+        let minor = parts.next()??;
+        let patch = parts.next()??;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            core: (major, minor, patch),
+            pre,
+        })
+    }
+}
+
+impl Ord for ReleaseVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // A pre-release sorts before the release itself
+        self.core.cmp(&other.core).then_with(|| match (&self.pre, &other.pre) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (Some(a), Some(b)) => compare_pre_release(a, b),
+        })
+    }
+}
+
+/// Semver pre-release precedence: dot-separated identifiers compared in turn, numeric
+/// ones as numbers and below alphanumeric ones; more identifiers win a tie
+fn compare_pre_release(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let numeric = |id: &str| id.bytes().all(|b| b.is_ascii_digit());
+    let mut a_ids = a.split('.');
+    let mut b_ids = b.split('.');
+    loop {
+        let ordering = match (a_ids.next(), b_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (numeric(a), numeric(b)) {
+                // Longer digit strings are bigger numbers, without overflowing a u64
+                (true, true) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl PartialOrd for ReleaseVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Commits on the first-parent chain from `tip` (the branch's own history, without
+/// commits brought in by merges)
+fn first_parent_line(
+    repo: &Repository,
+    tip: git2::Oid,
+) -> Result<std::collections::HashSet<git2::Oid>> {
+    let mut line = std::collections::HashSet::new();
+    let mut commit = repo.find_commit(tip)?;
+    loop {
+        line.insert(commit.id());
+        match commit.parent(0) {
+            Ok(parent) => commit = parent,
+            Err(_) => break,
+        }
+    }
+    Ok(line)
+}
+
+/// Semver-named tags with the commit each points at, oldest version first
+fn release_tags(repo: &Repository) -> Result<Vec<(ReleaseVersion, String, git2::Oid)>> {
+    let mut releases = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Some(version) = ReleaseVersion::parse(name) else {
+            continue;
+        };
+        let commit = repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit());
+        match commit {
+            Ok(commit) => releases.push((version, name.to_string(), commit.id())),
+            Err(e) => tracing::warn!("Skipping tag {}: {}", name, e.message()),
+        }
+    }
+    releases.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(releases)
+}
+
+/// The first release (in version order) that contains each commit
+fn first_releases(
+    repo: &Repository,
+    releases: &[(ReleaseVersion, String, git2::Oid)],
+) -> Result<std::collections::HashMap<git2::Oid, String>> {
+    let mut release_of = std::collections::HashMap::new();
+    for (i, (_, name, oid)) in releases.iter().enumerate() {
+        let mut walk = repo.revwalk()?;
+        walk.push(*oid)?;
+        for (_, _, earlier) in &releases[..i] {
+            walk.hide(*earlier)?;
+        }
+        for commit in walk.flatten() {
+            release_of.entry(commit).or_insert_with(|| name.clone());
+        }
+    }
+    Ok(release_of)
+}
+
+/// Whether `.gitattributes` marks a path `linguist-generated` or `linguist-vendored`
+fn is_linguist_excluded(repo: &Repository, path: &str) -> bool {
+    ["linguist-generated", "linguist-vendored"].iter().any(|attr| {
+        let value = repo
+            .get_attr(Path::new(path), attr, git2::AttrCheckFlags::FILE_THEN_INDEX)
+            .ok()
+            .flatten();
+        match git2::AttrValue::from_string(value) {
+            git2::AttrValue::True => true,
+            git2::AttrValue::String(v) => v.eq_ignore_ascii_case("true"),
+            _ => false,
+        }
+    })
+}
+
+/// Hash function a repository names its objects with (`extensions.objectFormat`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            other => anyhow::bail!("Unknown object format '{}'", other),
+        }
+    }
+}
+
+/// Object format of a local clone, read from its config (SHA-1 when unset)
+pub fn object_format(repo_path: &Path) -> Result<ObjectFormat> {
+    let config_path = [repo_path.join(".git").join("config"), repo_path.join("config")]
+        .into_iter()
+        .find(|path| path.is_file());
+    let Some(config_path) = config_path else {
+        return Ok(ObjectFormat::Sha1);
+    };
+    let config = git2::Config::open(&config_path).context("Failed to read repository config")?;
+    match config.get_string("extensions.objectformat") {
+        Ok(name) => ObjectFormat::from_name(&name),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(ObjectFormat::Sha1),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Open a local clone, failing clearly on object formats libgit2 cannot read
+/// (it otherwise reports an opaque "unsupported extension" error)
+fn open_repository(repo_path: &Path) -> Result<Repository> {
+    let format = object_format(repo_path)?;
+    if format != ObjectFormat::Sha1 {
+        anyhow::bail!(
+            "Repository uses {:?} object ids, which the bundled libgit2 cannot read yet",
+            format
+        );
+    }
+    Repository::open(repo_path).context("Failed to open repository")
+}
+
+/// Abbreviated commit id for logs, for SHA-1 and SHA-256 ids alike
+pub fn short_sha(sha: &str) -> &str {
+    sha.get(..SHORT_SHA_LEN).unwrap_or(sha)
+}
+
+/// Whether a clone/fetch failed because the host is throttling us (HTTP 429, or
+/// the "rate limit" messages hosts send over the smart protocol)
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    static RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| {
+        regex::Regex::new(r"(?i)\b429\b|too many requests|rate limit")
+            .expect("valid rate limit regex")
+    });
+    error.chain().any(|cause| re.is_match(&cause.to_string()))
+}
+
+/// Delay a host asked for in its error text (`Retry-After: 30`, "retry after 30 seconds").
+/// libgit2 doesn't expose response headers, so this only helps when the host echoes it.
+fn retry_after(error: &anyhow::Error) -> Option<std::time::Duration> {
+    static RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| {
+        regex::Regex::new(r"(?i)retry[- ]after:?\s*(\d+)").expect("valid retry-after regex")
+    });
+    error.chain().find_map(|cause| {
+        re.captures(&cause.to_string())
+            .and_then(|caps| caps[1].parse().ok())
+            .map(std::time::Duration::from_secs)
+    })
+}
+
+/// Whether an error was caused by an object missing from the local clone
+/// (a corrupt or partial clone), as opposed to a bad request or DB failure
+pub fn is_missing_object(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<git2::Error>().is_some_and(|e| {
+            e.code() == git2::ErrorCode::NotFound && e.class() == git2::ErrorClass::Odb
+        })
+    })
+}
+
+/// Hide history an earlier run already walked, when `since` is still on the branch
+fn hide_analyzed(
+    repo: &Repository,
+    revwalk: &mut git2::Revwalk<'_>,
+    since: &str,
+    tip: git2::Oid,
+) -> Result<()> {
+    let still_on_branch = git2::Oid::from_str(since).ok().filter(|&oid| {
+        repo.find_commit(oid).is_ok()
+            && (oid == tip || repo.graph_descendant_of(tip, oid).unwrap_or(false))
+    });
+    match still_on_branch {
+        Some(oid) => {
+            revwalk.hide(oid)?;
+            tracing::info!("Incremental walk: skipping history up to {}", since);
+        }
+        None => tracing::warn!(
+            "Last analyzed commit {} is no longer on the branch, walking the full history",
+            since
+        ),
+    }
+    Ok(())
+}
+
+/// Commits returned by `parse_commits`, with counts to tell an empty range from
+/// author filters that matched nothing
+pub struct ParsedCommits {
+    pub commits: Vec<ParsedCommit>,
+    /// Branch tip the walk started from, when a single branch was analyzed
+    pub tip: Option<String>,
+    /// Commits inside the date range, before any author filter
+    pub in_range: usize,
+    /// Commits that also passed the author and email-domain filters
+    pub author_matched: usize,
+}
+
+impl ParsedCommits {
+    /// Author filters were given and excluded every commit of a non-empty range
+    pub fn author_filter_matched_nothing(&self, options: &ParseOptions) -> bool {
+        let filtered = options
+            .author_filter
+            .as_deref()
+            .is_some_and(|f| !f.trim().is_empty())
+            || !options.email_domains.is_empty()
+            || !options.exclude_email_domains.is_empty();
+        filtered && self.in_range > 0 && self.author_matched == 0
+    }
+}
+
+/// Write (or extend) the clone's commit-graph, which stores parents, dates and
+/// generation numbers of every reachable commit so revwalks need not inflate commit
+/// objects. libgit2 reads the file but cannot write it, so this runs the git CLI.
+fn write_commit_graph(repo_path: &Path) -> Result<()> {
+    let started = std::time::Instant::now();
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .args(["commit-graph", "write", "--reachable", "--split", "--no-progress"])
+        .output()
+        .context("Failed to run git commit-graph (is git installed?)")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git commit-graph write failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    open_repository(repo_path)?
+        .config()?
+        .set_bool("core.commitGraph", true)?;
+    tracing::info!("Updated commit-graph in {:?}", started.elapsed());
+    Ok(())
+}
+
+/// Commits reachable from `tip` but not from `hidden`, newest first; the first `limit`
+/// are returned and `total` counts them all
+fn commits_between(
+    repo: &Repository,
+    tip: git2::Oid,
+    hidden: git2::Oid,
+    limit: usize,
+    total: &mut usize,
+) -> Result<Vec<DeltaCommit>> {
+    let mut walk = repo.revwalk()?;
+    walk.push(tip)?;
+    walk.hide(hidden)?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    let mut commits = Vec::new();
+    for oid in walk {
+        let oid = oid?;
+        *total += 1;
+        if commits.len() >= limit {
+            continue;
+        }
+        let commit = repo.find_commit(oid)?;
+        let author = commit.author();
+        commits.push(DeltaCommit {
+            sha: oid.to_string(),
+            title: commit.summary().unwrap_or("").to_string(),
+            author_name: author.name().unwrap_or("").to_string(),
+            author_email: author.email().unwrap_or("").to_string(),
+            commit_date: Utc.timestamp_opt(commit.time().seconds(), 0).unwrap(),
+        });
+    }
+    Ok(commits)
+}
+
+/// Tip of `branch` in a clone: the remote-tracking branch, a local one, a tag, or HEAD
+fn branch_tip<'r>(repo: &'r Repository, branch: &str) -> Result<git2::Object<'r>> {
+    [
+        format!("refs/remotes/origin/{}", branch),
+        format!("refs/heads/{}", branch),
+        format!("refs/tags/{}", branch),
+        "HEAD".to_string(),
+    ]
+    .iter()
+    .find_map(|name| repo.revparse_single(name).ok())
+    .with_context(|| format!("No branch {} or HEAD in the clone", branch))
+}
+
+/// How history moved between two analyzed tips
+#[derive(Debug, Default)]
+pub struct TipDelta {
+    /// The earlier tip still exists; without it nothing can be compared
+    pub previous_tip_found: bool,
+    pub added: Vec<DeltaCommit>,
+    pub added_total: usize,
+    pub removed: Vec<DeltaCommit>,
+    pub removed_total: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaCommit {
+    pub sha: String,
+    pub title: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub commit_date: chrono::DateTime<Utc>,
+}
+
+/// Consecutive lines of a file last changed by the same commit
+#[derive(Debug, Clone)]
+pub struct BlameHunk {
+    /// 1-based first line of the hunk in the file at the tip
+    pub start_line: usize,
+    pub lines: usize,
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub commit_date: chrono::DateTime<Utc>,
+}
+
+/// Authorship of every line of a file at one commit
+#[derive(Debug)]
+pub struct FileBlame {
+    /// Branch tip the file was blamed at
+    pub commit: String,
+    pub hunks: Vec<BlameHunk>,
+}
+
+pub enum BlameOutcome {
+    Blamed(std::sync::Arc<FileBlame>),
+    /// No local clone of the repository
+    NoClone,
+    /// The path is not a file at the branch tip
+    NotFound,
+    /// The file is larger than the limit (its size in bytes)
+    TooLarge(u64),
+    Binary,
+}
+
+/// Size limits of the per-file diff text kept for a commit
+#[derive(Debug, Clone, Copy)]
+pub struct DiffTextLimits {
+    /// Longest diff kept per file, in bytes; longer ones are cut
+    pub per_file: usize,
+    /// Diff bytes kept per commit; files past it keep only their size
+    pub per_commit: usize,
+}
+
+/// First `max` bytes of `bytes` as text, cut back to a line end where there is one
+fn truncate_patch(bytes: &[u8], max: usize) -> String {
+    let cut = &bytes[..bytes.len().min(max)];
+    let cut = match cut.iter().rposition(|&b| b == b'\n') {
+        Some(end) if cut.len() < bytes.len() => &cut[..=end],
+        _ => cut,
+    };
+    String::from_utf8_lossy(cut).into_owned()
+}
+
+/// Diff text of one file as it is printed, keeping only what fits its size limit
+struct PendingDiff {
+    text: Vec<u8>,
+    bytes: usize,
+    max: usize,
+}
+
+impl PendingDiff {
+    fn new(max: usize) -> Self {
+        Self {
+            text: Vec::new(),
+            bytes: 0,
+            max,
+        }
+    }
+
+    /// Append a line the way `Patch::to_buf` renders it
+    fn push(&mut self, line: &git2::DiffLine) {
+        let origin = line.origin();
+        let prefix = matches!(origin, '+' | '-' | ' ') as usize;
+        self.bytes += prefix + line.content().len();
+        // One byte past the limit is enough for `truncate_patch` to see the cut
+        if self.text.len() > self.max {
+            return;
+        }
+        if prefix == 1 {
+            self.text.push(origin as u8);
+        }
+        self.text.extend_from_slice(line.content());
+    }
+
+    fn into_file_diff(self, path: String) -> FileDiff {
+        FileDiff {
+            path,
+            patch: truncate_patch(&self.text, self.max),
+            bytes: self.bytes,
+            truncated: self.bytes > self.max,
+        }
+    }
+}
+
+/// `git_diff_patchid` computed from the lines of a `DiffFormat::Patch` print, so it
+/// can share that pass: index lines and hunk headers are left out, whitespace is
+/// stripped and the SHA-1 of each file is summed into the id, as libgit2 does
+#[derive(Default)]
+struct PatchIdHasher {
+    result: [u8; 20],
+    file: sha1::Sha1,
+    started: bool,
+}
+
+impl PatchIdHasher {
+    fn line(&mut self, line: &git2::DiffLine) {
+        use sha1::Digest;
+
+        let origin = line.origin();
+        if matches!(origin, 'H' | '=' | '>' | '<') {
+            return;
+        }
+        let mut bytes = Vec::with_capacity(line.content().len() + 1);
+        if matches!(origin, '+' | '-' | ' ') {
+            bytes.push(origin as u8);
+        }
+        if origin == 'F' {
+            if self.started {
+                self.flush();
+            }
+            self.started = true;
+            for header in line.content().split_inclusive(|&b| b == b'\n') {
+                if !header.starts_with(b"index ") {
+                    bytes.extend_from_slice(header);
+                }
+            }
+        } else {
+            bytes.extend_from_slice(line.content());
+        }
+        bytes.retain(|b| !matches!(b, b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r'));
+        self.file.update(&bytes);
+    }
+
+    /// Add the current file's hash into the id, byte-wise with carry
+    fn flush(&mut self) {
+        use sha1::Digest;
+
+        let hash = std::mem::take(&mut self.file).finalize();
+        let mut carry = 0u16;
+        for (sum, byte) in self.result.iter_mut().zip(hash.iter()) {
+            carry += *sum as u16 + *byte as u16;
+            *sum = carry as u8;
+            carry >>= 8;
+        }
+    }
+
+    fn finish(mut self) -> git2::Oid {
+        self.flush();
+        git2::Oid::from_bytes(&self.result).expect("20-byte patch-id")
+    }
+}
+
+/// Patch text of a single commit, possibly cut short
+pub struct CommitPatch {
+    pub patch: String,
+    /// The patch was longer than the size limit and ends early
+    pub truncated: bool,
+}
+
+/// Result of diffing a commit against its parent (all empty when diffs are skipped)
+#[derive(Default)]
+struct DiffSummary {
+    files_changed: Option<usize>,
+    code_files_changed: Option<usize>,
+    changed_paths: Option<String>,
+    /// Status and source path of each entry of `changed_paths`
+    file_changes: Vec<FileChange>,
+    /// Number of changed paths when `changed_paths` was cut to `max_stored_paths`
+    truncated_from: Option<usize>,
+    /// Changed paths marked generated or vendored in `.gitattributes`
+    excluded_paths: Vec<String>,
+    /// Lines added and removed outside excluded paths; None when content diffing
+    /// was skipped or timed out
+    insertions: Option<usize>,
+    deletions: Option<usize>,
+    /// Stable hash of the normalized diff, shared by cherry-picks and rebased copies
+    patch_id: Option<String>,
+    /// Content diffing ran out of time; only name-level counts are set
+    diff_timeout: bool,
+    /// Biggest blob added or modified by the commit (path, bytes)
+    largest_file: Option<(String, u64)>,
+    /// Unified diff text per file, when requested
+    file_diffs: Vec<FileDiff>,
+}
+
+impl DiffSummary {
+    /// Counts of zero with no paths, for commits kept out of churn
+    fn zero_churn() -> Self {
+        Self {
+            files_changed: Some(0),
+            code_files_changed: Some(0),
+            changed_paths: Some(String::new()),
+            insertions: Some(0),
+            deletions: Some(0),
+            ..Self::default()
+        }
+    }
+}
+
+/// Normalize domain filters (`@Company.com ` -> `company.com`)
+pub fn normalize_domains<S: AsRef<str>>(domains: &[S]) -> Vec<String> {
+    domains
+        .iter()
+        .map(|d| d.as_ref().trim().trim_start_matches('@').to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+/// Whether an email's domain equals, or is a subdomain of, one of the given domains
+fn email_in_domains(email: &str, domains: &[String]) -> bool {
+    let Some((_, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    let domain = domain.trim_end_matches('>').to_ascii_lowercase();
+    domains.iter().any(|d| {
+        domain == *d
+            || domain
+                .strip_suffix(d.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+/// Whether a path's extension is in the configured code extension list
+fn is_code_path(path: &str, code_extensions: &[String]) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| code_extensions.contains(&ext))
+}
+
+/// Convert CRLF and lone CR line endings to LF
+fn normalize_line_endings(message: &str) -> String {
+    if !message.contains('\r') {
+        return message.to_string();
+    }
+    message.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Host and port a remote URL connects to; None for local paths and `file://` URLs
+fn remote_endpoint(url: &str) -> Option<(String, u16)> {
+    match url::Url::parse(url) {
+        Ok(parsed) if parsed.has_host() => {
+            let port = parsed.port().or(match parsed.scheme() {
+                "https" => Some(443),
+                "http" => Some(80),
+                "ssh" | "git+ssh" => Some(22),
+                "git" => Some(9418),
+                _ => None,
+            })?;
+            Some((parsed.host_str()?.to_string(), port))
+        }
+        Ok(_) => None,
+        // scp-style `git@github.com:org/repo.git`
+        Err(_) => match url.split_once(':') {
+            Some((user_host, _)) if !user_host.contains('/') => {
+                Some((user_host.rsplit('@').next()?.to_string(), 22))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Resolve the remote host and open a TCP connection, so an unreachable host fails
+/// in seconds with a clear error instead of after libgit2's network timeouts
+fn check_connectivity(url: &str, timeout: std::time::Duration) -> Result<()> {
+    use std::net::{TcpStream, ToSocketAddrs};
+
+    let Some((host, port)) = remote_endpoint(url) else {
+        return Ok(());
+    };
+    // Behind a proxy the host may only be reachable through it
+    let proxied = ["https_proxy", "HTTPS_PROXY", "http_proxy", "HTTP_PROXY"]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|v| !v.is_empty()));
+    if proxied && matches!(port, 80 | 443) {
+        return Ok(());
+    }
+    let addrs: Vec<_> = (host.as_str(), port)
+        .to_socket_addrs()
+        .with_context(|| format!("Host unreachable: could not resolve {}", host))?
+        .collect();
+    let mut last_error = None;
+    for addr in &addrs {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) => anyhow::bail!("Host unreachable: cannot connect to {}:{} ({})", host, port, e),
+        None => anyhow::bail!("Host unreachable: {} resolved to no addresses", host),
+    }
+}
+
+/// Host, owner path and repository name of a remote URL (https, ssh or scp-style)
+pub fn url_parts(url: &str) -> (String, String, String) {
+    let (host, path) = match url::Url::parse(url) {
+        Ok(parsed) if parsed.has_host() => (
+            parsed.host_str().unwrap_or("").to_string(),
+            parsed.path().to_string(),
+        ),
+        // scp-style `git@github.com:org/repo.git`
+        _ => match url.split_once(':') {
+            Some((user_host, path)) if !user_host.contains('/') => (
+                user_host.rsplit('@').next().unwrap_or(user_host).to_string(),
+                path.to_string(),
+            ),
+            _ => (String::new(), url.to_string()),
+        },
+    };
+
+    let segments: Vec<&str> = path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (org, repo) = match segments.split_last() {
+        Some((repo, org)) => (org.join("/"), repo.trim_end_matches(".git").to_string()),
+        None => (String::new(), String::new()),
+    };
+
+    let or_placeholder = |s: String| if s.is_empty() { "_".to_string() } else { s };
+    (or_placeholder(host), or_placeholder(org), or_placeholder(repo))
+}
+
+/// Keep a single path component to `[A-Za-z0-9._-]`, never `.` or `..`
+fn sanitize_path_component(component: &str) -> String {
+    let cleaned: String = component
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if cleaned.is_empty() || cleaned.chars().all(|c| c == '.') {
+        "_".to_string()
+    } else {
+        cleaned
     }
 }
 
@@ -286,3 +2320,217 @@ mod md5 {
         hasher.finish() as u128
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Repository in a fresh temp directory, removed again when dropped
+    struct TempRepo {
+        dir: PathBuf,
+        repo: Repository,
+    }
+
+    impl TempRepo {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("git-doc-test-{}", uuid::Uuid::new_v4()));
+            let repo = Repository::init(&dir).unwrap();
+            Self { dir, repo }
+        }
+
+        /// Commit a single file holding `message` on top of `main`
+        fn commit(&self, message: &str) -> git2::Oid {
+            let signature = git2::Signature::new(
+                "Test",
+                "test@example.com",
+                &git2::Time::new(1_700_000_000, 0),
+            )
+            .unwrap();
+            let blob = self.repo.blob(message.as_bytes()).unwrap();
+            let mut tree = self.repo.treebuilder(None).unwrap();
+            tree.insert("file.txt", blob, 0o100644).unwrap();
+            let tree = self.repo.find_tree(tree.write().unwrap()).unwrap();
+            let parent = self
+                .repo
+                .find_reference("refs/heads/main")
+                .ok()
+                .and_then(|r| r.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            self.repo
+                .commit(
+                    Some("refs/heads/main"),
+                    &signature,
+                    &signature,
+                    message,
+                    &tree,
+                    &parents,
+                )
+                .unwrap()
+        }
+
+        fn parse(&self, branch: &str, options: &ParseOptions) -> Result<ParsedCommits> {
+            GitProcessor::new(&self.dir.to_string_lossy())
+                .parse_commits(&self.dir, branch, options, None)
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn normalize_line_endings_converts_crlf_and_lone_cr() {
+        assert_eq!(
+            normalize_line_endings("title\r\n\r\nbody\r\n"),
+            "title\n\nbody\n"
+        );
+        assert_eq!(normalize_line_endings("title\rbody\r"), "title\nbody\n");
+        assert_eq!(
+            normalize_line_endings("a\r\nb\rc\nd\r\r\n"),
+            "a\nb\nc\nd\n\n"
+        );
+        assert_eq!(
+            normalize_line_endings("plain\nmessage\n"),
+            "plain\nmessage\n"
+        );
+    }
+
+    #[test]
+    fn message_title_has_no_carriage_return() {
+        let repo = TempRepo::new();
+        repo.commit("Fix parser\r\n\r\nFirst line\r\nsecond\rthird\r\n");
+
+        let options = ParseOptions {
+            normalize_line_endings: true,
+            ..Default::default()
+        };
+        let parsed = repo.parse("main", &options).unwrap();
+        let commit = &parsed.commits[0];
+        assert_eq!(commit.message_title, "Fix parser");
+        assert_eq!(commit.message, "Fix parser\n\nFirst line\nsecond\nthird\n");
+
+        // Titles are trimmed even when the message is stored as written
+        let parsed = repo.parse("main", &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.commits[0].message_title, "Fix parser");
+        assert!(parsed.commits[0].message.contains("\r\n"));
+    }
+
+    #[test]
+    fn annotated_tag_start_ref_walks_from_the_tagged_commit() {
+        let repo = TempRepo::new();
+        let tagged = repo.commit("First release");
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let target = repo.repo.find_object(tagged, None).unwrap();
+        repo.repo
+            .tag("v1.0.0", &target, &signature, "Release 1.0.0", false)
+            .unwrap();
+        repo.commit("After the release");
+
+        let parsed = repo.parse("v1.0.0", &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.tip, Some(tagged.to_string()));
+        let shas: Vec<&str> = parsed.commits.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(shas, vec![tagged.to_string().as_str()]);
+    }
+
+    #[test]
+    fn tag_of_a_tree_is_an_error() {
+        let repo = TempRepo::new();
+        let commit = repo.commit("Only commit");
+        let tree = repo.repo.find_commit(commit).unwrap().tree().unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.repo
+            .tag(
+                "tree-tag",
+                tree.as_object(),
+                &signature,
+                "Not a commit",
+                false,
+            )
+            .unwrap();
+
+        let error = repo
+            .parse("tree-tag", &ParseOptions::default())
+            .err()
+            .expect("a tag of a tree has no commits to walk");
+        assert!(
+            error.to_string().contains("does not point to a commit"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn tarball_with_a_link_is_refused() {
+        let repo = TempRepo::new();
+        let tarball = repo.dir.join("upload.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            std::fs::File::create(&tarball).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "repo/.git", "/tmp/other/.git")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let processor = GitProcessor::new(&repo.dir.to_string_lossy());
+        let error = processor
+            .extract_tarball(&tarball, "job", u64::MAX)
+            .expect_err("symlinked .git must be refused");
+        assert!(error.to_string().contains("link"), "{:#}", error);
+    }
+
+    #[test]
+    fn release_version_parse() {
+        let version = ReleaseVersion::parse("v1.2.3").unwrap();
+        assert_eq!(version.core, (1, 2, 3));
+        assert_eq!(version.pre, None);
+        let version = ReleaseVersion::parse("1.2.3-rc.1+build.5").unwrap();
+        assert_eq!(version.core, (1, 2, 3));
+        assert_eq!(version.pre.as_deref(), Some("rc.1"));
+        for tag in [
+            "v1.2",
+            "1.2.3.4",
+            "release-1",
+            "v1.x.3",
+            "1.2.3-",
+            "1.2.3-rc..1",
+        ] {
+            assert_eq!(ReleaseVersion::parse(tag), None, "{}", tag);
+        }
+    }
+
+    #[test]
+    fn release_versions_follow_semver_precedence() {
+        // In order, from the semver spec's precedence example plus multi-digit numbers
+        let tags = [
+            "v1.0.0-alpha",
+            "v1.0.0-alpha.1",
+            "v1.0.0-alpha.beta",
+            "v1.0.0-beta",
+            "v1.0.0-beta.2",
+            "v1.0.0-beta.11",
+            "v1.0.0-rc.1",
+            "v1.0.0-rc.2",
+            "v1.0.0-rc.10",
+            "v1.0.0",
+            "v1.0.1",
+            "v1.10.0",
+        ];
+        let versions: Vec<ReleaseVersion> = tags
+            .iter()
+            .map(|tag| ReleaseVersion::parse(tag).unwrap())
+            .collect();
+        for pair in versions.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+        let mut shuffled = versions.clone();
+        shuffled.reverse();
+        shuffled.sort();
+        assert_eq!(shuffled, versions);
+    }
+}
@@ -0,0 +1,75 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::{mpsc, Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Dedicated, bounded thread pool for blocking git2 work.
+///
+/// Keeps CPU-heavy clones, walks and diffs off both the async runtime and
+/// tokio's shared blocking pool so DB calls stay responsive.
+#[derive(Clone)]
+pub struct GitPool {
+    sender: mpsc::Sender<Job>,
+}
+
+impl GitPool {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            std::thread::Builder::new()
+                .name(format!("git-worker-{}", i))
+                .spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => break,
+                    };
+                    match job {
+                        // A panicking job must not take the worker down with it
+                        Ok(job) => {
+                            let _ = std::panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => break,
+                    }
+                })
+                .expect("failed to spawn git worker thread");
+        }
+
+        Self { sender }
+    }
+
+    /// Size from `GIT_WORKER_THREADS`, defaulting to the number of CPUs
+    pub fn from_env() -> Self {
+        let threads = std::env::var("GIT_WORKER_THREADS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(4)
+            });
+        tracing::info!("Starting git worker pool with {} threads", threads);
+        Self::new(threads)
+    }
+
+    /// Run a blocking git operation on the pool and await its result
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        // Keep the caller's span so worker logs stay attributed to its job
+        let span = tracing::Span::current();
+        self.sender
+            .send(Box::new(move || {
+                let _ = tx.send(span.in_scope(f));
+            }))
+            .map_err(|_| anyhow!("Git worker pool has shut down"))?;
+        rx.await.map_err(|_| anyhow!("Git operation panicked"))?
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+
+use crate::models::ParsedCommit;
+use crate::pipeline::CommitProcessor;
+
+/// Commits looked up per GraphQL request
+const BATCH_SIZE: usize = 50;
+
+/// Stores GitHub's own verdict on each signed commit (`verification.reason` in the REST
+/// API, e.g. `valid`, `unknown_key`, `bad_email`), which knows the keys users uploaded
+/// to GitHub and is richer than checking signatures against a local keyring.
+///
+/// Signed commits are collected and looked up in batches through the GraphQL API after
+/// they were stored. Lookup failures are logged and never fail the job.
+pub struct GithubVerification {
+    client: reqwest::Client,
+    graphql_url: String,
+    token: String,
+    owner: String,
+    name: String,
+    db: sqlx::MySqlPool,
+    repository_id: String,
+    pending: Mutex<Vec<String>>,
+}
+
+impl GithubVerification {
+    /// Enrichment for a job analyzing `repo_url`, or None when it is not hosted on
+    /// GitHub (`GITHUB_HOST`) or there is no token to call the API with
+    pub fn for_job(
+        db: &sqlx::MySqlPool,
+        repository_id: &str,
+        repo_url: &str,
+        token: Option<&str>,
+    ) -> Option<Self> {
+        let token = token.filter(|t| !t.is_empty())?;
+        let github_host = std::env::var("GITHUB_HOST").unwrap_or_else(|_| "github.com".into());
+        let (host, owner, name) = crate::git::url_parts(repo_url);
+        if !host.eq_ignore_ascii_case(&github_host) || owner.contains('/') {
+            return None;
+        }
+        let api_url = std::env::var("GITHUB_API_URL")
+            .unwrap_or_else(|_| "https://api.github.com".into());
+
+        Some(Self {
+            client: reqwest::Client::new(),
+            graphql_url: format!("{}/graphql", api_url.trim_end_matches('/')),
+            token: token.to_string(),
+            owner,
+            name,
+            db: db.clone(),
+            repository_id: repository_id.to_string(),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn take_batch(&self, all: bool) -> Vec<String> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if all || pending.len() >= BATCH_SIZE {
+            std::mem::take(&mut *pending)
+        } else {
+            Vec::new()
+        }
+    }
+
+    async fn flush(&self, shas: Vec<String>) {
+        for chunk in shas.chunks(BATCH_SIZE) {
+            if let Err(e) = self.lookup_and_store(chunk).await {
+                tracing::warn!(
+                    "GitHub verification lookup failed for {} commits: {:#}",
+                    chunk.len(),
+                    e
+                );
+            }
+        }
+    }
+
+    async fn lookup_and_store(&self, shas: &[String]) -> Result<()> {
+        // One aliased `object` lookup per commit; SHAs are hex, so inlining them is safe
+        let objects: String = shas
+            .iter()
+            .enumerate()
+            .filter(|(_, sha)| sha.chars().all(|c| c.is_ascii_hexdigit()))
+            .map(|(i, sha)| {
+                format!(
+                    "c{}: object(oid: \"{}\") {{ ... on Commit {{ signature {{ isValid state }} }} }} ",
+                    i, sha
+                )
+            })
+            .collect();
+        let query = format!(
+            "query($owner: String!, $name: String!) {{ repository(owner: $owner, name: $name) {{ {}}} }}",
+            objects
+        );
+
+        let response: Value = self
+            .client
+            .post(&self.graphql_url)
+            .bearer_auth(&self.token)
+            .header(reqwest::header::USER_AGENT, "git-doc-service")
+            .json(&json!({
+                "query": query,
+                "variables": { "owner": self.owner, "name": self.name },
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Invalid GitHub GraphQL response")?;
+        if let Some(errors) = response.get("errors") {
+            anyhow::bail!("GitHub GraphQL errors: {}", errors);
+        }
+        let repository = &response["data"]["repository"];
+
+        for (i, sha) in shas.iter().enumerate() {
+            let signature = &repository[format!("c{}", i)]["signature"];
+            let Some(state) = signature["state"].as_str() else {
+                continue;
+            };
+            sqlx::query(
+                "UPDATE Commit SET githubVerified = ?, githubVerificationReason = ? \
+                 WHERE repositoryId = ? AND sha = ?",
+            )
+            .bind(signature["isValid"].as_bool())
+            .bind(state.to_ascii_lowercase())
+            .bind(&self.repository_id)
+            .bind(sha)
+            .execute(&self.db)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+impl CommitProcessor for GithubVerification {
+    fn name(&self) -> &str {
+        "github-verification"
+    }
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // GitHub reports unsigned commits as `unsigned`; nothing worth a lookup
+            if commit.is_signed {
+                self.pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(commit.sha.clone());
+                let batch = self.take_batch(false);
+                self.flush(batch).await;
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let batch = self.take_batch(true);
+            self.flush(batch).await;
+            Ok(())
+        })
+    }
+}
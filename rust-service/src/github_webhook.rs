@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{default_branch, AnalyzeRequest, AppState};
+
+/// Tag put on jobs started by a push, so they can be told apart from manual runs
+const TRIGGER_TAG: (&str, &str) = ("trigger", "github-push");
+
+/// The parts of a GitHub `push` event payload used here
+#[derive(Debug, Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    after: String,
+    #[serde(default)]
+    deleted: bool,
+    repository: PushRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct PushRepository {
+    html_url: String,
+    default_branch: Option<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubWebhookResponse {
+    /// `X-GitHub-Event` of the delivery
+    pub event: String,
+    /// Analysis jobs started for the push, one per matching repository
+    pub job_ids: Vec<String>,
+    pub message: String,
+}
+
+/// Whether `signature` (`sha256=<hex>`, from `X-Hub-Signature-256`) is the HMAC of
+/// `body` under `secret`; compared in constant time
+fn signature_matches(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+/// Receive GitHub push events and re-analyze the pushed branch incrementally.
+///
+/// Deliveries must be signed with `GITHUB_WEBHOOK_SECRET`. A push starts one job per
+/// Repository row with the same host/owner/name whose branch is the pushed one (rows
+/// following the default branch match pushes to it); other events, tag pushes and
+/// branch deletions are acknowledged and ignored.
+#[utoipa::path(
+    post,
+    path = "/webhooks/github",
+    tag = "webhooks",
+    request_body(content = String, description = "GitHub webhook payload", content_type = "application/json"),
+    responses(
+        (status = 200, body = GithubWebhookResponse),
+        (status = 400, description = "Malformed push payload", body = String),
+        (status = 401, description = "Missing or invalid X-Hub-Signature-256", body = String),
+        (status = 503, description = "GITHUB_WEBHOOK_SECRET is not set", body = String)
+    )
+)]
+pub async fn github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<GithubWebhookResponse>, (StatusCode, String)> {
+    let secret = std::env::var("GITHUB_WEBHOOK_SECRET")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "GITHUB_WEBHOOK_SECRET is not set".to_string(),
+        ))?;
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string()
+    };
+    if !signature_matches(&secret, &body, &header("x-hub-signature-256")) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid X-Hub-Signature-256".to_string(),
+        ));
+    }
+
+    let event = header("x-github-event");
+    let ignored = |message: &str| {
+        Ok(Json(GithubWebhookResponse {
+            event: event.clone(),
+            job_ids: Vec::new(),
+            message: message.to_string(),
+        }))
+    };
+    if event != "push" {
+        return ignored("Only push events trigger analysis");
+    }
+    let push: PushEvent = serde_json::from_slice(&body).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid push payload: {}", e),
+        )
+    })?;
+    let Some(branch) = push.git_ref.strip_prefix("refs/heads/") else {
+        return ignored("Not a branch push");
+    };
+    if push.deleted {
+        return ignored("Branch was deleted");
+    }
+
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let pushed = normalized_parts(&push.repository.html_url);
+    let candidates: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, url, branch, defaultBranch FROM Repository WHERE url LIKE CONCAT('%', ?, '%')",
+    )
+    .bind(&pushed.2)
+    .fetch_all(&state.db)
+    .await
+    .map_err(internal)?;
+
+    let follows_default = |configured: &str, cached: Option<&str>| {
+        default_branch::is_requested(configured)
+            && cached
+                .or(push.repository.default_branch.as_deref())
+                .is_some_and(|default| default == branch)
+    };
+    let mut job_ids = Vec::new();
+    for (repository_id, url, configured, cached_default) in candidates {
+        if normalized_parts(&url) != pushed
+            || !(configured == branch || follows_default(&configured, cached_default.as_deref()))
+        {
+            continue;
+        }
+
+        // A queued job hasn't cloned yet and will see this push anyway
+        let waiting: Option<String> = sqlx::query_scalar(
+            "SELECT id FROM AnalysisJob WHERE repositoryId = ? AND status = 'QUEUED' LIMIT 1",
+        )
+        .bind(&repository_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(internal)?;
+        if let Some(waiting) = waiting {
+            tracing::info!(
+                "Push to {} {}: job {} is still queued, not starting another",
+                url,
+                branch,
+                waiting
+            );
+            continue;
+        }
+
+        let job_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO AnalysisJob (id, repositoryId, status) VALUES (?, ?, 'PENDING')")
+            .bind(&job_id)
+            .bind(&repository_id)
+            .execute(&state.db)
+            .await
+            .map_err(internal)?;
+        tracing::info!(
+            "Push of {} to {} {}: starting incremental job {}",
+            push.after,
+            url,
+            branch,
+            job_id
+        );
+        let started = crate::spawn_analysis(
+            state.clone(),
+            AnalyzeRequest {
+                job_id: job_id.clone(),
+                repo_url: url,
+                branch: branch.to_string(),
+                incremental: Some(true),
+                tags: Some(HashMap::from([(
+                    TRIGGER_TAG.0.to_string(),
+                    TRIGGER_TAG.1.to_string(),
+                )])),
+                ..Default::default()
+            },
+        )
+        .await;
+        // Don't leave the row PENDING; a redelivered push creates a new job anyway
+        if let Err((status, error)) = started {
+            crate::fail_job(&state, &job_id, &error).await;
+            return Err((status, error));
+        }
+        job_ids.push(job_id);
+    }
+
+    let message = match job_ids.len() {
+        0 => format!(
+            "No repository tracks {} {}",
+            push.repository.html_url, branch
+        ),
+        n => format!("Started {} incremental analysis job(s)", n),
+    };
+    Ok(Json(GithubWebhookResponse {
+        event,
+        job_ids,
+        message,
+    }))
+}
+
+/// Host, owner and name of a remote URL, lowercased, so https, ssh and `.git`
+/// spellings of one repository compare equal
+fn normalized_parts(url: &str) -> (String, String, String) {
+    let (host, owner, name) = crate::git::url_parts(url);
+    (
+        host.to_lowercase(),
+        owner.to_lowercase(),
+        name.to_lowercase(),
+    )
+}
@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::models::ParsedCommit;
+use crate::pipeline::CommitProcessor;
+
+/// Give up on an identity service request after this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolved id per email (None: unknown to the service) and when it was looked up
+type Entries = HashMap<String, (Option<String>, Instant)>;
+
+/// Identity ids resolved for author emails, shared by all jobs. Emails the service
+/// doesn't know are cached too, so they aren't asked about on every commit.
+#[derive(Clone)]
+pub struct IdentityCache {
+    inner: Arc<Mutex<Entries>>,
+    ttl: Duration,
+}
+
+impl IdentityCache {
+    /// Entries live for `IDENTITY_CACHE_TTL_SECS` (default 1 hour)
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("IDENTITY_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600);
+        Self {
+            inner: Arc::new(Mutex::new(HashMap::new())),
+            ttl: Duration::from_secs(ttl),
+        }
+    }
+
+    /// Cached id for an email: Some(None) when the service had no identity for it
+    fn get(&self, email: &str) -> Option<Option<String>> {
+        let entries = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(email)
+            .filter(|(_, at)| at.elapsed() < self.ttl)
+            .map(|(id, _)| id.clone())
+    }
+
+    fn insert(&self, email: String, id: Option<String>) {
+        let mut entries = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (_, at)| at.elapsed() < self.ttl);
+        entries.insert(email, (id, Instant::now()));
+    }
+}
+
+/// Response of the identity service: canonical id per email, null or absent when unknown
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    identities: HashMap<String, Option<String>>,
+}
+
+/// Maps author emails to canonical identity ids (e.g. employee ids) with an external
+/// service and stores them as the commit's `identityId`.
+///
+/// Commits are collected and resolved in batches after they were stored: emails not
+/// in the cache are POSTed as `{"emails": [...]}` to `IDENTITY_SERVICE_URL`, which
+/// answers `{"identities": {"<email>": "<id>"}}`. When the service fails, lookups stop
+/// for the rest of the job and its commits keep a null id; the job itself never fails.
+pub struct IdentityResolution {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    batch_size: usize,
+    cache: IdentityCache,
+    db: sqlx::MySqlPool,
+    /// (commit id, author email) waiting for a lookup
+    pending: Mutex<Vec<(String, String)>>,
+    unavailable: AtomicBool,
+}
+
+impl IdentityResolution {
+    /// Enrichment for a job, or None when no identity service is configured
+    pub fn for_job(db: &sqlx::MySqlPool, cache: &IdentityCache) -> Option<Self> {
+        let url = std::env::var("IDENTITY_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())?;
+        Some(Self {
+            client: reqwest::Client::new(),
+            url,
+            token: std::env::var("IDENTITY_SERVICE_TOKEN")
+                .ok()
+                .filter(|t| !t.is_empty()),
+            batch_size: std::env::var("IDENTITY_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100usize)
+                .max(1),
+            cache: cache.clone(),
+            db: db.clone(),
+            pending: Mutex::new(Vec::new()),
+            unavailable: AtomicBool::new(false),
+        })
+    }
+
+    fn take_batch(&self, all: bool) -> Vec<(String, String)> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if all || pending.len() >= self.batch_size {
+            std::mem::take(&mut *pending)
+        } else {
+            Vec::new()
+        }
+    }
+
+    async fn flush(&self, commits: Vec<(String, String)>) {
+        if commits.is_empty() || self.unavailable.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.resolve_and_store(&commits).await {
+            self.unavailable.store(true, Ordering::Relaxed);
+            tracing::warn!(
+                "Identity resolution failed for {} commits, skipping it for the rest of the job: {:#}",
+                commits.len(),
+                e
+            );
+        }
+    }
+
+    async fn resolve_and_store(&self, commits: &[(String, String)]) -> Result<()> {
+        let unknown: Vec<&str> = commits
+            .iter()
+            .map(|(_, email)| email.as_str())
+            .filter(|email| self.cache.get(email).is_none())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        for emails in unknown.chunks(self.batch_size) {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .timeout(REQUEST_TIMEOUT)
+                .json(&json!({ "emails": emails }));
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let response: LookupResponse = request
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("Invalid identity service response")?;
+            for email in emails {
+                let id = response.identities.get(*email).cloned().flatten();
+                self.cache.insert(email.to_string(), id);
+            }
+        }
+
+        // One update per identity, covering all of its commits in the batch
+        let mut by_identity: HashMap<String, Vec<&str>> = HashMap::new();
+        for (commit_id, email) in commits {
+            if let Some(Some(identity)) = self.cache.get(email) {
+                by_identity.entry(identity).or_default().push(commit_id);
+            }
+        }
+        for (identity, commit_ids) in by_identity {
+            let mut query =
+                sqlx::QueryBuilder::<sqlx::MySql>::new("UPDATE Commit SET identityId = ");
+            query.push_bind(identity).push(" WHERE id IN (");
+            let mut ids = query.separated(", ");
+            for id in commit_ids {
+                ids.push_bind(id);
+            }
+            query.push(")");
+            query.build().execute(&self.db).await?;
+        }
+        Ok(())
+    }
+}
+
+impl CommitProcessor for IdentityResolution {
+    fn name(&self) -> &str {
+        "identity"
+    }
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if !commit.author_email.is_empty() {
+                self.pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((commit.id.clone(), commit.author_email.to_lowercase()));
+                let batch = self.take_batch(false);
+                self.flush(batch).await;
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let batch = self.take_batch(true);
+            self.flush(batch).await;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+fn jira_key_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"([A-Z][A-Z0-9]+-\d+)").expect("valid JIRA key regex"))
+}
+
+/// Extract every distinct JIRA ticket key from a commit message, in order of appearance
+pub fn extract_jira_keys(message: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for m in jira_key_regex().find_iter(message) {
+        let key = m.as_str();
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+/// Project prefix of a JIRA key (`PROJ-123` -> `PROJ`)
+pub fn jira_project(key: &str) -> &str {
+    key.rsplit_once('-').map(|(project, _)| project).unwrap_or(key)
+}
+
+/// Normalize a JIRA base URL: add `default_scheme` when missing and strip trailing slashes.
+/// Returns None when the result is not a valid http(s) URL.
+pub fn normalize_base_url(raw: &str, default_scheme: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("{}://{}", default_scheme, trimmed)
+    };
+    let base = with_scheme.trim_end_matches('/').to_string();
+
+    match url::Url::parse(&base) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some() => {
+            Some(base)
+        }
+        _ => None,
+    }
+}
+
+/// Link to a ticket on a (normalized) JIRA base URL
+pub fn browse_url(base: &str, key: &str) -> String {
+    format!("{}/browse/{}", base, key)
+}
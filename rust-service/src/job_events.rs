@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::job_status::JobStatus;
+use crate::AppState;
+
+/// Events buffered per subscriber; a client that falls further behind skips ahead
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Comment line sent on idle streams so proxies don't close them
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Something that happened to an analysis job, as sent on `/jobs/:id/events`
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum JobEvent {
+    /// The job moved to a new status (`event: status`)
+    #[serde(rename_all = "camelCase")]
+    Status {
+        job_id: String,
+        status: String,
+        error: Option<String>,
+    },
+    /// More commits were stored (`event: progress`), at most every
+    /// `PROGRESS_UPDATE_INTERVAL` commits
+    #[serde(rename_all = "camelCase")]
+    Progress {
+        job_id: String,
+        processed_commits: usize,
+        total_commits: usize,
+    },
+}
+
+impl JobEvent {
+    /// Whether the job can't change after this event
+    fn is_final(&self) -> bool {
+        matches!(self, JobEvent::Status { status, .. } if JobStatus::is_final_str(status))
+    }
+
+    fn to_sse(&self) -> Event {
+        let name = match self {
+            JobEvent::Status { .. } => "status",
+            JobEvent::Progress { .. } => "progress",
+        };
+        Event::default()
+            .event(name)
+            .data(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// Live status and progress of the jobs running in this process, fanned out to
+/// `/jobs/:id/events` subscribers. Nothing is kept for jobs nobody listens to.
+#[derive(Clone, Default)]
+pub struct JobEvents {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<JobEvent>>>>,
+}
+
+impl JobEvents {
+    fn subscribe(&self, job_id: &str) -> broadcast::Receiver<JobEvent> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        // Drop channels whose listeners all went away
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .entry(job_id.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Send an event to the job's subscribers, if any
+    pub fn publish(&self, event: JobEvent) {
+        let job_id = match &event {
+            JobEvent::Status { job_id, .. } | JobEvent::Progress { job_id, .. } => job_id.clone(),
+        };
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        let finished = event.is_final();
+        if let Some(sender) = channels.get(&job_id) {
+            let _ = sender.send(event);
+        }
+        if finished {
+            channels.remove(&job_id);
+        }
+    }
+
+    pub fn status(&self, job_id: &str, status: JobStatus, error: Option<&str>) {
+        self.publish(JobEvent::Status {
+            job_id: job_id.to_string(),
+            status: status.as_str().to_string(),
+            error: error.map(String::from),
+        });
+    }
+
+    pub fn progress(&self, job_id: &str, processed_commits: usize, total_commits: usize) {
+        self.publish(JobEvent::Progress {
+            job_id: job_id.to_string(),
+            processed_commits,
+            total_commits,
+        });
+    }
+}
+
+/// Stream a job's status transitions and progress as Server-Sent Events.
+///
+/// The stream opens with the job's current status and progress from the database,
+/// then follows the job live until it is COMPLETED, FAILED or CANCELLED. Only jobs
+/// running in this service instance are followed live.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/events",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Analysis job id")),
+    responses(
+        (status = 200, description = "`text/event-stream` of `status` and `progress` events", body = JobEvent),
+        (status = 404, description = "Job not found", body = String)
+    )
+)]
+pub async fn job_events(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    // Subscribe before reading the snapshot so no transition falls in between
+    let receiver = state.job_events.subscribe(&id);
+    let job: Option<(String, Option<String>, i32, i32)> = sqlx::query_as(
+        "SELECT status, error, processedCommits, totalCommits FROM AnalysisJob WHERE id = ?",
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (status, error, processed, total) =
+        job.ok_or_else(|| (StatusCode::NOT_FOUND, format!("Job {} not found", id)))?;
+
+    let finished = JobStatus::is_final_str(&status);
+    let mut initial = vec![JobEvent::Status {
+        job_id: id.clone(),
+        status,
+        error,
+    }];
+    if total > 0 {
+        initial.push(JobEvent::Progress {
+            job_id: id.clone(),
+            processed_commits: processed.max(0) as usize,
+            total_commits: total as usize,
+        });
+    }
+    let initial = futures::stream::iter(initial.into_iter().map(|event| Ok(event.to_sse())));
+
+    let live = futures::stream::unfold((!finished).then_some(receiver), |receiver| async move {
+        let mut receiver = receiver?;
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = event.to_sse();
+                    let next = (!event.is_final()).then_some(receiver);
+                    return Some((Ok(sse), next));
+                }
+                // Skipped progress is made up by the next event
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(futures::StreamExt::chain(initial, live))
+        .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
@@ -0,0 +1,222 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use regex::Regex;
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::AppState;
+
+/// Span field naming the job whose log an event belongs to
+const JOB_ID_FIELD: &str = "job_id";
+
+/// Bounded in-memory capture of the tracing output of each analysis job,
+/// so callers can see why a job failed without access to the server logs
+#[derive(Clone)]
+pub struct JobLogs {
+    inner: Arc<Mutex<Buffers>>,
+    max_lines: usize,
+    max_jobs: usize,
+}
+
+#[derive(Default)]
+struct Buffers {
+    jobs: HashMap<String, JobBuffer>,
+    /// Jobs in the order their first line arrived, for evicting the oldest
+    order: VecDeque<String>,
+}
+
+#[derive(Default)]
+struct JobBuffer {
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+impl JobLogs {
+    pub fn new(max_lines: usize, max_jobs: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Buffers::default())),
+            max_lines: max_lines.max(1),
+            max_jobs: max_jobs.max(1),
+        }
+    }
+
+    /// Limits from `JOB_LOG_MAX_LINES` (per job) and `JOB_LOG_MAX_JOBS`
+    pub fn from_env() -> Self {
+        let env_usize = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            env_usize("JOB_LOG_MAX_LINES", 500),
+            env_usize("JOB_LOG_MAX_JOBS", 200),
+        )
+    }
+
+    /// Tracing layer feeding this buffer from events inside a span with a `job_id` field
+    pub fn layer(&self) -> JobLogLayer {
+        JobLogLayer { logs: self.clone() }
+    }
+
+    fn push(&self, job_id: &str, line: String) {
+        let Ok(mut buffers) = self.inner.lock() else {
+            return;
+        };
+        if !buffers.jobs.contains_key(job_id) {
+            while buffers.order.len() >= self.max_jobs {
+                if let Some(oldest) = buffers.order.pop_front() {
+                    buffers.jobs.remove(&oldest);
+                }
+            }
+            buffers.order.push_back(job_id.to_string());
+        }
+
+        // Keep the most recent lines; the failure is usually at the end
+        let buffer = buffers.jobs.entry(job_id.to_string()).or_default();
+        if buffer.lines.len() >= self.max_lines {
+            buffer.lines.pop_front();
+            buffer.dropped += 1;
+        }
+        buffer.lines.push_back(line);
+    }
+
+    /// Captured lines of a job and how many older ones were dropped
+    fn snapshot(&self, job_id: &str) -> Option<(Vec<String>, usize)> {
+        let buffers = self.inner.lock().ok()?;
+        buffers
+            .jobs
+            .get(job_id)
+            .map(|buffer| (buffer.lines.iter().cloned().collect(), buffer.dropped))
+    }
+}
+
+/// Job id attached to a span's extensions
+struct JobSpan(String);
+
+pub struct JobLogLayer {
+    logs: JobLogs,
+}
+
+impl<S> Layer<S> for JobLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = JobIdVisitor(None);
+        attrs.record(&mut visitor);
+        if let (Some(job_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(JobSpan(job_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        let job_id = scope
+            .into_iter()
+            .find_map(|span| span.extensions().get::<JobSpan>().map(|j| j.0.clone()));
+        let Some(job_id) = job_id else {
+            return;
+        };
+
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let line = format!(
+            "{} {:>5} {}",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            event.metadata().level(),
+            redact_url_credentials(&visitor.line)
+        );
+        self.logs.push(&job_id, line);
+    }
+}
+
+struct JobIdVisitor(Option<String>);
+
+impl Visit for JobIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == JOB_ID_FIELD {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == JOB_ID_FIELD {
+            self.0 = Some(format!("{:?}", value).trim_matches('"').to_string());
+        }
+    }
+}
+
+/// Message first, then any other fields as `name=value`
+#[derive(Default)]
+struct LineVisitor {
+    line: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.line);
+            let _ = write!(self.line, "{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.line, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Hide `user:token@` in URLs; job logs are served to API callers
+fn redact_url_credentials(line: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"://[^/@\s]+@").expect("valid credential regex"))
+        .replace_all(line, "://***@")
+        .into_owned()
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLogsResponse {
+    pub job_id: String,
+    /// Most recent captured log lines, oldest first
+    pub lines: Vec<String>,
+    /// Older lines dropped to stay within `JOB_LOG_MAX_LINES`
+    pub dropped: usize,
+}
+
+/// Captured log output of an analysis job (kept in memory; lost on restart)
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/logs",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Analysis job id")),
+    responses(
+        (status = 200, description = "Log lines captured for the job", body = JobLogsResponse),
+        (status = 404, description = "No log output buffered for this job", body = String)
+    )
+)]
+pub async fn get_job_logs(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobLogsResponse>, (StatusCode, String)> {
+    let (lines, dropped) = state.job_logs.snapshot(&job_id).ok_or((
+        StatusCode::NOT_FOUND,
+        "No log output buffered for this job".to_string(),
+    ))?;
+    Ok(Json(JobLogsResponse {
+        job_id,
+        lines,
+        dropped,
+    }))
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::job_status::{self, JobStatus};
+use crate::{AnalyzeRequest, AppState};
+
+/// Caps how many analysis jobs run at once. Every running job holds a clone on disk
+/// and database connections, so extra jobs wait as QUEUED and start in arrival order
+/// as slots free up.
+#[derive(Clone)]
+pub struct JobQueue {
+    slots: Arc<Semaphore>,
+}
+
+impl JobQueue {
+    /// `MAX_CONCURRENT_JOBS` running at once (default 2)
+    pub fn from_env() -> Self {
+        let limit = std::env::var("MAX_CONCURRENT_JOBS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2usize)
+            .max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(limit)),
+        }
+    }
+
+    /// Wait for a free slot for a QUEUED job, then move it to CLONING. None when the
+    /// job left the queue while it waited (e.g. it was cancelled) and must not run;
+    /// the slot is held until the returned permit is dropped.
+    pub async fn wait_turn(
+        &self,
+        state: &AppState,
+        job_id: &str,
+    ) -> Result<Option<OwnedSemaphorePermit>> {
+        let permit = match Arc::clone(&self.slots).try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                tracing::info!("All job slots busy, job queued");
+                Arc::clone(&self.slots)
+                    .acquire_owned()
+                    .await
+                    .expect("job semaphore is never closed")
+            }
+        };
+        if !job_status::transition(state, job_id, JobStatus::Cloning, None).await? {
+            return Ok(None);
+        }
+        Ok(Some(permit))
+    }
+}
+
+/// Pick up analysis jobs a shutdown left QUEUED, CLONING, FETCHING or PARSING. With
+/// `INTERRUPTED_JOB_POLICY=resume` (the default) they are queued again with the options
+/// they were started with, incrementally unless they asked otherwise; commits stored
+/// before the restart are skipped as already known. With `fail`, or when the job can't
+/// be resumed (jobs started before options were stored, or whose inline credentials
+/// aren't covered by a stored credential or `SSH_KEY_PATH`), they are marked FAILED.
+///
+/// Assumes one service instance per database: jobs of other instances look interrupted.
+pub async fn recover_interrupted(state: AppState) {
+    let resume = match std::env::var("INTERRUPTED_JOB_POLICY")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "" | "resume" => true,
+        "fail" => false,
+        other => {
+            tracing::warn!("Unknown INTERRUPTED_JOB_POLICY {:?}, using resume", other);
+            true
+        }
+    };
+    let interrupted: Vec<(String, String, Option<String>, String)> = match sqlx::query_as(
+        r#"
+        SELECT id, status, CAST(request AS CHAR), repositoryId
+        FROM AnalysisJob
+        WHERE status IN ('QUEUED', 'CLONING', 'FETCHING', 'PARSING')
+        ORDER BY createdAt
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Could not look up interrupted analysis jobs: {}", e);
+            return;
+        }
+    };
+
+    for (job_id, status, request, repository_id) in interrupted {
+        let stored = request.and_then(|json| serde_json::from_str::<StoredRequest>(&json).ok());
+        let (mut request, withheld_secrets) = match stored {
+            Some(stored) if resume => (stored.request, stored.withheld_secrets),
+            stored => {
+                let request = stored.map(|stored| stored.request);
+                tracing::info!(
+                    "Failing analysis job {} interrupted while {}",
+                    job_id,
+                    status
+                );
+                let error = format!("Service restarted while the job was {}", status);
+                let _ =
+                    job_status::transition(&state, &job_id, JobStatus::Failed, Some(&error)).await;
+                // A failed upload job is never resumed, so its kept upload can go
+                if let Some(upload) = request.and_then(|request| request.upload) {
+                    upload.remove(&state.work_dir).await;
+                }
+                continue;
+            }
+        };
+
+        let missing = missing_secrets(&state, &repository_id, &withheld_secrets).await;
+        if request.upload.is_none() && !missing.is_empty() {
+            tracing::info!(
+                "Failing analysis job {}: its {} can't be recovered",
+                job_id,
+                missing.join(", ")
+            );
+            let error = format!(
+                "Service restarted while the job was {}; credentials not persisted ({} only came with the request)",
+                status,
+                missing.join(", ")
+            );
+            let _ = job_status::transition(&state, &job_id, JobStatus::Failed, Some(&error)).await;
+            continue;
+        }
+
+        if status != JobStatus::Queued.as_str() {
+            match job_status::requeue_interrupted(&state, &job_id).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::warn!("Could not requeue analysis job {}: {}", job_id, e);
+                    continue;
+                }
+            }
+        }
+        tracing::info!(
+            "Resuming analysis job {} interrupted while {}",
+            job_id,
+            status
+        );
+        request.job_id = job_id;
+        request.incremental.get_or_insert(true);
+        crate::run_queued(state.clone(), request);
+    }
+}
+
+/// `AnalysisJob.request`: the job's options and the secrets that were left out of them
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredRequest {
+    #[serde(flatten)]
+    request: AnalyzeRequest,
+    #[serde(default)]
+    withheld_secrets: Vec<String>,
+}
+
+/// Secrets a job was started with that nothing stands in for on resume: the repository's
+/// stored credential replaces a token, `SSH_KEY_PATH` (and `SSH_KEY_PASSPHRASE`) a key
+async fn missing_secrets(
+    state: &AppState,
+    repository_id: &str,
+    withheld: &[String],
+) -> Vec<String> {
+    let env_set = |name| std::env::var(name).is_ok_and(|v| !v.is_empty());
+    let withheld = |name: &str| withheld.iter().any(|w| w == name);
+    let mut missing = Vec::new();
+    if withheld("credentialToken")
+        && !matches!(
+            crate::credentials::stored_token(state, repository_id).await,
+            Ok(Some(_))
+        )
+    {
+        missing.push("credentialToken".to_string());
+    }
+    if withheld("sshPrivateKey") {
+        if !env_set("SSH_KEY_PATH") {
+            missing.push("sshPrivateKey".to_string());
+        }
+    } else if withheld("sshKeyPassphrase") && !env_set("SSH_KEY_PASSPHRASE") {
+        missing.push("sshKeyPassphrase".to_string());
+    }
+    missing
+}
@@ -0,0 +1,158 @@
+use axum::http::StatusCode;
+
+use crate::AppState;
+
+/// AnalysisJob lifecycle; mirrors the `AnalysisStatus` enum in the Prisma schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Pending,
+    Queued,
+    Cloning,
+    Fetching,
+    Parsing,
+    Summarizing,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Pending => "PENDING",
+            JobStatus::Queued => "QUEUED",
+            JobStatus::Cloning => "CLONING",
+            JobStatus::Fetching => "FETCHING",
+            JobStatus::Parsing => "PARSING",
+            JobStatus::Summarizing => "SUMMARIZING",
+            JobStatus::Completed => "COMPLETED",
+            JobStatus::Failed => "FAILED",
+            JobStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// Whether a job with this status (as stored) is done and won't change again
+    pub fn is_final_str(status: &str) -> bool {
+        [JobStatus::Completed, JobStatus::Failed, JobStatus::Cancelled]
+            .iter()
+            .any(|s| s.as_str() == status)
+    }
+
+    /// Statuses a job may move to `self` from
+    pub fn allowed_from(self) -> &'static [JobStatus] {
+        use JobStatus::*;
+        match self {
+            Pending => &[],
+            // A failed job may be re-run under the same id; jobs cut off by a restart
+            // are queued again by `requeue_interrupted`, not through this table
+            Queued => &[Pending, Failed],
+            // Parsing -> Cloning when a corrupt clone is thrown away and re-cloned
+            Cloning => &[Queued, Parsing],
+            Fetching => &[Cloning],
+            Parsing => &[Cloning, Fetching],
+            Summarizing => &[Parsing],
+            Completed => &[Parsing, Summarizing],
+            Failed => &[Pending, Queued, Cloning, Fetching, Parsing, Summarizing],
+            Cancelled => &[Pending, Queued, Cloning, Fetching, Parsing, Summarizing],
+        }
+    }
+}
+
+/// Move a job into QUEUED for a new run, refusing ids that belong to a job that is
+/// running or finished (404 for unknown ids, 409 otherwise). FAILED jobs may be re-run
+/// under the same id unless `RESTART_FAILED_JOBS=false`.
+pub async fn start(state: &AppState, job_id: &str) -> Result<(), (StatusCode, String)> {
+    let db = &state.db;
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let current: Option<String> = sqlx::query_scalar("SELECT status FROM AnalysisJob WHERE id = ?")
+        .bind(job_id)
+        .fetch_optional(db)
+        .await
+        .map_err(internal)?;
+    let Some(current) = current else {
+        return Err((StatusCode::NOT_FOUND, format!("Job {} not found", job_id)));
+    };
+
+    let conflict = || {
+        (
+            StatusCode::CONFLICT,
+            format!("Job {} is {} and cannot be started again", job_id, current),
+        )
+    };
+    if current == JobStatus::Failed.as_str() && !crate::env_flag("RESTART_FAILED_JOBS", true) {
+        return Err(conflict());
+    }
+    // The guarded UPDATE also catches a concurrent start that won the race
+    if !transition(state, job_id, JobStatus::Queued, None).await.map_err(internal)? {
+        return Err(conflict());
+    }
+    Ok(())
+}
+
+/// Put a job a restart cut off while CLONING, FETCHING or PARSING back in the queue.
+/// Only for startup recovery: a live job in those states must never be requeued, which
+/// is why `allowed_from` doesn't permit it. False when the job is no longer in them.
+pub async fn requeue_interrupted(state: &AppState, job_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE AnalysisJob SET status = 'QUEUED' WHERE id = ? AND status IN ('CLONING', 'FETCHING', 'PARSING')",
+    )
+    .bind(job_id)
+    .execute(&state.db)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    state.job_events.status(job_id, JobStatus::Queued, None);
+    Ok(true)
+}
+
+/// Move a job to `to` only if its current status allows it.
+/// Returns false (and logs) when the transition was refused, e.g. a late
+/// COMPLETED arriving after the job was cancelled. Accepted transitions are
+/// published to the job's `/jobs/:id/events` subscribers.
+pub async fn transition(
+    state: &AppState,
+    job_id: &str,
+    to: JobStatus,
+    error: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    if to.allowed_from().is_empty() {
+        return Ok(false);
+    }
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new("UPDATE AnalysisJob SET status = ");
+    builder.push_bind(to.as_str());
+    if let Some(error) = error {
+        builder.push(", error = ").push_bind(error);
+    }
+    if to == JobStatus::Completed {
+        builder.push(", completedAt = NOW()");
+    }
+    builder.push(" WHERE id = ").push_bind(job_id);
+    builder.push(" AND status IN (");
+    let mut separated = builder.separated(", ");
+    for from in to.allowed_from() {
+        separated.push_bind(from.as_str());
+    }
+    separated.push_unseparated(")");
+
+    let db = &state.db;
+    let result = builder.build().execute(db).await?;
+    if result.rows_affected() == 0 {
+        let current: Option<String> =
+            sqlx::query_scalar("SELECT status FROM AnalysisJob WHERE id = ?")
+                .bind(job_id)
+                .fetch_optional(db)
+                .await?;
+        tracing::warn!(
+            "Refused status transition for job {}: {} -> {}",
+            job_id,
+            current.as_deref().unwrap_or("<missing>"),
+            to.as_str()
+        );
+        return Ok(false);
+    }
+    state.job_events.status(job_id, to, error);
+    Ok(true)
+}
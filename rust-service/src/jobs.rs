@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::job_status::{self, JobStatus};
+use crate::AppState;
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_NAME_LEN: usize = 64;
+const MAX_TAG_VALUE_LEN: usize = 255;
+const MAX_STATUS_BATCH: usize = 200;
+
+/// Reject tag sets that are too large or use unexpected characters in names
+pub fn validate_tags(tags: &HashMap<String, String>) -> Result<(), String> {
+    if tags.len() > MAX_TAGS {
+        return Err(format!("At most {} tags are allowed", MAX_TAGS));
+    }
+    for (name, value) in tags {
+        let valid_name = !name.is_empty()
+            && name.len() <= MAX_TAG_NAME_LEN
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'));
+        if !valid_name {
+            return Err(format!(
+                "Invalid tag name '{}': use 1-{} of [A-Za-z0-9_.-/]",
+                name, MAX_TAG_NAME_LEN
+            ));
+        }
+        if value.len() > MAX_TAG_VALUE_LEN {
+            return Err(format!(
+                "Tag '{}' value exceeds {} characters",
+                name, MAX_TAG_VALUE_LEN
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Attach tags to a job, replacing values for names it already has
+pub async fn store_tags(
+    db: &sqlx::MySqlPool,
+    job_id: &str,
+    tags: &HashMap<String, String>,
+) -> Result<(), sqlx::Error> {
+    for (name, value) in tags {
+        sqlx::query(
+            r#"
+            INSERT INTO AnalysisJobTag (id, jobId, name, value) VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE value = VALUES(value)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(job_id)
+        .bind(name)
+        .bind(value)
+        .execute(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Store the summarizer context supplied with a job
+pub async fn store_summary_context(
+    db: &sqlx::MySqlPool,
+    job_id: &str,
+    context: &crate::summaries::SummaryContext,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE AnalysisJob SET summaryContext = ? WHERE id = ?")
+        .bind(sqlx::types::Json(context))
+        .bind(job_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ListJobsQuery {
+    /// Comma-separated `name:value` pairs; a job must carry all of them
+    pub tags: Option<String>,
+    /// Filter by AnalysisJob status
+    pub status: Option<String>,
+    /// Maximum number of jobs returned (default 50, max 500)
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct JobSummary {
+    pub id: String,
+    pub repository_id: String,
+    pub status: String,
+    pub total_commits: i32,
+    pub processed_commits: i32,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub analysis_started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub analysis_finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Parse + insert throughput, set when the job completes
+    pub commits_per_second: Option<f64>,
+    /// Commits in the date range before author filters; null until parsing finishes
+    pub commits_in_range: Option<i32>,
+    /// Author filters excluded every commit in range (the repository was not empty)
+    pub author_filter_matched_nothing: bool,
+    /// Branch the repository was analyzed on before this job switched branches (confirmed change)
+    pub branch_changed_from: Option<String>,
+    /// Phase the running job is in (`cloning`, `parsing`, `inserting`); null between phases
+    pub phase: Option<String>,
+    /// Seconds spent in the current phase so far
+    pub phase_seconds: Option<i64>,
+    /// Seconds each finished phase took
+    #[schema(value_type = Option<Object>)]
+    pub phase_durations: Option<sqlx::types::Json<HashMap<String, f64>>>,
+    #[sqlx(skip)]
+    pub tags: HashMap<String, String>,
+}
+
+fn parse_tag_filter(raw: &str) -> Result<Vec<(String, String)>, (StatusCode, String)> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once(':')
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid tag filter '{}', expected name:value", pair),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Recent analysis jobs, optionally filtered by tags and status
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    tag = "jobs",
+    params(ListJobsQuery),
+    responses(
+        (status = 200, body = [JobSummary]),
+        (status = 400, description = "Malformed tag filter", body = String)
+    )
+)]
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Query(query): Query<ListJobsQuery>,
+) -> Result<Json<Vec<JobSummary>>, (StatusCode, String)> {
+    let tag_filter = match query.tags.as_deref() {
+        Some(raw) => parse_tag_filter(raw)?,
+        None => Vec::new(),
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        r#"
+        SELECT j.id, j.repositoryId, j.status, j.totalCommits, j.processedCommits,
+               j.error, j.createdAt, j.completedAt, j.analysisStartedAt,
+               j.analysisFinishedAt, j.commitsPerSecond, j.commitsInRange,
+               j.authorFilterMatchedNothing, j.branchChangedFrom, j.phase,
+               TIMESTAMPDIFF(SECOND, j.phaseStartedAt, NOW()) AS phaseSeconds, j.phaseDurations
+        FROM AnalysisJob j
+        WHERE 1 = 1
+        "#,
+    );
+    if let Some(status) = &query.status {
+        builder.push(" AND j.status = ").push_bind(status);
+    }
+    for (name, value) in &tag_filter {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM AnalysisJobTag t WHERE t.jobId = j.id AND t.name = ")
+            .push_bind(name)
+            .push(" AND t.value = ")
+            .push_bind(value)
+            .push(")");
+    }
+    builder
+        .push(" ORDER BY j.createdAt DESC LIMIT ")
+        .push_bind(limit);
+
+    let mut jobs: Vec<JobSummary> = builder
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    attach_tags(&state.db, &mut jobs)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(jobs))
+}
+
+/// Fill in each job's tags with a single query
+async fn attach_tags(db: &sqlx::MySqlPool, jobs: &mut [JobSummary]) -> Result<(), sqlx::Error> {
+    if !jobs.is_empty() {
+        let mut tag_query = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "SELECT jobId, name, value FROM AnalysisJobTag WHERE jobId IN (",
+        );
+        let mut ids = tag_query.separated(", ");
+        for job in jobs.iter() {
+            ids.push_bind(&job.id);
+        }
+        ids.push_unseparated(")");
+
+        let tags: Vec<(String, String, String)> =
+            tag_query.build_query_as().fetch_all(db).await?;
+
+        let mut by_job: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (job_id, name, value) in tags {
+            by_job.entry(job_id).or_default().insert(name, value);
+        }
+        for job in jobs.iter_mut() {
+            if let Some(tags) = by_job.remove(&job.id) {
+                job.tags = tags;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Status, progress, error and timestamps of one analysis job
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Analysis job id")),
+    responses(
+        (status = 200, body = JobSummary),
+        (status = 404, description = "Job not found", body = String)
+    )
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<JobSummary>, (StatusCode, String)> {
+    let job: Option<JobSummary> = sqlx::query_as(
+        r#"
+        SELECT id, repositoryId, status, totalCommits, processedCommits, error, createdAt,
+               completedAt, analysisStartedAt, analysisFinishedAt, commitsPerSecond,
+               commitsInRange, authorFilterMatchedNothing, branchChangedFrom, phase,
+               TIMESTAMPDIFF(SECOND, phaseStartedAt, NOW()) AS phaseSeconds, phaseDurations
+        FROM AnalysisJob
+        WHERE id = ?
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut job = job.ok_or_else(|| (StatusCode::NOT_FOUND, format!("Job {} not found", id)))?;
+
+    attach_tags(&state.db, std::slice::from_mut(&mut job))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(job))
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelJobResponse {
+    pub job_id: String,
+    pub status: String,
+    /// The job was running in this service and was told to stop; false for jobs that
+    /// had not been picked up yet (or run elsewhere)
+    pub was_running: bool,
+}
+
+/// Cancel a pending or running job. The job is marked CANCELLED right away; a running
+/// job stops at its next check (between commits while walking or storing, or during
+/// a clone/fetch transfer). Commits stored before that are kept.
+#[utoipa::path(
+    post,
+    path = "/jobs/{id}/cancel",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Analysis job id")),
+    responses(
+        (status = 200, body = CancelJobResponse),
+        (status = 404, description = "Job not found", body = String),
+        (status = 409, description = "Job already finished", body = String)
+    )
+)]
+pub async fn cancel_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CancelJobResponse>, (StatusCode, String)> {
+    let internal = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let cancelled = job_status::transition(&state, &id, JobStatus::Cancelled, None)
+        .await
+        .map_err(internal)?;
+    if !cancelled {
+        let current: Option<String> =
+            sqlx::query_scalar("SELECT status FROM AnalysisJob WHERE id = ?")
+                .bind(&id)
+                .fetch_optional(&state.db)
+                .await
+                .map_err(internal)?;
+        return Err(match current {
+            None => (StatusCode::NOT_FOUND, format!("Job {} not found", id)),
+            Some(current) => (
+                StatusCode::CONFLICT,
+                format!("Job {} is {} and cannot be cancelled", id, current),
+            ),
+        });
+    }
+
+    let was_running = state.cancellations.cancel(&id);
+    tracing::info!("Cancelled job {} (running: {})", id, was_running);
+    Ok(Json(CancelJobResponse {
+        job_id: id,
+        status: JobStatus::Cancelled.as_str().to_string(),
+        was_running,
+    }))
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusRequest {
+    /// Up to 200 job ids
+    pub job_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusResponse {
+    pub jobs: Vec<JobSummary>,
+    /// Requested ids with no matching job
+    pub missing: Vec<String>,
+}
+
+/// Statuses for a batch of jobs in one round trip
+#[utoipa::path(
+    post,
+    path = "/jobs/status",
+    tag = "jobs",
+    request_body = JobStatusRequest,
+    responses(
+        (status = 200, body = JobStatusResponse),
+        (status = 400, description = "Too many job ids", body = String)
+    )
+)]
+pub async fn batch_status(
+    State(state): State<AppState>,
+    Json(request): Json<JobStatusRequest>,
+) -> Result<Json<JobStatusResponse>, (StatusCode, String)> {
+    if request.job_ids.len() > MAX_STATUS_BATCH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("At most {} job ids are allowed", MAX_STATUS_BATCH),
+        ));
+    }
+    if request.job_ids.is_empty() {
+        return Ok(Json(JobStatusResponse {
+            jobs: Vec::new(),
+            missing: Vec::new(),
+        }));
+    }
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        r#"
+        SELECT id, repositoryId, status, totalCommits, processedCommits, error, createdAt,
+               completedAt, analysisStartedAt, analysisFinishedAt, commitsPerSecond,
+               commitsInRange, authorFilterMatchedNothing, branchChangedFrom, phase,
+               TIMESTAMPDIFF(SECOND, phaseStartedAt, NOW()) AS phaseSeconds, phaseDurations
+        FROM AnalysisJob
+        WHERE id IN (
+        "#,
+    );
+    let mut ids = builder.separated(", ");
+    for id in &request.job_ids {
+        ids.push_bind(id);
+    }
+    ids.push_unseparated(")");
+
+    let mut jobs: Vec<JobSummary> = builder
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    attach_tags(&state.db, &mut jobs)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Answer in the order the ids were requested, once per id
+    let mut by_id: HashMap<String, JobSummary> =
+        jobs.drain(..).map(|job| (job.id.clone(), job)).collect();
+    let mut seen = std::collections::HashSet::new();
+    let mut missing = Vec::new();
+    for id in request.job_ids {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        match by_id.remove(&id) {
+            Some(job) => jobs.push(job),
+            None => missing.push(id),
+        }
+    }
+
+    Ok(Json(JobStatusResponse { jobs, missing }))
+}
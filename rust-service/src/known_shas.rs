@@ -0,0 +1,220 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use anyhow::Result;
+use futures::TryStreamExt;
+
+/// Above this many stored commits, fall back to a bloom filter (`KNOWN_SHA_SET_LIMIT`)
+const DEFAULT_EXACT_SET_LIMIT: usize = 1_000_000;
+
+/// Target false-positive rate of the bloom filter fallback
+const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// SHAs already stored for a repository, loaded once before the revwalk
+pub enum KnownShas {
+    /// Every stored SHA; membership is exact so known commits can be skipped outright
+    Exact(HashSet<String>),
+    /// Memory-bounded summary for huge repos; a hit must be confirmed against the DB
+    Bloom(BloomShas),
+}
+
+impl KnownShas {
+    /// Load the stored SHAs for a repository in a single streaming query, switching to
+    /// a bloom filter once more than `KNOWN_SHA_SET_LIMIT` have been read
+    pub async fn load(db: &sqlx::MySqlPool, repository_id: &str) -> Result<Self> {
+        let limit = std::env::var("KNOWN_SHA_SET_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_EXACT_SET_LIMIT);
+
+        let mut rows = sqlx::query_as::<_, (String,)>("SELECT sha FROM Commit WHERE repositoryId = ?")
+            .bind(repository_id)
+            .fetch(db);
+
+        let mut set = HashSet::new();
+        while let Some((sha,)) = rows.try_next().await? {
+            set.insert(sha);
+            if set.len() > limit {
+                break;
+            }
+        }
+        if set.len() <= limit {
+            tracing::info!("Loaded {} known SHAs into memory", set.len());
+            return Ok(Self::Exact(set));
+        }
+
+        // Too many to hold exactly: move what was read into a bloom filter and stream
+        // the rest straight into it
+        let mut bloom = BloomShas::new(db.clone(), repository_id, set.len() * 2);
+        for sha in set.drain() {
+            bloom.insert(&sha);
+        }
+        drop(set);
+        while let Some((sha,)) = rows.try_next().await? {
+            bloom.insert(&sha);
+        }
+        tracing::info!(
+            "Loaded {} known SHAs into a bloom filter ({} KiB)",
+            bloom.len(),
+            bloom.size_bytes() / 1024
+        );
+        Ok(Self::Bloom(bloom))
+    }
+
+    /// Which of `oids` are stored. Bloom filter hits are confirmed with one query for
+    /// the whole slice, so callers pass commits in chunks before diffing them. Blocks
+    /// on the database in bloom mode: call it from a git worker thread, not async code.
+    pub fn stored(&self, oids: &[git2::Oid]) -> Result<HashSet<git2::Oid>> {
+        match self {
+            Self::Exact(set) => Ok(oids
+                .iter()
+                .filter(|oid| set.contains(&oid.to_string()))
+                .copied()
+                .collect()),
+            Self::Bloom(bloom) => bloom.confirm(oids),
+        }
+    }
+
+    /// False when the SHA is certainly not stored, so no existence query is needed
+    pub fn might_contain(&self, sha: &str) -> bool {
+        match self {
+            Self::Exact(set) => set.contains(sha),
+            Self::Bloom(bloom) => bloom.might_contain(sha),
+        }
+    }
+}
+
+/// Bloom filter over a repository's stored SHAs that grows as SHAs are added: once a
+/// filter is full the next one gets twice its capacity and half its false-positive
+/// rate, keeping the combined rate under twice `BLOOM_FALSE_POSITIVE_RATE`
+pub struct BloomShas {
+    filters: Vec<BloomFilter>,
+    len: usize,
+    db: sqlx::MySqlPool,
+    repository_id: String,
+    runtime: tokio::runtime::Handle,
+}
+
+impl BloomShas {
+    fn new(db: sqlx::MySqlPool, repository_id: &str, capacity: usize) -> Self {
+        Self {
+            filters: vec![BloomFilter::new(capacity, BLOOM_FALSE_POSITIVE_RATE)],
+            len: 0,
+            db,
+            repository_id: repository_id.to_string(),
+            runtime: tokio::runtime::Handle::current(),
+        }
+    }
+
+    fn insert(&mut self, sha: &str) {
+        let last = self.filters.last().expect("at least one filter");
+        if last.len >= last.capacity {
+            let next = BloomFilter::new(
+                last.capacity * 2,
+                BLOOM_FALSE_POSITIVE_RATE / 2f64.powi(self.filters.len() as i32),
+            );
+            self.filters.push(next);
+        }
+        self.filters
+            .last_mut()
+            .expect("at least one filter")
+            .insert(sha);
+        self.len += 1;
+    }
+
+    fn might_contain(&self, sha: &str) -> bool {
+        self.filters.iter().any(|filter| filter.might_contain(sha))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.filters.iter().map(BloomFilter::size_bytes).sum()
+    }
+
+    fn confirm(&self, oids: &[git2::Oid]) -> Result<HashSet<git2::Oid>> {
+        let candidates: Vec<String> = oids
+            .iter()
+            .map(git2::Oid::to_string)
+            .filter(|sha| self.might_contain(sha))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let mut query =
+            sqlx::QueryBuilder::<sqlx::MySql>::new("SELECT sha FROM Commit WHERE repositoryId = ");
+        query.push_bind(&self.repository_id).push(" AND sha IN (");
+        let mut shas = query.separated(", ");
+        for sha in &candidates {
+            shas.push_bind(sha);
+        }
+        query.push(")");
+        let stored: Vec<String> = self
+            .runtime
+            .block_on(query.build_query_scalar::<String>().fetch_all(&self.db))?;
+        Ok(stored
+            .iter()
+            .filter_map(|sha| git2::Oid::from_str(sha).ok())
+            .collect())
+    }
+}
+
+/// Minimal bloom filter over string keys using double hashing
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    /// Keys it was sized for, and keys inserted so far
+    capacity: usize,
+    len: usize,
+}
+
+impl BloomFilter {
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = ((-n * false_positive_rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            capacity: expected_items.max(1),
+            len: 0,
+        }
+    }
+
+    fn hashes(key: &str) -> (u64, u64) {
+        let mut first = DefaultHasher::new();
+        key.hash(&mut first);
+        let mut second = DefaultHasher::new();
+        (key, 0x9e37_79b9_7f4a_7c15u64).hash(&mut second);
+        (first.finish(), second.finish() | 1)
+    }
+
+    fn bit_indexes(&self, key: &str) -> impl Iterator<Item = u64> + '_ {
+        let (h1, h2) = Self::hashes(key);
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        let indexes: Vec<u64> = self.bit_indexes(key).collect();
+        for idx in indexes {
+            self.bits[(idx / 64) as usize] |= 1 << (idx % 64);
+        }
+        self.len += 1;
+    }
+
+    pub fn might_contain(&self, key: &str) -> bool {
+        self.bit_indexes(key)
+            .all(|idx| self.bits[(idx / 64) as usize] & (1 << (idx % 64)) != 0)
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
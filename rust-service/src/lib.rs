@@ -0,0 +1,1256 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::Instrument;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+
+pub mod git;
+mod git_pool;
+mod github;
+mod github_webhook;
+mod identity;
+mod jira;
+mod job_events;
+pub mod job_logs;
+pub mod job_queue;
+mod job_status;
+mod jobs;
+mod llm;
+mod known_shas;
+mod metadata;
+mod models;
+mod analysis_diff;
+mod analyzed_branch;
+mod anonymize;
+mod blame;
+mod cancel;
+mod coauthors;
+mod commits;
+mod credentials;
+mod default_branch;
+pub mod export;
+mod openapi;
+mod phase;
+mod pipeline;
+mod projects;
+mod queue;
+mod s3;
+mod search;
+mod sanitize;
+mod signature;
+mod stats;
+mod store;
+mod summaries;
+pub mod summarizer;
+mod upload;
+mod webhook;
+
+use git::{GitProcessor, ParseOptions};
+use job_status::JobStatus;
+use git_pool::GitPool;
+use known_shas::KnownShas;
+use phase::Phase;
+use s3::S3Client;
+
+// Helper to sanitize strings for MySQL (remove null bytes, control chars, and ensure valid UTF-8)
+fn sanitize_for_mysql(s: &str, max_len: usize) -> String {
+    let cleaned: String = s.chars()
+        .filter(|c| {
+            // Keep printable ASCII, newlines, tabs, and common unicode
+            // Remove null bytes and other problematic control characters
+            let code = *c as u32;
+            *c == '\n' || *c == '\r' || *c == '\t' || 
+            (0x20..0x7F).contains(&code) || // Printable ASCII
+            (0x80..0xFFFF).contains(&code) // Common unicode (excluding surrogates)
+        })
+        .take(max_len)
+        .collect();
+    cleaned
+}
+
+// Read a boolean flag from the environment, falling back to a default when unset or invalid
+fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name) {
+        Ok(v) => match v.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => default,
+        },
+        Err(_) => default,
+    }
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: sqlx::MySqlPool,
+    pub work_dir: String,
+    pub git_pool: GitPool,
+    pub s3: Option<S3Client>,
+    /// Encrypts per-repository tokens at rest; None when no key is configured
+    pub credential_cipher: Option<credentials::Cipher>,
+    /// Recent log lines of each analysis job, served at `/jobs/:id/logs`
+    pub job_logs: job_logs::JobLogs,
+    /// Live status and progress of running jobs, streamed at `/jobs/:id/events`
+    pub job_events: job_events::JobEvents,
+    /// Limits exports running (and waiting) at once
+    pub exports: export::ExportLimiter,
+    /// Extra processors run after the database insert for every analyzed commit
+    pub commit_processors: Vec<Arc<dyn pipeline::CommitProcessor>>,
+    /// Maximum commit batch inserts in flight at once during analysis
+    pub db_write_limit: usize,
+    /// Blames served by `/repositories/:id/blame`, reused while the branch tip is unchanged
+    pub blame_cache: blame::BlameCache,
+    /// Identity ids resolved for author emails, reused across jobs
+    pub identity_cache: identity::IdentityCache,
+    /// Cancellation flags of the jobs running in this process
+    pub cancellations: cancel::CancelRegistry,
+    /// Limits analysis jobs running at once; the rest wait as QUEUED
+    pub job_queue: job_queue::JobQueue,
+}
+
+impl AppState {
+    /// State for a service using `db`, with the remaining parts configured from the
+    /// environment. Background workers (export/job recovery, summarizer) aren't started.
+    pub async fn new(
+        db: sqlx::MySqlPool,
+        work_dir: String,
+        job_logs: job_logs::JobLogs,
+    ) -> Result<Self> {
+        let max_connections = db.options().get_max_connections();
+        // Leave one connection free for status/progress updates while inserts are in flight
+        let db_write_limit = std::env::var("DB_MAX_INFLIGHT_WRITES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or_else(|| max_connections.saturating_sub(1) as usize)
+            .clamp(1, max_connections.max(1) as usize);
+        tracing::info!(
+            "Commit batch inserts limited to {} in flight",
+            db_write_limit
+        );
+
+        let credential_cipher = credentials::Cipher::from_env()?;
+        if credential_cipher.is_none() {
+            tracing::info!(
+                "CREDENTIAL_ENCRYPTION_KEY not set, stored repository credentials disabled"
+            );
+        }
+
+        Ok(AppState {
+            db,
+            work_dir,
+            git_pool: GitPool::from_env(),
+            s3: S3Client::new().await,
+            credential_cipher,
+            job_logs,
+            job_events: job_events::JobEvents::default(),
+            exports: export::ExportLimiter::from_env(),
+            // Optional sinks and custom `CommitProcessor`s are registered here
+            commit_processors: queue::QueueSink::from_env().await.into_iter().collect(),
+            db_write_limit,
+            blame_cache: blame::BlameCache::from_env(),
+            identity_cache: identity::IdentityCache::from_env(),
+            cancellations: cancel::CancelRegistry::default(),
+            job_queue: job_queue::JobQueue::from_env(),
+        })
+    }
+}
+
+/// All HTTP routes of the service
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/version", get(version))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/analyze", post(analyze_repository))
+        .route("/jobs", get(jobs::list_jobs))
+        .route("/jobs/status", post(jobs::batch_status))
+        .route("/jobs/:id", get(jobs::get_job))
+        .route("/jobs/:id/cancel", post(jobs::cancel_job))
+        .route("/jobs/:id/logs", get(job_logs::get_job_logs))
+        .route("/jobs/:id/events", get(job_events::job_events))
+        .route("/jobs/:id/commits", get(commits::list_job_commits))
+        .route(
+            "/analyze/bundle",
+            post(upload::analyze_bundle).layer(DefaultBodyLimit::max(upload::max_upload_bytes())),
+        )
+        .route(
+            "/analyze/tarball",
+            post(upload::analyze_tarball).layer(DefaultBodyLimit::max(upload::max_upload_bytes())),
+        )
+        .route(
+            "/repositories/:id/stats/jira-projects",
+            get(stats::jira_projects),
+        )
+        .route(
+            "/repositories/:id/duplicates",
+            get(stats::duplicate_commits),
+        )
+        .route("/repositories/:id/stats/punchcard", get(stats::punchcard))
+        .route(
+            "/repositories/:id/stats/directories",
+            get(stats::directory_churn),
+        )
+        .route(
+            "/repositories/:id/stats/co-authors",
+            get(stats::co_author_pairs),
+        )
+        .route(
+            "/repositories/:id/stats/large-files",
+            get(stats::large_files),
+        )
+        .route(
+            "/repositories/:id/stats/author-churn",
+            get(stats::author_churn),
+        )
+        .route(
+            "/repositories/:id/stats/author-cadence",
+            get(stats::author_cadence_stats),
+        )
+        .route(
+            "/repositories/:id/stats/template-compliance",
+            get(stats::template_compliance),
+        )
+        .route("/repositories/:id/blame", get(blame::file_blame))
+        .route(
+            "/repositories/:id/analysis-diff",
+            get(analysis_diff::analysis_diff),
+        )
+        .route(
+            "/repositories/:id/signature-mismatches",
+            get(stats::signature_mismatches),
+        )
+        .route("/projects", post(projects::create_project))
+        .route("/projects/:id", get(projects::get_project))
+        .route(
+            "/projects/:id/repositories",
+            put(projects::set_project_repositories),
+        )
+        .route("/projects/:id/stats", get(projects::project_stats))
+        .route("/search/commits", get(search::search_commits))
+        .route("/commits/:id", get(commits::get_commit))
+        .route("/commits/:id/message", get(commits::full_message))
+        .route("/commits/:id/diff", get(commits::commit_diff))
+        .route("/commits/:id/file-diffs", get(commits::file_diffs))
+        .route(
+            "/repositories/:id/summary-context",
+            put(summaries::set_repository_context),
+        )
+        .route(
+            "/repositories/:id/credential",
+            put(credentials::set_credential).delete(credentials::delete_credential),
+        )
+        .route("/summaries/claim", post(summaries::claim))
+        .route("/summaries/:id", post(summaries::complete))
+        .route("/exports", post(export::create_export))
+        .route("/exports/:id", get(export::get_export))
+        .route("/webhooks/github", post(github_webhook::github_webhook))
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "service",
+    responses((status = 200, description = "Service is up"))
+)]
+async fn health() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    /// Crate version from Cargo.toml
+    pub version: String,
+    /// Commit the binary was built from (`unknown` outside a git checkout)
+    pub git_commit: String,
+    /// The build had uncommitted changes to tracked files
+    pub git_dirty: bool,
+    pub built_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Build metadata, for checking which build is deployed
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "service",
+    responses((status = 200, body = VersionInfo))
+)]
+async fn version() -> Json<VersionInfo> {
+    Json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("BUILD_GIT_COMMIT").to_string(),
+        git_dirty: env!("BUILD_GIT_DIRTY") == "true",
+        built_at: env!("BUILD_TIMESTAMP")
+            .parse()
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0)),
+    })
+}
+
+// Stored without its secrets in `AnalysisJob.request`, to resume the job after a restart
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyzeRequest {
+    pub job_id: String,
+    pub repo_url: String,
+    pub branch: String,
+    #[serde(skip_serializing)]
+    pub credential_token: Option<String>,
+    /// Private key (PEM / OpenSSH text) for SSH repository URLs
+    #[serde(skip_serializing)]
+    pub ssh_private_key: Option<String>,
+    /// Path of a private key file on the service host, instead of `sshPrivateKey`
+    /// (default `SSH_KEY_PATH`)
+    pub ssh_key_path: Option<String>,
+    /// Passphrase of the SSH key, if it is encrypted (default `SSH_KEY_PASSPHRASE`)
+    #[serde(skip_serializing)]
+    pub ssh_key_passphrase: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub author_filter: Option<String>,
+    pub all_branches: Option<bool>,
+    /// Only analyze commits reachable from semver release tags (`v1.2.3`); each commit
+    /// records the first release containing it. Takes precedence over `allBranches`.
+    pub release_only: Option<bool>,
+    /// Only analyze squash-merge commits (single-parent mainline commits titled like
+    /// `Fix login (#123)` or ending in `See merge request group/project!45`), one per PR
+    pub squash_merges_only: Option<bool>,
+    /// Follow only first-parent links (`git log --first-parent`), leaving out the commits
+    /// of merged branches, for a linear changelog of a mainline (default `FIRST_PARENT`)
+    pub first_parent: Option<bool>,
+    /// Only analyze commits touching these paths: git pathspecs such as `services/api` or
+    /// globs like `services/*/Cargo.toml`, for one service of a monorepo. Stored paths and
+    /// line counts cover only the matching files (default `PATH_FILTERS`, comma-separated).
+    pub path_filters: Option<Vec<String>>,
+    pub normalize_line_endings: Option<bool>,
+    /// Lowercase author emails before filtering and storage (default `NORMALIZE_AUTHOR_EMAILS`)
+    pub normalize_author_emails: Option<bool>,
+    pub jira_base_url: Option<String>,
+    pub code_extensions: Option<Vec<String>>,
+    /// Only analyze authors with an email in these domains
+    pub email_domains: Option<Vec<String>>,
+    /// Skip authors with an email in these domains
+    pub exclude_email_domains: Option<Vec<String>>,
+    /// Verify commit signatures against the configured keyring (default `VERIFY_SIGNATURES`)
+    pub verify_signatures: Option<bool>,
+    /// Look up GitHub's verification reason for signed commits of github.com repositories
+    /// (default `GITHUB_VERIFICATION`; needs `credentialToken` or a stored token)
+    pub github_verification: Option<bool>,
+    /// Map author emails to canonical identity ids with `IDENTITY_SERVICE_URL`, when one
+    /// is configured (default true)
+    pub resolve_identities: Option<bool>,
+    /// Store commit metadata without per-commit diffs (file counts and paths left null)
+    pub skip_diffs: Option<bool>,
+    /// Store each changed file's unified diff text in CommitDiff, e.g. for summarizers
+    /// (default `INCLUDE_DIFFS`; ignored with `skipDiffs`)
+    pub include_diffs: Option<bool>,
+    /// Longest diff stored per file, in bytes (default `FILE_DIFF_MAX_BYTES`, 64 KiB)
+    pub diff_max_bytes: Option<usize>,
+    /// Skip commits changing fewer files than this (ignored with `skipDiffs`)
+    pub min_files: Option<usize>,
+    /// Skip commits changing more files than this (ignored with `skipDiffs`)
+    pub max_files: Option<usize>,
+    /// Store at most this many changed paths per commit; the true count is kept in
+    /// `changedPathsTotal` metadata (default `MAX_STORED_PATHS`, unlimited)
+    pub max_stored_paths: Option<usize>,
+    /// Only analyze signed (`true`) or unsigned (`false`) commits, e.g. to audit
+    /// unsigned commits on a protected branch
+    pub signed: Option<bool>,
+    /// Only walk commits added since the repository's last completed single-branch run
+    /// on the same branch (default `INCREMENTAL_ANALYSIS`, off). Only unfiltered runs that
+    /// stored every commit move that starting point, so history left out by filters
+    /// (dates, authors, paths, ...) or dead-lettered commits is walked again.
+    pub incremental: Option<bool>,
+    /// Count a renamed file as one change instead of a delete plus an add
+    /// (default `DETECT_RENAMES`, off; implied by `renameThreshold` or `detectCopies`)
+    pub detect_renames: Option<bool>,
+    /// Similarity percentage for rename/copy detection (default `RENAME_THRESHOLD`, 50)
+    pub rename_threshold: Option<u16>,
+    /// Also detect files copied from a file modified in the same commit
+    pub detect_copies: Option<bool>,
+    /// Context lines around diff hunks (default `DIFF_CONTEXT_LINES`, 3)
+    pub diff_context_lines: Option<u32>,
+    /// Fail the job instead of completing it with a warning flag when the author filters
+    /// exclude every commit in range (default `FAIL_ON_EMPTY_AUTHOR_FILTER`)
+    pub fail_on_empty_author_filter: Option<bool>,
+    /// Regex commit messages must match (e.g. `^(feat|fix|docs|chore)(\(.+\))?: `);
+    /// non-matching non-merge commits get `templateViolation` (default `COMMIT_TEMPLATE_REGEX`)
+    pub commit_template: Option<String>,
+    /// Analyze this branch even though the repository was last analyzed on another one
+    /// (otherwise the job fails while `REQUIRE_BRANCH_CONFIRMATION` is on)
+    pub confirm_branch_change: Option<bool>,
+    /// Diff the root commit against the empty tree or count it as zero churn
+    /// (default `ROOT_COMMIT_POLICY`)
+    pub root_commit_policy: Option<git::RootCommitPolicy>,
+    /// Credit merge commits with their first-parent diff, only the files that differ
+    /// from every parent (`combined`), or nothing (`skip`) (default `MERGE_DIFF_STRATEGY`)
+    pub merge_diff_strategy: Option<git::MergeDiffStrategy>,
+    /// Analyze (`keep`) or drop (`skip`) commits dated in the future by a skewed clock
+    /// (default `FUTURE_COMMIT_POLICY`)
+    pub future_commit_policy: Option<git::FutureCommitPolicy>,
+    /// URL to POST progress to as the job crosses milestones (default `PROGRESS_WEBHOOK_URL`)
+    pub progress_webhook_url: Option<String>,
+    /// Percent between progress notifications (default `PROGRESS_WEBHOOK_STEP`, 25)
+    pub progress_webhook_step: Option<u32>,
+    /// Context for summarizers (project description, language), overriding the repository's
+    pub summary_context: Option<summaries::SummaryContext>,
+    /// Free-form labels (e.g. team, cost center) stored on the job for filtering
+    pub tags: Option<HashMap<String, String>>,
+    /// Set by `/analyze/bundle` and `/analyze/tarball` for the repository they received
+    #[schema(ignore)]
+    pub(crate) upload: Option<upload::UploadedFile>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct AnalyzeResponse {
+    pub job_id: String,
+    pub status: String,
+    pub message: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/analyze",
+    tag = "analysis",
+    request_body = AnalyzeRequest,
+    responses(
+        (status = 200, description = "Analysis started in background", body = AnalyzeResponse),
+        (status = 404, description = "Job not found", body = String),
+        (status = 409, description = "Job is running or finished", body = String),
+        (status = 500, description = "Job could not be started", body = String)
+    )
+)]
+async fn analyze_repository(
+    State(state): State<AppState>,
+    Json(request): Json<AnalyzeRequest>,
+) -> Result<Json<AnalyzeResponse>, (StatusCode, String)> {
+    tracing::info!("Starting analysis for job: {}", request.job_id);
+    tracing::info!("Repo URL: {}, Branch: {}", request.repo_url, request.branch);
+    tracing::info!("Token present: {}", request.credential_token.is_some());
+    if request.upload.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "upload is only set by /analyze/bundle and /analyze/tarball".to_string(),
+        ));
+    }
+    validate_request(&request)?;
+
+    let job_id = request.job_id.clone();
+    spawn_analysis(state, request).await?;
+
+    Ok(Json(AnalyzeResponse {
+        job_id,
+        status: "PROCESSING".to_string(),
+        message: "Analysis started in background".to_string(),
+    }))
+}
+
+/// Checks every entry point runs on a request before its job is started
+fn validate_request(request: &AnalyzeRequest) -> Result<(), (StatusCode, String)> {
+    ssh_key(request).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let Some(tags) = &request.tags {
+        jobs::validate_tags(tags).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    commit_template(request).map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    if let Some(url) = &request.progress_webhook_url {
+        webhook::validate_url(url).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    Ok(())
+}
+
+/// Start a validated analysis request: queue its job, store its tags and summary
+/// context, and run it in the background once a job slot is free
+async fn spawn_analysis(
+    state: AppState,
+    request: AnalyzeRequest,
+) -> Result<(), (StatusCode, String)> {
+    // Update job status to QUEUED
+    job_status::start(&state, &request.job_id).await?;
+
+    if let Some(tags) = &request.tags {
+        jobs::store_tags(&state.db, &request.job_id, tags)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    if let Some(context) = &request.summary_context {
+        jobs::store_summary_context(&state.db, &request.job_id, context)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    sqlx::query("UPDATE AnalysisJob SET request = ? WHERE id = ?")
+        .bind(stored_request(&request))
+        .bind(&request.job_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    run_queued(state, request);
+    Ok(())
+}
+
+/// A request as stored on its job: without its secrets, but naming the ones it carried
+/// (`withheldSecrets`) so a resumed job can tell it would run without them
+fn stored_request(request: &AnalyzeRequest) -> Option<String> {
+    let mut value = serde_json::to_value(request).ok()?;
+    let withheld: Vec<&str> = [
+        ("credentialToken", request.credential_token.is_some()),
+        ("sshPrivateKey", request.ssh_private_key.is_some()),
+        ("sshKeyPassphrase", request.ssh_key_passphrase.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, given)| given.then_some(name))
+    .collect();
+    if !withheld.is_empty() {
+        value["withheldSecrets"] = withheld.into();
+    }
+    Some(value.to_string())
+}
+
+/// Run a QUEUED job in the background once a job slot is free
+fn run_queued(state: AppState, request: AnalyzeRequest) {
+    // Clone values before spawning
+    let job_id = request.job_id.clone();
+    let state_clone = state.clone();
+    let registration = state.cancellations.register(&job_id);
+    let upload = request.upload.clone();
+
+    let span = tracing::info_span!("analysis", job_id = %job_id);
+    tokio::spawn(
+        async move {
+            let cancel = registration.token();
+            let result = match state.job_queue.wait_turn(&state, &job_id).await {
+                Ok(Some(_slot)) => match &upload {
+                    Some(upload) => upload::process_upload(&state, &request, upload, cancel).await,
+                    None => process_analysis(state_clone, request, cancel).await,
+                },
+                Ok(None) => {
+                    tracing::info!("Job left the queue before it started");
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if cancel::is_cancelled(&e) => tracing::info!("Analysis cancelled"),
+                Err(e) => {
+                    tracing::error!("Analysis failed: {}", e);
+                    fail_job(&state, &job_id, &e.to_string()).await;
+                }
+                Ok(()) => {}
+            }
+            if let Some(upload) = upload {
+                upload.remove(&state.work_dir).await;
+            }
+            drop(registration);
+        }
+        .instrument(span),
+    );
+}
+
+/// Rename/copy detection for a job; asking for a threshold or copies turns it on
+fn rename_detection(request: &AnalyzeRequest) -> Option<git::RenameDetection> {
+    let enabled = request.detect_renames.unwrap_or_else(|| {
+        request.rename_threshold.is_some()
+            || request.detect_copies == Some(true)
+            || env_flag("DETECT_RENAMES", false)
+    });
+    enabled.then(|| git::RenameDetection {
+        threshold: request
+            .rename_threshold
+            .or_else(|| {
+                std::env::var("RENAME_THRESHOLD")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+            })
+            .unwrap_or(50)
+            .min(100),
+        copies: request
+            .detect_copies
+            .unwrap_or_else(|| env_flag("DETECT_COPIES", false)),
+    })
+}
+
+/// Pathspecs a job is limited to: the request's, else `PATH_FILTERS` (comma-separated)
+fn path_filters(request: &AnalyzeRequest) -> Vec<String> {
+    let filters = match &request.path_filters {
+        Some(filters) => filters.clone(),
+        None => std::env::var("PATH_FILTERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::to_string)
+            .collect(),
+    };
+    filters
+        .iter()
+        .map(|f| f.trim().trim_start_matches("./").to_string())
+        .filter(|f| !f.is_empty())
+        .collect()
+}
+
+/// SSH key of a job: the request's key text or key file, falling back to `SSH_KEY_PATH`
+fn ssh_key(request: &AnalyzeRequest) -> Result<Option<git::SshKey>, String> {
+    let env = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+    let source = match (&request.ssh_private_key, &request.ssh_key_path) {
+        (Some(_), Some(_)) => {
+            return Err("Give either sshPrivateKey or sshKeyPath, not both".to_string())
+        }
+        (Some(pem), None) => git::SshKeySource::Pem(pem.clone()),
+        (None, path) => match path.clone().or_else(|| env("SSH_KEY_PATH")) {
+            Some(path) if std::path::Path::new(&path).is_file() => {
+                git::SshKeySource::Path(path.into())
+            }
+            Some(path) => return Err(format!("SSH key file not found: {}", path)),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(git::SshKey {
+        source,
+        passphrase: request
+            .ssh_key_passphrase
+            .clone()
+            .or_else(|| env("SSH_KEY_PASSPHRASE")),
+    }))
+}
+
+/// Commit message template of a job; the request's beats `COMMIT_TEMPLATE_REGEX`
+fn commit_template(request: &AnalyzeRequest) -> Result<Option<regex::Regex>> {
+    let pattern = request
+        .commit_template
+        .clone()
+        .or_else(|| std::env::var("COMMIT_TEMPLATE_REGEX").ok())
+        .filter(|pattern| !pattern.trim().is_empty());
+    pattern
+        .map(|pattern| {
+            regex::Regex::new(&pattern)
+                .with_context(|| format!("Invalid commit template regex: {}", pattern))
+        })
+        .transpose()
+}
+
+/// Mark a job as FAILED with the given error message
+async fn fail_job(state: &AppState, job_id: &str, error: &str) {
+    let _ = job_status::transition(state, job_id, JobStatus::Failed, Some(error)).await;
+}
+
+async fn process_analysis(
+    state: AppState,
+    mut request: AnalyzeRequest,
+    cancel: cancel::CancelToken,
+) -> Result<()> {
+    let processor = GitProcessor::new(&state.work_dir)
+        .with_cancel(cancel)
+        .with_ssh_key(ssh_key(&request).map_err(anyhow::Error::msg)?);
+    let all_branches = request.all_branches.unwrap_or(false);
+
+    // An empty branch (or "HEAD") means the remote's default branch, cached on the Repository
+    let use_default_branch = default_branch::is_requested(&request.branch);
+    let repository_id: String =
+        sqlx::query_scalar("SELECT repositoryId FROM AnalysisJob WHERE id = ?")
+            .bind(&request.job_id)
+            .fetch_one(&state.db)
+            .await?;
+
+    // Fall back to the token stored for this repository when the request carries none
+    if request.credential_token.is_none() {
+        request.credential_token = credentials::stored_token(&state, &repository_id).await?;
+        if request.credential_token.is_some() {
+            tracing::info!("Using stored credential for repository {}", repository_id);
+        }
+    }
+
+    if use_default_branch {
+        request.branch = default_branch::resolve(
+            &state,
+            &processor,
+            &repository_id,
+            &request.repo_url,
+            request.credential_token.as_deref(),
+        )
+        .await?;
+    }
+
+    // Clone or fetch repository
+    tracing::info!("Cloning/fetching repository...");
+    let job_id = request.job_id.clone();
+    let repo_path = phase::run(&state.db, &job_id, Phase::Cloning, processor.cancel_token(), async {
+        let repo_path = {
+            let processor = processor.clone();
+            let url = request.repo_url.clone();
+            let branch = request.branch.clone();
+            let token = request.credential_token.clone();
+            state
+                .git_pool
+                .run(move || processor.clone_or_fetch(&url, &branch, token.as_deref(), all_branches))
+                .await?
+        };
+        tracing::info!("Repository ready at {:?}", repo_path);
+
+        // The clone/fetch just saw the remote's HEAD; refresh the cache if it moved
+        let cloned_default = {
+            let processor = processor.clone();
+            let repo_path = repo_path.clone();
+            state
+                .git_pool
+                .run(move || processor.cloned_default_branch(&repo_path))
+                .await?
+        };
+        if let Some(current) = cloned_default {
+            if use_default_branch && current != request.branch {
+                tracing::info!(
+                    "Default branch changed from '{}' to '{}', fetching it",
+                    request.branch,
+                    current
+                );
+                default_branch::record(&state, &repository_id, &current).await?;
+                request.branch = current;
+                let processor = processor.clone();
+                let url = request.repo_url.clone();
+                let branch = request.branch.clone();
+                let token = request.credential_token.clone();
+                state
+                    .git_pool
+                    .run(move || processor.clone_or_fetch(&url, &branch, token.as_deref(), all_branches))
+                    .await?;
+            }
+        }
+        Ok(repo_path)
+    })
+    .await?;
+
+    processor.check_cancelled()?;
+
+    // Stored commits belong to one branch; don't silently mix in another branch's history
+    let release_only = request.release_only.unwrap_or(false);
+    if analyzed_branch::is_tracked(all_branches, release_only) {
+        analyzed_branch::check(
+            &state,
+            &request.job_id,
+            &repository_id,
+            &request.branch,
+            request.confirm_branch_change.unwrap_or(false),
+        )
+        .await?;
+    }
+
+    match analyze_local_repository(&state, &request, &processor, &repo_path).await {
+        // A missing object means the clone on disk is damaged: re-clone once and retry
+        Err(e) if git::is_missing_object(&e) && env_flag("RECLONE_ON_CORRUPTION", true) => {
+            tracing::warn!("Clone looks corrupt ({}), re-cloning and retrying once", e);
+            if !job_status::transition(&state, &request.job_id, JobStatus::Cloning, None).await? {
+                return Ok(());
+            }
+            let repo_path = {
+                let cancel = processor.cancel_token().clone();
+                let processor = processor.clone();
+                let url = request.repo_url.clone();
+                let branch = request.branch.clone();
+                let token = request.credential_token.clone();
+                phase::run(
+                    &state.db,
+                    &request.job_id,
+                    Phase::Cloning,
+                    &cancel,
+                    state
+                        .git_pool
+                        .run(move || processor.reclone(&url, &branch, token.as_deref())),
+                )
+                .await?
+            };
+            analyze_local_repository(&state, &request, &processor, &repo_path).await
+        }
+        result => result,
+    }
+}
+
+/// Throttles `processedCommits` writes (and `progress` events) to one every
+/// `PROGRESS_UPDATE_INTERVAL` commits and feeds the job's progress webhook, if any
+struct ProgressReporter {
+    every: usize,
+    reported: usize,
+    total: usize,
+    webhook: Option<webhook::ProgressWebhook>,
+}
+
+impl ProgressReporter {
+    fn from_env(total: usize, webhook: Option<webhook::ProgressWebhook>) -> Self {
+        Self {
+            every: std::env::var("PROGRESS_UPDATE_INTERVAL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1usize)
+                .max(1),
+            reported: 0,
+            total,
+            webhook,
+        }
+    }
+
+    /// Write the count once it moved by at least the interval (or `force` for the final count)
+    async fn report(
+        &mut self,
+        state: &AppState,
+        job_id: &str,
+        processed: usize,
+        force: bool,
+    ) -> Result<()> {
+        if let Some(webhook) = &mut self.webhook {
+            webhook.progress(processed);
+        }
+        if processed == self.reported || (!force && processed - self.reported < self.every) {
+            return Ok(());
+        }
+        update_processed(state, job_id, processed).await?;
+        state.job_events.progress(job_id, processed, self.total);
+        self.reported = processed;
+        Ok(())
+    }
+}
+
+/// Record how many commits of the job have been handled so far
+async fn update_processed(state: &AppState, job_id: &str, processed: usize) -> Result<()> {
+    sqlx::query("UPDATE AnalysisJob SET processedCommits = ? WHERE id = ?")
+        .bind(processed as i32)
+        .bind(job_id)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+/// Parse an already-cloned repository and store its commits for the job
+pub async fn analyze_local_repository(
+    state: &AppState,
+    request: &AnalyzeRequest,
+    processor: &GitProcessor,
+    repo_path: &std::path::Path,
+) -> Result<()> {
+    let all_branches = request.all_branches.unwrap_or(false);
+
+    // Update status to PARSING
+    tracing::info!("Updating status to PARSING...");
+    if !job_status::transition(state, &request.job_id, JobStatus::Parsing, None).await? {
+        return Ok(());
+    }
+    tracing::info!("Status updated to PARSING");
+
+    // Throughput covers parse + insert
+    let phase_started = std::time::Instant::now();
+    sqlx::query("UPDATE AnalysisJob SET analysisStartedAt = NOW() WHERE id = ?")
+        .bind(&request.job_id)
+        .execute(&state.db)
+        .await?;
+
+    // Get repository ID from job
+    tracing::info!("Getting repository ID...");
+    let row: (String,) = sqlx::query_as("SELECT repositoryId FROM AnalysisJob WHERE id = ?")
+        .bind(&request.job_id)
+        .fetch_one(&state.db)
+        .await?;
+    let repository_id = row.0;
+    tracing::info!("Repository ID: {}", repository_id);
+
+    // Incremental runs hide the history behind the last analyzed tip of this branch
+    let tracked = analyzed_branch::is_tracked(all_branches, request.release_only.unwrap_or(false));
+    let since_sha: Option<String> = if tracked
+        && request
+            .incremental
+            .unwrap_or_else(|| env_flag("INCREMENTAL_ANALYSIS", false))
+    {
+        sqlx::query_scalar(
+            "SELECT lastAnalyzedSha FROM Repository WHERE id = ? AND analyzedBranch = ?",
+        )
+        .bind(&repository_id)
+        .bind(&request.branch)
+        .fetch_optional(&state.db)
+        .await?
+        .flatten()
+    } else {
+        None
+    };
+
+    // Parse commits
+    if request.release_only.unwrap_or(false) {
+        tracing::info!("Parsing commits from release tags...");
+    } else if all_branches {
+        tracing::info!("Parsing commits from all branches...");
+    } else {
+        tracing::info!("Parsing commits from branch: {}...", request.branch);
+    }
+    let options = ParseOptions {
+        repository_id: repository_id.clone(),
+        start_date: request.start_date.clone(),
+        end_date: request.end_date.clone(),
+        author_filter: request.author_filter.clone(),
+        all_branches,
+        normalize_line_endings: request
+            .normalize_line_endings
+            .unwrap_or_else(|| env_flag("NORMALIZE_LINE_ENDINGS", true)),
+        code_extensions: request
+            .code_extensions
+            .as_deref()
+            .map(git::normalize_extensions)
+            .unwrap_or_else(git::default_code_extensions),
+        email_domains: request
+            .email_domains
+            .as_deref()
+            .map(git::normalize_domains)
+            .unwrap_or_default(),
+        exclude_email_domains: request
+            .exclude_email_domains
+            .as_deref()
+            .map(git::normalize_domains)
+            .unwrap_or_default(),
+        signature_keyring: request
+            .verify_signatures
+            .unwrap_or_else(|| env_flag("VERIFY_SIGNATURES", false))
+            .then(signature::Keyring::from_env),
+        skip_diffs: request.skip_diffs.unwrap_or(false),
+        diff_text: request
+            .include_diffs
+            .unwrap_or_else(|| env_flag("INCLUDE_DIFFS", false))
+            .then(|| git::DiffTextLimits {
+                per_file: request.diff_max_bytes.unwrap_or_else(|| {
+                    std::env::var("FILE_DIFF_MAX_BYTES")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(64 * 1024)
+                }),
+                per_commit: std::env::var("COMMIT_DIFF_TEXT_MAX_BYTES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(1024 * 1024),
+            }),
+        lowercase_author_emails: request
+            .normalize_author_emails
+            .unwrap_or_else(|| env_flag("NORMALIZE_AUTHOR_EMAILS", true)),
+        exclude_linguist_paths: env_flag("EXCLUDE_LINGUIST_PATHS", true),
+        diff_timeout: std::env::var("DIFF_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .or(Some(10_000))
+            .filter(|ms| *ms > 0)
+            .map(std::time::Duration::from_millis),
+        min_files: request.min_files,
+        max_files: request.max_files,
+        signed: request.signed,
+        max_stored_paths: request.max_stored_paths.or_else(|| {
+            std::env::var("MAX_STORED_PATHS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+        }),
+        root_commit_policy: request
+            .root_commit_policy
+            .unwrap_or_else(git::RootCommitPolicy::from_env),
+        merge_diffs: request
+            .merge_diff_strategy
+            .unwrap_or_else(git::MergeDiffStrategy::from_env),
+        future_commits: request
+            .future_commit_policy
+            .unwrap_or_else(git::FutureCommitPolicy::from_env),
+        future_tolerance: chrono::Duration::hours(
+            std::env::var("FUTURE_COMMIT_TOLERANCE_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24),
+        ),
+        renames: rename_detection(request),
+        release_only: request.release_only.unwrap_or(false),
+        squash_merges_only: request.squash_merges_only.unwrap_or(false),
+        first_parent: request
+            .first_parent
+            .unwrap_or_else(|| env_flag("FIRST_PARENT", false)),
+        path_filters: path_filters(request),
+        empty_title_placeholder: std::env::var("EMPTY_MESSAGE_TITLE")
+            .unwrap_or_else(|_| "(no message)".to_string()),
+        commit_template: commit_template(request)?,
+        context_lines: request.diff_context_lines.or_else(|| {
+            std::env::var("DIFF_CONTEXT_LINES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+        }),
+        since_sha,
+    };
+    // Load already-stored SHAs once so known commits are skipped without per-commit queries
+    let known_shas = Arc::new(KnownShas::load(&state.db, &repository_id).await?);
+    let excludes_commits = options.excludes_commits();
+    let (parsed, author_filter_matched_nothing) = {
+        let cancel = processor.cancel_token().clone();
+        let processor = processor.clone();
+        let repo_path = repo_path.to_path_buf();
+        let branch = request.branch.clone();
+        let known_shas = Arc::clone(&known_shas);
+        phase::run(
+            &state.db,
+            &request.job_id,
+            Phase::Parsing,
+            &cancel,
+            state.git_pool.run(move || {
+                let parsed =
+                    processor.parse_commits(&repo_path, &branch, &options, Some(&known_shas))?;
+                let matched_nothing = parsed.author_filter_matched_nothing(&options);
+                Ok((parsed, matched_nothing))
+            }),
+        )
+        .await?
+    };
+
+    // Tell "no commits in range" apart from "author filter too strict"; the tip lets
+    // later runs report what changed since this one
+    sqlx::query(
+        "UPDATE AnalysisJob SET commitsInRange = ?, authorFilterMatchedNothing = ?, tipSha = ? WHERE id = ?",
+    )
+    .bind(parsed.in_range as i32)
+    .bind(author_filter_matched_nothing)
+    .bind(&parsed.tip)
+    .bind(&request.job_id)
+    .execute(&state.db)
+    .await?;
+    if author_filter_matched_nothing {
+        tracing::warn!(
+            "Author filters matched none of the {} commits in range",
+            parsed.in_range
+        );
+        if request
+            .fail_on_empty_author_filter
+            .unwrap_or_else(|| env_flag("FAIL_ON_EMPTY_AUTHOR_FILTER", false))
+        {
+            anyhow::bail!(
+                "Author filters matched none of the {} commits in range",
+                parsed.in_range
+            );
+        }
+    }
+
+    let commits = parsed.commits;
+    let total_commits = commits.len();
+    tracing::info!("Found {} commits to process", total_commits);
+
+    // Update total commits count
+    tracing::info!("Updating total commits count...");
+    match sqlx::query("UPDATE AnalysisJob SET totalCommits = ? WHERE id = ?")
+        .bind(total_commits as i32)
+        .bind(&request.job_id)
+        .execute(&state.db)
+        .await
+    {
+        Ok(result) => tracing::info!("Total commits count updated, rows affected: {}", result.rows_affected()),
+        Err(e) => {
+            tracing::error!("Failed to update total commits: {:?}", e);
+            return Err(e.into());
+        }
+    }
+
+    // Sleep briefly to let connection settle
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // Test a simple query first
+    tracing::debug!("Testing connection with simple query...");
+    match sqlx::query_as::<_, (i64,)>("SELECT 1")
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok(_) => tracing::debug!("Connection test passed"),
+        Err(e) => {
+            tracing::error!("Connection test failed: {:?}", e);
+            return Err(e.into());
+        }
+    }
+
+    // Resolve the JIRA base URL once per job; a per-request override beats the env default
+    let jira_default_scheme =
+        std::env::var("JIRA_DEFAULT_SCHEME").unwrap_or_else(|_| "https".into());
+    let jira_base_url = request
+        .jira_base_url
+        .clone()
+        .or_else(|| std::env::var("JIRA_BASE_URL").ok())
+        .filter(|raw| !raw.trim().is_empty())
+        .and_then(|raw| {
+            let normalized = jira::normalize_base_url(&raw, &jira_default_scheme);
+            if normalized.is_none() {
+                tracing::warn!("Ignoring invalid JIRA base URL: {}", raw);
+            }
+            normalized
+        });
+
+    let github_verification = request
+        .github_verification
+        .unwrap_or_else(|| env_flag("GITHUB_VERIFICATION", false))
+        .then(|| {
+            github::GithubVerification::for_job(
+                &state.db,
+                &repository_id,
+                &request.repo_url,
+                request.credential_token.as_deref(),
+            )
+        })
+        .flatten()
+        .map(|enrichment| Arc::new(enrichment) as Arc<dyn pipeline::CommitProcessor>);
+    if request.github_verification == Some(true) && github_verification.is_none() {
+        tracing::warn!("GitHub verification requested, but the repository is not on GitHub or no token is available");
+    }
+
+    let identity_resolution = request
+        .resolve_identities
+        .unwrap_or(true)
+        .then(|| identity::IdentityResolution::for_job(&state.db, &state.identity_cache))
+        .flatten()
+        .map(|enrichment| Arc::new(enrichment) as Arc<dyn pipeline::CommitProcessor>);
+
+    // Insert commits in batches, pipelining up to `db_write_limit` batches against the pool
+    let context = Arc::new(store::CommitContext {
+        repository_id: repository_id.clone(),
+        job_id: request.job_id.clone(),
+        jira_base_url,
+        known_shas,
+    });
+    let pipeline = Arc::new(
+        pipeline::Pipeline::default()
+            .with(Arc::new(pipeline::StoreProcessor::new(
+                state.clone(),
+                context,
+            )))
+            .with(Arc::new(pipeline::MetricsProcessor::default()))
+            .with_all(github_verification)
+            .with_all(identity_resolution)
+            .with_all(state.commit_processors.iter().cloned()),
+    );
+    let write_limit = Arc::new(tokio::sync::Semaphore::new(state.db_write_limit));
+    let mut inserts = tokio::task::JoinSet::new();
+    let mut processed = 0usize;
+    let mut progress = ProgressReporter::from_env(
+        total_commits,
+        webhook::ProgressWebhook::for_job(
+            request.progress_webhook_url.as_deref(),
+            request.progress_webhook_step,
+            &request.job_id,
+            total_commits,
+        ),
+    );
+
+    let batch_size = store::commit_batch_size();
+
+    phase::run(&state.db, &request.job_id, Phase::Inserting, processor.cancel_token(), async {
+        let mut commits = commits.into_iter();
+        loop {
+            let batch: Vec<_> = commits.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                break;
+            }
+            processor.check_cancelled()?;
+            let permit = Arc::clone(&write_limit).acquire_owned().await?;
+            let pipeline = Arc::clone(&pipeline);
+            inserts.spawn(async move {
+                let _permit = permit;
+                pipeline.process_batch(&batch).await.map(|()| batch.len())
+            });
+
+            while let Some(result) = inserts.try_join_next() {
+                processed += result??;
+                progress.report(state, &request.job_id, processed, false).await?;
+            }
+        }
+        while let Some(result) = inserts.join_next().await {
+            processed += result??;
+            progress.report(state, &request.job_id, processed, false).await?;
+        }
+        progress.report(state, &request.job_id, processed, true).await?;
+        pipeline.finish().await
+    })
+    .await?;
+
+    let elapsed = phase_started.elapsed().as_secs_f64();
+    let commits_per_second = if elapsed > 0.0 {
+        total_commits as f64 / elapsed
+    } else {
+        0.0
+    };
+    tracing::info!(
+        "Analyzed {} commits in {:.1}s ({:.1} commits/s)",
+        total_commits,
+        elapsed,
+        commits_per_second
+    );
+    sqlx::query(
+        "UPDATE AnalysisJob SET analysisFinishedAt = NOW(), commitsPerSecond = ? WHERE id = ?",
+    )
+    .bind(commits_per_second)
+    .bind(&request.job_id)
+    .execute(&state.db)
+    .await?;
+
+    // Update job to completed
+    if !job_status::transition(state, &request.job_id, JobStatus::Completed, None).await? {
+        return Ok(());
+    }
+
+    // Update repository last sync time and size at the analyzed tip
+    let file_count = {
+        let processor = processor.clone();
+        let repo_path = repo_path.to_path_buf();
+        let branch = request.branch.clone();
+        state
+            .git_pool
+            .run(move || {
+                processor.count_head_files(
+                    &repo_path,
+                    &branch,
+                    env_flag("EXCLUDE_LINGUIST_PATHS", true),
+                )
+            })
+            .await
+    };
+    let file_count = match file_count {
+        Ok(count) => Some(count as i32),
+        Err(e) => {
+            tracing::warn!("Could not count files at HEAD: {}", e);
+            None
+        }
+    };
+    // The tip is where the next incremental run of this branch picks up, so it only
+    // advances when everything behind it is stored
+    let dead_letters: i32 =
+        sqlx::query_scalar("SELECT deadLetterCommits FROM AnalysisJob WHERE id = ?")
+            .bind(&request.job_id)
+            .fetch_one(&state.db)
+            .await?;
+    let analyzed_tip = parsed
+        .tip
+        .filter(|_| tracked && !excludes_commits && dead_letters == 0);
+    if tracked && analyzed_tip.is_none() {
+        tracing::info!("Run was filtered or left commits unstored; not moving lastAnalyzedSha");
+    }
+    sqlx::query(
+        r#"
+        UPDATE Repository
+        SET lastSyncAt = NOW(), fileCount = COALESCE(?, fileCount),
+            lastAnalyzedSha = COALESCE(?, lastAnalyzedSha)
+        WHERE id = ?
+        "#,
+    )
+    .bind(file_count)
+    .bind(analyzed_tip)
+    .bind(&repository_id)
+    .execute(&state.db)
+    .await?;
+    if tracked {
+        analyzed_branch::record(state, &repository_id, &request.branch).await?;
+    }
+
+    tracing::info!(
+        "Analysis completed for job {}: {} commits processed",
+        request.job_id,
+        total_commits
+    );
+
+    Ok(())
+}
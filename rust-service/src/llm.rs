@@ -0,0 +1,354 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Give up on a completion request after this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest summary a provider is asked for
+const MAX_OUTPUT_TOKENS: u32 = 300;
+
+/// Text generated by a provider and the tokens it billed for it
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Token counts as reported by the provider; None when it reports none
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+}
+
+/// A chat model the summarization worker can ask for summaries.
+///
+/// Implementations only translate one request and response; retries live in
+/// [`complete_with_retry`] so every provider backs off the same way.
+pub trait LlmProvider: Send + Sync {
+    /// Short name used in logs (`openai`, `anthropic`, `ollama`)
+    fn name(&self) -> &str;
+
+    /// Model id, stored with each summary
+    fn model(&self) -> &str;
+
+    fn complete<'a>(
+        &'a self,
+        system: &'a str,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Completion>>;
+}
+
+/// Provider picked by `SUMMARIZER_PROVIDER` (`openai` by default, `anthropic`, `ollama`),
+/// configured from its own variables; `SUMMARIZER_MODEL` overrides the model of any
+pub fn from_env() -> Result<Box<dyn LlmProvider>> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let model = |default_var: &str, default: &str| {
+        var("SUMMARIZER_MODEL")
+            .or_else(|| var(default_var))
+            .unwrap_or_else(|| default.to_string())
+    };
+    let client = reqwest::Client::new();
+    let provider = var("SUMMARIZER_PROVIDER").unwrap_or_else(|| "openai".to_string());
+
+    Ok(match provider.to_ascii_lowercase().as_str() {
+        "openai" => Box::new(OpenAi {
+            client,
+            url: var("OPENAI_API_URL")
+                .unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string()),
+            api_key: var("OPENAI_API_KEY"),
+            model: model("OPENAI_MODEL", "gpt-4o-mini"),
+        }),
+        "anthropic" => Box::new(Anthropic {
+            client,
+            url: var("ANTHROPIC_API_URL")
+                .unwrap_or_else(|| "https://api.anthropic.com/v1/messages".to_string()),
+            api_key: var("ANTHROPIC_API_KEY").context("ANTHROPIC_API_KEY is not set")?,
+            model: model("ANTHROPIC_MODEL", "claude-3-5-haiku-latest"),
+        }),
+        "ollama" => Box::new(Ollama {
+            client,
+            url: var("OLLAMA_URL")
+                .unwrap_or_else(|| "http://localhost:11434".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            model: model("OLLAMA_MODEL", "llama3.1"),
+        }),
+        other => anyhow::bail!("Unknown SUMMARIZER_PROVIDER: {}", other),
+    })
+}
+
+/// Ask `provider` for a completion, retrying up to `retries` times on timeouts,
+/// connection errors, rate limiting (429) and server errors, with the delay doubling
+/// from `backoff`. Other failures (bad key, bad request) are returned right away.
+pub async fn complete_with_retry(
+    provider: &dyn LlmProvider,
+    system: &str,
+    prompt: &str,
+    retries: u32,
+    backoff: Duration,
+) -> Result<Completion> {
+    let mut attempt = 0;
+    loop {
+        match provider.complete(system, prompt).await {
+            Err(e) if attempt < retries && is_retryable(&e) => {
+                let delay = backoff.saturating_mul(1 << attempt.min(10));
+                attempt += 1;
+                tracing::warn!(
+                    "{} request failed ({:#}); retry {}/{} in {:?}",
+                    provider.name(),
+                    e,
+                    attempt,
+                    retries,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_retryable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|status| {
+                    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                })
+        })
+    })
+}
+
+/// OpenAI chat completions, or any API compatible with it
+struct OpenAi {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiResponse {
+    choices: Vec<OpenAiChoice>,
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiChoice {
+    message: OpenAiMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiMessage {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiUsage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+impl LlmProvider for OpenAi {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system: &'a str,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .timeout(REQUEST_TIMEOUT)
+                .json(&json!({
+                    "model": self.model,
+                    "temperature": 0.2,
+                    "max_tokens": MAX_OUTPUT_TOKENS,
+                    "messages": [
+                        { "role": "system", "content": system },
+                        { "role": "user", "content": prompt },
+                    ],
+                }));
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response: OpenAiResponse = request
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("Invalid OpenAI response")?;
+
+            let text = response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message.content)
+                .context("OpenAI response has no message")?;
+            Ok(Completion {
+                text,
+                usage: TokenUsage {
+                    input_tokens: response.usage.as_ref().and_then(|u| u.prompt_tokens),
+                    output_tokens: response.usage.as_ref().and_then(|u| u.completion_tokens),
+                },
+            })
+        })
+    }
+}
+
+/// Anthropic Messages API
+struct Anthropic {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicBlock>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+}
+
+impl LlmProvider for Anthropic {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system: &'a str,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let response: AnthropicResponse = self
+                .client
+                .post(&self.url)
+                .timeout(REQUEST_TIMEOUT)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .json(&json!({
+                    "model": self.model,
+                    "max_tokens": MAX_OUTPUT_TOKENS,
+                    "temperature": 0.2,
+                    "system": system,
+                    "messages": [{ "role": "user", "content": prompt }],
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("Invalid Anthropic response")?;
+
+            let text: String = response
+                .content
+                .into_iter()
+                .filter(|block| block.kind == "text")
+                .filter_map(|block| block.text)
+                .collect();
+            Ok(Completion {
+                text,
+                usage: TokenUsage {
+                    input_tokens: response.usage.as_ref().and_then(|u| u.input_tokens),
+                    output_tokens: response.usage.as_ref().and_then(|u| u.output_tokens),
+                },
+            })
+        })
+    }
+}
+
+/// Local models served by Ollama (`/api/chat`)
+struct Ollama {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaResponse {
+    message: Option<OpenAiMessage>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+impl LlmProvider for Ollama {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system: &'a str,
+        prompt: &'a str,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let response: OllamaResponse = self
+                .client
+                .post(format!("{}/api/chat", self.url))
+                .timeout(REQUEST_TIMEOUT)
+                .json(&json!({
+                    "model": self.model,
+                    "stream": false,
+                    "options": { "temperature": 0.2, "num_predict": MAX_OUTPUT_TOKENS },
+                    "messages": [
+                        { "role": "system", "content": system },
+                        { "role": "user", "content": prompt },
+                    ],
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("Invalid Ollama response")?;
+
+            let text = response
+                .message
+                .and_then(|message| message.content)
+                .context("Ollama response has no message")?;
+            Ok(Completion {
+                text,
+                usage: TokenUsage {
+                    input_tokens: response.prompt_eval_count,
+                    output_tokens: response.eval_count,
+                },
+            })
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Keys of the well-known entries in [`CommitMetadata`]
+pub const TRAILERS: &str = "trailers";
+pub const EXCLUDED_PATHS: &str = "excludedPaths";
+/// Root commit whose files were not counted (`RootCommitPolicy::Exclude`)
+pub const ROOT_COMMIT_EXCLUDED: &str = "rootCommitExcluded";
+/// Merge commit whose files were not counted (`MergeDiffStrategy::Skip`)
+pub const MERGE_DIFF_SKIPPED: &str = "mergeDiffSkipped";
+/// First semver release tag containing the commit (release-only analysis)
+pub const RELEASE: &str = "release";
+/// Pull/merge request a squash-merge commit came from (`#123`, `!45`), when named
+pub const PULL_REQUEST: &str = "pullRequest";
+/// Commit dated further in the future than `FUTURE_COMMIT_TOLERANCE_HOURS` (clock skew)
+pub const FUTURE_DATED: &str = "futureDated";
+/// Paths the commit really changed, when more than `MAX_STORED_PATHS` and only the
+/// first ones were stored
+pub const CHANGED_PATHS_TOTAL: &str = "changedPathsTotal";
+/// Changed paths whose CommitFile rows the database rejected and were left out
+pub const SKIPPED_FILES: &str = "skippedFiles";
+
+/// Less common extracted attributes of a commit, stored in the `metadata` JSON column
+/// so new ones don't each need a schema migration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CommitMetadata(Map<String, Value>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl CommitMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Typed read of an entry; None when missing or of a different shape
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.0
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Store an entry, replacing any previous value
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.0.insert(key.to_string(), value);
+        }
+    }
+
+    pub fn trailers(&self) -> Vec<Trailer> {
+        self.get(TRAILERS).unwrap_or_default()
+    }
+
+    pub fn excluded_paths(&self) -> Vec<String> {
+        self.get(EXCLUDED_PATHS).unwrap_or_default()
+    }
+}
+
+fn trailer_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^([A-Za-z0-9][A-Za-z0-9-]*):\s+(.+)$").expect("valid trailer regex")
+    })
+}
+
+/// `Key: value` trailers from the final paragraph of a commit message
+/// (only when every line of that paragraph is a trailer, as git does)
+pub fn extract_trailers(message: &str) -> Vec<Trailer> {
+    let Some(last) = message.trim_end().rsplit("\n\n").next() else {
+        return Vec::new();
+    };
+    // A one-paragraph message is a subject, not trailers
+    if last.len() == message.trim_end().len() {
+        return Vec::new();
+    }
+
+    let mut trailers = Vec::new();
+    for line in last.lines() {
+        match trailer_regex().captures(line.trim_end()) {
+            Some(caps) => trailers.push(Trailer {
+                key: caps[1].to_string(),
+                value: caps[2].trim().to_string(),
+            }),
+            None => return Vec::new(),
+        }
+    }
+    trailers
+}
+
+/// A commit message that looks like a squash merge of a pull/merge request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SquashMerge {
+    /// `#123` (GitHub, Bitbucket) or `!45` (GitLab); None for `git merge --squash`
+    pub pull_request: Option<String>,
+}
+
+fn squash_title_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // GitHub: "Title (#123)"; Bitbucket: "Merged in branch (pull request #12)"
+        Regex::new(r"\((?:pull request )?#(\d+)\)\s*$").expect("valid squash title regex")
+    })
+}
+
+fn merge_request_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?m)^See merge request \S*!(\d+)\s*$").expect("valid merge request regex")
+    })
+}
+
+/// Recognize the messages hosting platforms and `git merge --squash` write for squash merges
+pub fn squash_merge(title: &str, message: &str) -> Option<SquashMerge> {
+    if let Some(caps) = squash_title_regex().captures(title) {
+        return Some(SquashMerge {
+            pull_request: Some(format!("#{}", &caps[1])),
+        });
+    }
+    if let Some(caps) = merge_request_regex().captures(message) {
+        return Some(SquashMerge {
+            pull_request: Some(format!("!{}", &caps[1])),
+        });
+    }
+    message
+        .trim_start()
+        .starts_with("Squashed commit of the following:")
+        .then_some(SquashMerge { pull_request: None })
+}
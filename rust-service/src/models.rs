@@ -1,19 +1,126 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::coauthors::CoAuthor;
+use crate::metadata::CommitMetadata;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedCommit {
     pub id: String,
+    pub repository_id: String, // So processors outside the store know where a commit is from
     pub sha: String,
     pub author_name: String,
-    pub author_email: String,
+    pub author_email: String, // Lowercased unless disabled, so casing doesn't split authors
+    pub author_email_original: String, // As written in the commit
     pub commit_date: DateTime<Utc>,
+    pub commit_date_offset: i32, // Committer's UTC offset in minutes, for local-time reporting
     pub message: String,
-    pub message_title: String,
-    pub files_changed: usize,
-    pub changed_paths: String, // Comma-separated list of file paths
+    pub message_title: String, // First non-blank line, or the placeholder for empty messages
+    pub no_message: bool, // The commit message is empty or whitespace only
+    pub files_changed: Option<usize>, // None when diffs were skipped
+    pub code_files_changed: Option<usize>, // Subset of files_changed with a code extension
+    pub insertions: Option<usize>, // Lines added; None without a (completed) content diff
+    pub deletions: Option<usize>, // Lines removed; None without a (completed) content diff
+    pub changed_paths: Option<String>, // Comma-separated list of file paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_changes: Vec<FileChange>, // How each of changed_paths changed, in the same order
+    pub patch_id: Option<String>, // Hash of the normalized diff, for cherry-pick detection
+    pub diff_timeout: bool, // Content diff exceeded DIFF_TIMEOUT_MS; counts are name-only
+    pub largest_file_path: Option<String>, // Biggest blob added or modified; None without diffs
+    pub largest_file_size: Option<u64>, // Its size in bytes
+    pub is_signed: bool,
+    pub signature_verified: Option<bool>, // None when not checked against a keyring
+    pub signature_identity: Option<String>, // Signer email/principal of a verified signature
+    pub signature_mismatch: bool, // Verified signer differs from the commit author
+    pub signature_key_id: Option<String>, // OpenPGP issuer key id, parsed from the signature
+    pub signature_fingerprint: Option<String>, // OpenPGP fingerprint or SSH `SHA256:` fingerprint
+    pub template_violation: Option<bool>, // Message doesn't match the commit template; None if unchecked
+    pub parent_shas: Vec<String>, // More than one for merge commits
+    pub co_authors: Vec<CoAuthor>, // From Co-authored-by trailers
+    pub metadata: CommitMetadata, // Less common attributes (trailers, excluded paths, ...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_diffs: Vec<FileDiff>, // Unified diff text per file; only when diffs are included
+}
+
+/// How a file changed in a commit (`git diff --name-status`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeStatus {
+    #[serde(rename = "A")]
+    Added,
+    #[serde(rename = "M")]
+    Modified,
+    #[serde(rename = "D")]
+    Deleted,
+    /// Moved from `oldPath`; needs rename detection
+    #[serde(rename = "R")]
+    Renamed,
+    /// Copied from `oldPath`; needs copy detection
+    #[serde(rename = "C")]
+    Copied,
+    /// File type changed (e.g. a file replaced by a symlink)
+    #[serde(rename = "T")]
+    TypeChanged,
+}
+
+impl ChangeStatus {
+    pub fn from_delta(delta: git2::Delta) -> Self {
+        match delta {
+            git2::Delta::Added => Self::Added,
+            git2::Delta::Deleted => Self::Deleted,
+            git2::Delta::Renamed => Self::Renamed,
+            git2::Delta::Copied => Self::Copied,
+            git2::Delta::Typechange => Self::TypeChanged,
+            _ => Self::Modified,
+        }
+    }
+
+    /// Letter stored in `CommitFile.status`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Added => "A",
+            Self::Modified => "M",
+            Self::Deleted => "D",
+            Self::Renamed => "R",
+            Self::Copied => "C",
+            Self::TypeChanged => "T",
+        }
+    }
+}
+
+/// One changed file of a commit; `old_path` is set for renames and copies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub status: ChangeStatus,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+}
+
+/// Unified diff of one changed file, possibly cut short
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub path: String,
+    pub patch: String,
+    /// Size of the full diff in bytes, before any truncation
+    pub bytes: usize,
+    pub truncated: bool,
+}
+
+impl ParsedCommit {
+    /// Deterministic id for a commit within a repository (UUIDv5 of `repositoryId:sha`),
+    /// so re-analysis always maps the same commit to the same id
+    pub fn stable_id(repository_id: &str, sha: &str) -> String {
+        uuid::Uuid::new_v5(
+            &uuid::Uuid::NAMESPACE_OID,
+            format!("{}:{}", repository_id, sha).as_bytes(),
+        )
+        .to_string()
+    }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryInfo {
     pub name: String,
@@ -0,0 +1,122 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+/// Machine-readable description of the service API, served at `/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "git-doc service", description = "Git analysis backend for git-doc"),
+    paths(
+        crate::health,
+        crate::version,
+        crate::analyze_repository,
+        crate::upload::analyze_bundle,
+        crate::upload::analyze_tarball,
+        crate::jobs::list_jobs,
+        crate::jobs::batch_status,
+        crate::jobs::get_job,
+        crate::jobs::cancel_job,
+        crate::job_logs::get_job_logs,
+        crate::job_events::job_events,
+        crate::commits::list_job_commits,
+        crate::stats::jira_projects,
+        crate::stats::duplicate_commits,
+        crate::stats::punchcard,
+        crate::stats::directory_churn,
+        crate::stats::co_author_pairs,
+        crate::stats::signature_mismatches,
+        crate::stats::large_files,
+        crate::stats::author_churn,
+        crate::stats::author_cadence_stats,
+        crate::stats::template_compliance,
+        crate::blame::file_blame,
+        crate::analysis_diff::analysis_diff,
+        crate::commits::get_commit,
+        crate::search::search_commits,
+        crate::projects::create_project,
+        crate::projects::get_project,
+        crate::projects::set_project_repositories,
+        crate::projects::project_stats,
+        crate::commits::full_message,
+        crate::commits::commit_diff,
+        crate::commits::file_diffs,
+        crate::summaries::set_repository_context,
+        crate::summaries::claim,
+        crate::summaries::complete,
+        crate::credentials::set_credential,
+        crate::credentials::delete_credential,
+        crate::export::create_export,
+        crate::export::get_export,
+        crate::github_webhook::github_webhook,
+    ),
+    components(schemas(
+        crate::AnalyzeRequest,
+        crate::VersionInfo,
+        crate::stats::LargeFileCommit,
+        crate::projects::CreateProjectRequest,
+        crate::projects::ProjectRepositoriesRequest,
+        crate::projects::Project,
+        crate::projects::RepositoryActivity,
+        crate::projects::ProjectAuthor,
+        crate::projects::MonthlyActivity,
+        crate::projects::ProjectStats,
+        crate::search::SearchMode,
+        crate::search::SearchHit,
+        crate::search::Highlight,
+        crate::search::SearchResponse,
+        crate::job_logs::JobLogsResponse,
+        crate::job_events::JobEvent,
+        crate::git::RootCommitPolicy,
+        crate::git::MergeDiffStrategy,
+        crate::git::FutureCommitPolicy,
+        crate::AnalyzeResponse,
+        crate::upload::BundleUpload,
+        crate::upload::TarballUpload,
+        crate::jobs::JobSummary,
+        crate::jobs::CancelJobResponse,
+        crate::jobs::JobStatusRequest,
+        crate::jobs::JobStatusResponse,
+        crate::commits::JobCommitsPage,
+        crate::commits::CommitListItem,
+        crate::stats::JiraProjectStats,
+        crate::stats::DuplicateGroup,
+        crate::stats::DuplicateCommit,
+        crate::stats::Punchcard,
+        crate::stats::DirectoryChurn,
+        crate::stats::CoAuthorPair,
+        crate::stats::SignatureMismatch,
+        crate::stats::AuthorChurnWindow,
+        crate::stats::AuthorChurnPoint,
+        crate::stats::AuthorChurnShare,
+        crate::stats::AuthorCadence,
+        crate::stats::TemplateCompliance,
+        crate::stats::AuthorTemplateCompliance,
+        crate::blame::FileOwnership,
+        crate::analysis_diff::AnalysisDiff,
+        crate::git::DeltaCommit,
+        crate::blame::BlameAuthor,
+        crate::blame::BlameLines,
+        crate::commits::CommitDetail,
+        crate::commits::CommitSignature,
+        crate::commits::ChangedFile,
+        crate::commits::CommitDiff,
+        crate::commits::CommitFileDiffs,
+        crate::commits::StoredFileDiff,
+        crate::metadata::Trailer,
+        crate::commits::CommitMessage,
+        crate::summaries::SummaryContext,
+        crate::summaries::ClaimRequest,
+        crate::summaries::ClaimedCommit,
+        crate::summaries::SummaryResult,
+        crate::credentials::CredentialRequest,
+        crate::export::ExportRequest,
+        crate::export::ExportFormat,
+        crate::export::ExportResponse,
+        crate::export::ExportStatus,
+        crate::github_webhook::GithubWebhookResponse,
+    ))
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,115 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::cancel::CancelToken;
+
+/// How long a timed-out phase gets to notice its cancellation and stop
+const WIND_DOWN: Duration = Duration::from_secs(30);
+
+/// Stages of an analysis job, finer than its status: PARSING covers both walking the
+/// history and inserting the commits, which have very different costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Cloning,
+    Parsing,
+    Inserting,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Cloning => "cloning",
+            Phase::Parsing => "parsing",
+            Phase::Inserting => "inserting",
+        }
+    }
+
+    fn budget_var(self) -> &'static str {
+        match self {
+            Phase::Cloning => "PHASE_BUDGET_CLONING_SECS",
+            Phase::Parsing => "PHASE_BUDGET_PARSING_SECS",
+            Phase::Inserting => "PHASE_BUDGET_INSERTING_SECS",
+        }
+    }
+
+    /// Longest the phase may take (`PHASE_BUDGET_<PHASE>_SECS`); None when unset or 0
+    pub fn budget(self) -> Option<Duration> {
+        std::env::var(self.budget_var())
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+/// Run one phase of a job: record it as the job's current phase, fail it with a
+/// clear error once it exceeds its budget, and add its duration to `phaseDurations`.
+///
+/// A timed-out phase cancels the job's `cancel` token and waits (up to [`WIND_DOWN`])
+/// for its clone or walk to stop, so the job's slot isn't handed to another job while
+/// git work is still writing to the same clone.
+pub async fn run<T>(
+    db: &sqlx::MySqlPool,
+    job_id: &str,
+    phase: Phase,
+    cancel: &CancelToken,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    sqlx::query("UPDATE AnalysisJob SET phase = ?, phaseStartedAt = NOW() WHERE id = ?")
+        .bind(phase.as_str())
+        .bind(job_id)
+        .execute(db)
+        .await?;
+
+    let started = Instant::now();
+    let result = match phase.budget() {
+        Some(budget) => {
+            tokio::pin!(work);
+            match tokio::time::timeout(budget, &mut work).await {
+                Ok(result) => result,
+                Err(_) => {
+                    cancel.cancel();
+                    if tokio::time::timeout(WIND_DOWN, work).await.is_err() {
+                        tracing::warn!(
+                            "The {} phase did not stop within {}s of being cancelled",
+                            phase.as_str(),
+                            WIND_DOWN.as_secs()
+                        );
+                    }
+                    Err(anyhow::anyhow!(
+                        "The {} phase exceeded its {}s budget ({})",
+                        phase.as_str(),
+                        budget.as_secs(),
+                        phase.budget_var()
+                    ))
+                }
+            }
+        }
+        None => work.await,
+    };
+    let elapsed = started.elapsed().as_secs_f64();
+    tracing::info!("Phase {} took {:.1}s", phase.as_str(), elapsed);
+
+    // A re-clone runs the cloning phase twice; durations add up
+    sqlx::query(
+        r#"
+        UPDATE AnalysisJob
+        SET phase = NULL, phaseStartedAt = NULL,
+            phaseDurations = JSON_SET(
+                COALESCE(phaseDurations, JSON_OBJECT()), CONCAT('$.', ?),
+                COALESCE(JSON_EXTRACT(phaseDurations, CONCAT('$.', ?)), 0) + ?
+            )
+        WHERE id = ?
+        "#,
+    )
+    .bind(phase.as_str())
+    .bind(phase.as_str())
+    .bind(elapsed)
+    .bind(job_id)
+    .execute(db)
+    .await?;
+
+    result
+}
@@ -0,0 +1,227 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use crate::models::ParsedCommit;
+use crate::store::{self, CommitContext};
+use crate::AppState;
+
+/// A step run for every commit an analysis produces.
+///
+/// The analysis loop feeds batches of parsed commits through a [`Pipeline`] of
+/// these, so extra handling (metrics, forwarding to other systems) plugs in without
+/// touching the loop. Batches may be processed concurrently and out of order.
+pub trait CommitProcessor: Send + Sync {
+    /// Short name used in logs and errors
+    fn name(&self) -> &str;
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>>;
+
+    /// Handle a batch of commits; by default each commit is processed in turn
+    fn process_batch<'a>(&'a self, commits: &'a [ParsedCommit]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for commit in commits {
+                self.process(commit)
+                    .await
+                    .map_err(|e| e.context(format!("on {}", commit.sha)))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Called once after every commit of the job was processed (flush buffers, report)
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Processors run in order for each batch; the first error stops the batch
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    processors: Vec<Arc<dyn CommitProcessor>>,
+}
+
+impl Pipeline {
+    pub fn with(mut self, processor: Arc<dyn CommitProcessor>) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    pub fn with_all<I: IntoIterator<Item = Arc<dyn CommitProcessor>>>(
+        mut self,
+        processors: I,
+    ) -> Self {
+        self.processors.extend(processors);
+        self
+    }
+
+    pub async fn process_batch(&self, commits: &[ParsedCommit]) -> Result<()> {
+        for processor in &self.processors {
+            processor
+                .process_batch(commits)
+                .await
+                .map_err(|e| e.context(format!("{} failed", processor.name())))?;
+        }
+        Ok(())
+    }
+
+    pub async fn finish(&self) -> Result<()> {
+        for processor in &self.processors {
+            processor
+                .finish()
+                .await
+                .map_err(|e| e.context(format!("{} failed to finish", processor.name())))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes commits and their related rows to the database, a batch per transaction.
+/// When a batch fails its commits are stored one at a time instead; a commit whose
+/// insert still fails after `DB_WRITE_RETRIES` retries is recorded as a dead letter
+/// and skipped instead of failing the job.
+pub struct StoreProcessor {
+    state: AppState,
+    context: Arc<CommitContext>,
+    retries: u32,
+    dead_letters: AtomicUsize,
+}
+
+impl StoreProcessor {
+    pub fn new(state: AppState, context: Arc<CommitContext>) -> Self {
+        Self {
+            state,
+            context,
+            retries: std::env::var("DB_WRITE_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            dead_letters: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl CommitProcessor for StoreProcessor {
+    fn name(&self) -> &str {
+        "store"
+    }
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut attempt = 0;
+            let error = loop {
+                attempt += 1;
+                match store::store_commit(&self.state, &self.context, commit).await {
+                    Ok(_) => return Ok(()),
+                    Err(e) if attempt > self.retries => break e,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Storing {} failed (attempt {}): {:#}",
+                            commit.sha,
+                            attempt,
+                            e
+                        );
+                        let backoff = 200 * 2u64.pow((attempt - 1).min(5));
+                        tokio::time::sleep(std::time::Duration::from_millis(backoff)).await;
+                    }
+                }
+            };
+            // When even the dead letter can't be written the database is likely down: fail
+            if let Err(e) =
+                store::dead_letter(&self.state, &self.context, commit, attempt, &error).await
+            {
+                return Err(error.context(format!("recording a dead letter failed: {:#}", e)));
+            }
+            self.dead_letters.fetch_add(1, Ordering::Relaxed);
+            tracing::error!(
+                "Gave up storing {} after {} attempts: {:#}",
+                commit.sha,
+                attempt,
+                error
+            );
+            Ok(())
+        })
+    }
+
+    fn process_batch<'a>(&'a self, commits: &'a [ParsedCommit]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match store::store_batch(&self.state, &self.context, commits).await {
+                Ok(_) => return Ok(()),
+                Err(e) => tracing::warn!(
+                    "Storing a batch of {} commits failed, storing them one by one: {:#}",
+                    commits.len(),
+                    e
+                ),
+            }
+            // One bad commit shouldn't cost the rest of the batch
+            for commit in commits {
+                self.process(commit).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let dead_letters = self.dead_letters.load(Ordering::Relaxed);
+            if dead_letters > 0 {
+                tracing::warn!(
+                    "{} commits could not be stored; see DeadLetterCommit",
+                    dead_letters
+                );
+                sqlx::query("UPDATE AnalysisJob SET deadLetterCommits = ? WHERE id = ?")
+                    .bind(dead_letters as i32)
+                    .bind(&self.context.job_id)
+                    .execute(&self.state.db)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Tallies what a job analyzed and logs the totals when it finishes
+#[derive(Default)]
+pub struct MetricsProcessor {
+    commits: AtomicUsize,
+    merges: AtomicUsize,
+    files_changed: AtomicUsize,
+    diff_timeouts: AtomicUsize,
+    signed: AtomicUsize,
+}
+
+impl CommitProcessor for MetricsProcessor {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>> {
+        self.commits.fetch_add(1, Ordering::Relaxed);
+        if commit.parent_shas.len() > 1 {
+            self.merges.fetch_add(1, Ordering::Relaxed);
+        }
+        self.files_changed
+            .fetch_add(commit.files_changed.unwrap_or(0), Ordering::Relaxed);
+        if commit.diff_timeout {
+            self.diff_timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+        if commit.is_signed {
+            self.signed.fetch_add(1, Ordering::Relaxed);
+        }
+        Box::pin(async { Ok(()) })
+    }
+
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        tracing::info!(
+            "Processed {} commits ({} merges, {} signed), {} file changes, {} diff timeouts",
+            self.commits.load(Ordering::Relaxed),
+            self.merges.load(Ordering::Relaxed),
+            self.signed.load(Ordering::Relaxed),
+            self.files_changed.load(Ordering::Relaxed),
+            self.diff_timeouts.load(Ordering::Relaxed),
+        );
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -0,0 +1,420 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::anonymize::{self, AnonymizeQuery, Anonymizer};
+use crate::AppState;
+
+fn internal_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProjectRequest {
+    pub name: String,
+    #[serde(default)]
+    pub repository_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRepositoriesRequest {
+    /// Full membership; repositories not listed are removed from the project
+    pub repository_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub repository_ids: Vec<String>,
+}
+
+/// Group repositories into a project for cross-repository stats
+#[utoipa::path(
+    post,
+    path = "/projects",
+    tag = "projects",
+    request_body = CreateProjectRequest,
+    responses(
+        (status = 200, body = Project),
+        (status = 400, description = "Empty name or unknown repository ids", body = String)
+    )
+)]
+pub async fn create_project(
+    State(state): State<AppState>,
+    Json(request): Json<CreateProjectRequest>,
+) -> Result<Json<Project>, (StatusCode, String)> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "name must not be empty".to_string(),
+        ));
+    }
+    let repository_ids = dedup(request.repository_ids);
+    ensure_repositories_exist(&state.db, &repository_ids).await?;
+
+    let project_id = uuid::Uuid::new_v4().to_string();
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    sqlx::query(
+        "INSERT INTO Project (id, name, createdAt, updatedAt) VALUES (?, ?, NOW(3), NOW(3))",
+    )
+    .bind(&project_id)
+    .bind(name)
+    .execute(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    insert_members(&mut tx, &project_id, &repository_ids).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(Project {
+        id: project_id,
+        name: name.to_string(),
+        repository_ids,
+    }))
+}
+
+/// A project and its repositories
+#[utoipa::path(
+    get,
+    path = "/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id")),
+    responses(
+        (status = 200, body = Project),
+        (status = 404, description = "Project not found", body = String)
+    )
+)]
+pub async fn get_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+) -> Result<Json<Project>, (StatusCode, String)> {
+    let name = project_name(&state.db, &project_id).await?;
+    let repository_ids = member_ids(&state.db, &project_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(Project {
+        id: project_id,
+        name,
+        repository_ids,
+    }))
+}
+
+/// Replace the set of repositories in a project
+#[utoipa::path(
+    put,
+    path = "/projects/{id}/repositories",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id")),
+    request_body = ProjectRepositoriesRequest,
+    responses(
+        (status = 200, body = Project),
+        (status = 400, description = "Unknown repository ids", body = String),
+        (status = 404, description = "Project not found", body = String)
+    )
+)]
+pub async fn set_project_repositories(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Json(request): Json<ProjectRepositoriesRequest>,
+) -> Result<Json<Project>, (StatusCode, String)> {
+    let name = project_name(&state.db, &project_id).await?;
+    let repository_ids = dedup(request.repository_ids);
+    ensure_repositories_exist(&state.db, &repository_ids).await?;
+
+    let mut tx = state.db.begin().await.map_err(internal_error)?;
+    sqlx::query("DELETE FROM ProjectRepository WHERE projectId = ?")
+        .bind(&project_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    insert_members(&mut tx, &project_id, &repository_ids).await?;
+    sqlx::query("UPDATE Project SET updatedAt = NOW(3) WHERE id = ?")
+        .bind(&project_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(Project {
+        id: project_id,
+        name,
+        repository_ids,
+    }))
+}
+
+fn dedup(mut ids: Vec<String>) -> Vec<String> {
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+async fn project_name(
+    db: &sqlx::MySqlPool,
+    project_id: &str,
+) -> Result<String, (StatusCode, String)> {
+    sqlx::query_scalar("SELECT name FROM Project WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(db)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Project not found".to_string()))
+}
+
+async fn member_ids(db: &sqlx::MySqlPool, project_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT repositoryId FROM ProjectRepository WHERE projectId = ? ORDER BY repositoryId",
+    )
+    .bind(project_id)
+    .fetch_all(db)
+    .await
+}
+
+async fn ensure_repositories_exist(
+    db: &sqlx::MySqlPool,
+    repository_ids: &[String],
+) -> Result<(), (StatusCode, String)> {
+    if repository_ids.is_empty() {
+        return Ok(());
+    }
+    let mut query =
+        sqlx::QueryBuilder::<sqlx::MySql>::new("SELECT id FROM Repository WHERE id IN (");
+    let mut ids = query.separated(", ");
+    for id in repository_ids {
+        ids.push_bind(id);
+    }
+    ids.push_unseparated(")");
+    let found: Vec<String> = query
+        .build_query_scalar()
+        .fetch_all(db)
+        .await
+        .map_err(internal_error)?;
+
+    let missing: Vec<&str> = repository_ids
+        .iter()
+        .filter(|id| !found.contains(id))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown repository ids: {}", missing.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+async fn insert_members(
+    tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    project_id: &str,
+    repository_ids: &[String],
+) -> Result<(), (StatusCode, String)> {
+    if repository_ids.is_empty() {
+        return Ok(());
+    }
+    let mut insert = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "INSERT INTO ProjectRepository (projectId, repositoryId) ",
+    );
+    insert.push_values(repository_ids, |mut row, repository_id| {
+        row.push_bind(project_id).push_bind(repository_id);
+    });
+    insert
+        .build()
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatsQuery {
+    /// Only count commits on or after this date (YYYY-MM-DD)
+    pub start_date: Option<chrono::NaiveDate>,
+    /// Only count commits on or before this date (YYYY-MM-DD)
+    pub end_date: Option<chrono::NaiveDate>,
+    /// Number of top authors returned (default 20, max 200)
+    pub top_authors: Option<u32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct RepositoryActivity {
+    pub repository_id: String,
+    pub name: String,
+    pub commits: i64,
+    pub authors: i64,
+    pub last_commit_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct ProjectAuthor {
+    pub author_email: String,
+    pub author_name: String,
+    pub commits: i64,
+    /// Repositories of the project this author committed to
+    pub repositories: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct MonthlyActivity {
+    /// `YYYY-MM` (UTC)
+    pub month: String,
+    pub commits: i64,
+    pub authors: i64,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub project_id: String,
+    pub name: String,
+    /// Distinct commits across all repositories (a SHA shared by forks counts once)
+    pub commits: i64,
+    pub authors: i64,
+    pub first_commit_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_commit_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub repositories: Vec<RepositoryActivity>,
+    pub top_authors: Vec<ProjectAuthor>,
+    pub activity: Vec<MonthlyActivity>,
+}
+
+/// Push the project membership and date filters shared by every stats query
+fn push_scope(
+    builder: &mut sqlx::QueryBuilder<'_, sqlx::MySql>,
+    project_id: &str,
+    query: &ProjectStatsQuery,
+) {
+    builder
+        .push(
+            " FROM Commit c JOIN ProjectRepository pr ON pr.repositoryId = c.repositoryId \
+             WHERE pr.projectId = ",
+        )
+        .push_bind(project_id.to_string());
+    if let Some(start) = query.start_date {
+        builder
+            .push(" AND c.commitDate >= ")
+            .push_bind(start.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(end) = query.end_date {
+        builder
+            .push(" AND c.commitDate <= ")
+            .push_bind(end.and_hms_opt(23, 59, 59).unwrap());
+    }
+}
+
+/// Commits, authors and monthly activity aggregated over all repositories of a project
+#[utoipa::path(
+    get,
+    path = "/projects/{id}/stats",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id"), ProjectStatsQuery, AnonymizeQuery),
+    responses(
+        (status = 200, body = ProjectStats),
+        (status = 404, description = "Project not found", body = String)
+    )
+)]
+pub async fn project_stats(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<ProjectStatsQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<ProjectStats>, (StatusCode, String)> {
+    let name = project_name(&state.db, &project_id).await?;
+    let top_authors = query.top_authors.unwrap_or(20).clamp(1, 200);
+
+    let mut totals = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT COUNT(DISTINCT c.sha), COUNT(DISTINCT c.authorEmail), \
+         MIN(c.commitDate), MAX(c.commitDate)",
+    );
+    push_scope(&mut totals, &project_id, &query);
+    let (commits, authors, first_commit_at, last_commit_at): (
+        i64,
+        i64,
+        Option<chrono::DateTime<chrono::Utc>>,
+        Option<chrono::DateTime<chrono::Utc>>,
+    ) = totals
+        .build_query_as()
+        .fetch_one(&state.db)
+        .await
+        .map_err(internal_error)?;
+
+    // Left join so member repositories without commits in range still show up
+    let mut repositories = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT r.id AS repositoryId, r.name, COUNT(c.id) AS commits, \
+         COUNT(DISTINCT c.authorEmail) AS authors, MAX(c.commitDate) AS lastCommitAt \
+         FROM ProjectRepository pr JOIN Repository r ON r.id = pr.repositoryId \
+         LEFT JOIN Commit c ON c.repositoryId = r.id",
+    );
+    if let Some(start) = query.start_date {
+        repositories
+            .push(" AND c.commitDate >= ")
+            .push_bind(start.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(end) = query.end_date {
+        repositories
+            .push(" AND c.commitDate <= ")
+            .push_bind(end.and_hms_opt(23, 59, 59).unwrap());
+    }
+    repositories
+        .push(" WHERE pr.projectId = ")
+        .push_bind(&project_id)
+        .push(" GROUP BY r.id, r.name ORDER BY commits DESC, r.name");
+    let repositories: Vec<RepositoryActivity> = repositories
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(internal_error)?;
+
+    let mut authors_query = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT c.authorEmail, MAX(c.authorName) AS authorName, \
+         COUNT(DISTINCT c.sha) AS commits, COUNT(DISTINCT c.repositoryId) AS repositories",
+    );
+    push_scope(&mut authors_query, &project_id, &query);
+    authors_query
+        .push(" GROUP BY c.authorEmail ORDER BY commits DESC, c.authorEmail LIMIT ")
+        .push_bind(top_authors);
+    let mut top_authors: Vec<ProjectAuthor> = authors_query
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(internal_error)?;
+    anonymize::authors(&Anonymizer::for_request(&anonymize), &mut top_authors, |a| {
+        (&mut a.author_email, &mut a.author_name)
+    });
+
+    let mut activity = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT DATE_FORMAT(c.commitDate, '%Y-%m') AS month, \
+         COUNT(DISTINCT c.sha) AS commits, COUNT(DISTINCT c.authorEmail) AS authors",
+    );
+    push_scope(&mut activity, &project_id, &query);
+    activity.push(" GROUP BY month ORDER BY month");
+    let activity: Vec<MonthlyActivity> = activity
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(ProjectStats {
+        project_id,
+        name,
+        commits,
+        authors,
+        first_commit_at,
+        last_commit_at,
+        repositories,
+        top_authors,
+        activity,
+    }))
+}
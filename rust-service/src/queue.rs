@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use aws_sdk_sqs::types::SendMessageBatchRequestEntry;
+use futures::future::BoxFuture;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::models::ParsedCommit;
+use crate::pipeline::CommitProcessor;
+
+/// SQS accepts at most 10 messages per batch
+const BATCH_SIZE: usize = 10;
+/// SQS limit on the combined size of a batch (and of a single message)
+const MAX_BATCH_BYTES: usize = 256 * 1024;
+/// Send attempts per batch before its messages go to the dead-letter queue
+const MAX_ATTEMPTS: u32 = 3;
+
+enum Item {
+    Commit {
+        id: String,
+        group: String,
+        body: String,
+    },
+    /// Resolved once everything queued before it was sent (or dead-lettered)
+    Flush(oneshot::Sender<()>),
+}
+
+/// Publishes every analyzed commit as a JSON message to an SQS queue.
+///
+/// Commits go through a bounded buffer drained by a background sender, so a slow
+/// queue holds the analysis back instead of growing memory. Delivery failures never
+/// fail the job: after retries, messages go to the dead-letter queue if one is
+/// configured and are otherwise logged and dropped.
+pub struct QueueSink {
+    sender: mpsc::Sender<Item>,
+}
+
+impl QueueSink {
+    /// Enabled by `COMMIT_QUEUE_URL`. `COMMIT_QUEUE_REGION` / `COMMIT_QUEUE_ENDPOINT` override
+    /// the AWS defaults, `COMMIT_QUEUE_DLQ_URL` receives undeliverable messages and
+    /// `COMMIT_QUEUE_BUFFER` bounds the commits waiting to be sent.
+    pub async fn from_env() -> Option<Arc<dyn CommitProcessor>> {
+        let queue_url = std::env::var("COMMIT_QUEUE_URL")
+            .ok()
+            .filter(|u| !u.trim().is_empty())?;
+        let dlq_url = std::env::var("COMMIT_QUEUE_DLQ_URL")
+            .ok()
+            .filter(|u| !u.trim().is_empty());
+        let buffer = std::env::var("COMMIT_QUEUE_BUFFER")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(1000)
+            .max(BATCH_SIZE);
+
+        let mut loader = aws_config::from_env();
+        if let Ok(region) = std::env::var("COMMIT_QUEUE_REGION") {
+            loader = loader.region(aws_config::Region::new(region));
+        }
+        let shared = loader.load().await;
+        let mut config = aws_sdk_sqs::config::Builder::from(&shared);
+        if let Ok(endpoint) = std::env::var("COMMIT_QUEUE_ENDPOINT") {
+            config = config.endpoint_url(endpoint);
+        }
+        let client = aws_sdk_sqs::Client::from_conf(config.build());
+
+        let (sender, receiver) = mpsc::channel(buffer);
+        tokio::spawn(run_sender(client, queue_url.clone(), dlq_url, receiver));
+
+        tracing::info!("Publishing analyzed commits to {}", queue_url);
+        Some(Arc::new(Self { sender }))
+    }
+}
+
+impl CommitProcessor for QueueSink {
+    fn name(&self) -> &str {
+        "queue"
+    }
+
+    fn process<'a>(&'a self, commit: &'a ParsedCommit) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let body = serde_json::to_string(commit)?;
+            let item = Item::Commit {
+                id: commit.id.clone(),
+                group: commit.repository_id.clone(),
+                body,
+            };
+            // Waits while the buffer is full; that is the backpressure
+            if self.sender.send(item).await.is_err() {
+                tracing::error!("Commit queue sender stopped; not publishing {}", commit.sha);
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let (done, flushed) = oneshot::channel();
+            if self.sender.send(Item::Flush(done)).await.is_ok() {
+                let _ = flushed.await;
+            }
+            Ok(())
+        })
+    }
+}
+
+async fn run_sender(
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+    dlq_url: Option<String>,
+    mut receiver: mpsc::Receiver<Item>,
+) {
+    // FIFO queues need a group (ordering scope) and a deduplication id per message
+    let fifo = queue_url.ends_with(".fifo");
+    let mut batch: Vec<(String, String, String)> = Vec::with_capacity(BATCH_SIZE);
+
+    while let Some(item) = receiver.recv().await {
+        let mut flushes = Vec::new();
+        let mut next = Some(item);
+        // Take whatever is already waiting, up to a full batch
+        while let Some(item) = next.take() {
+            match item {
+                Item::Commit { id, group, body } => batch.push((id, group, body)),
+                Item::Flush(done) => flushes.push(done),
+            }
+            if batch.len() < BATCH_SIZE {
+                next = receiver.try_recv().ok();
+            }
+        }
+
+        for chunk in split_by_size(&batch) {
+            let failed = send_with_retry(&client, &queue_url, fifo, chunk).await;
+            if !failed.is_empty() {
+                dead_letter(&client, dlq_url.as_deref(), failed).await;
+            }
+        }
+        batch.clear();
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Consecutive runs of entries that fit in one SQS batch request
+fn split_by_size(batch: &[(String, String, String)]) -> Vec<&[(String, String, String)]> {
+    let mut chunks = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (i, (_, _, body)) in batch.iter().enumerate() {
+        if i > start && bytes + body.len() > MAX_BATCH_BYTES {
+            chunks.push(&batch[start..i]);
+            (start, bytes) = (i, 0);
+        }
+        bytes += body.len();
+    }
+    if start < batch.len() {
+        chunks.push(&batch[start..]);
+    }
+    chunks
+}
+
+/// Send a batch, retrying the entries SQS rejected. Returns the ones that never made it.
+async fn send_with_retry(
+    client: &aws_sdk_sqs::Client,
+    queue_url: &str,
+    fifo: bool,
+    batch: &[(String, String, String)],
+) -> Vec<(String, String, String)> {
+    let mut pending: Vec<(String, String, String)> = batch.to_vec();
+    for attempt in 1..=MAX_ATTEMPTS {
+        match send_batch(client, queue_url, fifo, &pending).await {
+            Ok(failed_ids) if failed_ids.is_empty() => return Vec::new(),
+            Ok(failed_ids) => {
+                pending.retain(|(id, _, _)| failed_ids.contains(id));
+                tracing::warn!(
+                    "SQS rejected {} of the batch (attempt {}/{})",
+                    pending.len(),
+                    attempt,
+                    MAX_ATTEMPTS
+                );
+            }
+            Err(e) => tracing::warn!(
+                "Failed to publish commits to SQS (attempt {}/{}): {:#}",
+                attempt,
+                MAX_ATTEMPTS,
+                e
+            ),
+        }
+        tokio::time::sleep(Duration::from_millis(200 * 2u64.pow(attempt))).await;
+    }
+    pending
+}
+
+/// Send one batch; returns the ids of entries SQS did not accept
+async fn send_batch(
+    client: &aws_sdk_sqs::Client,
+    queue_url: &str,
+    fifo: bool,
+    batch: &[(String, String, String)],
+) -> Result<Vec<String>> {
+    let entries = batch
+        .iter()
+        .map(|(id, group, body)| {
+            let mut entry = SendMessageBatchRequestEntry::builder()
+                .id(id)
+                .message_body(body);
+            if fifo {
+                entry = entry.message_group_id(group).message_deduplication_id(id);
+            }
+            entry.build().context("Invalid SQS batch entry")
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = client
+        .send_message_batch()
+        .queue_url(queue_url)
+        .set_entries(Some(entries))
+        .send()
+        .await
+        .context("SendMessageBatch failed")?;
+    Ok(output.failed().iter().map(|f| f.id().to_string()).collect())
+}
+
+async fn dead_letter(
+    client: &aws_sdk_sqs::Client,
+    dlq_url: Option<&str>,
+    failed: Vec<(String, String, String)>,
+) {
+    let Some(dlq_url) = dlq_url else {
+        tracing::error!(
+            "Dropping {} commit messages after {} attempts (no COMMIT_QUEUE_DLQ_URL)",
+            failed.len(),
+            MAX_ATTEMPTS
+        );
+        return;
+    };
+    let fifo = dlq_url.ends_with(".fifo");
+    let lost = send_with_retry(client, dlq_url, fifo, &failed).await;
+    if lost.is_empty() {
+        tracing::warn!(
+            "Sent {} undeliverable commit messages to the DLQ",
+            failed.len()
+        );
+    } else {
+        tracing::error!(
+            "Dropping {} commit messages: the DLQ rejected them too",
+            lost.len()
+        );
+    }
+}
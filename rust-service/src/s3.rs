@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use serde::{Deserialize, Serialize};
+
+/// S3 requires every part except the last to be at least 5 MiB
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Thin wrapper around the AWS S3 client bound to the configured bucket
+#[derive(Clone)]
+pub struct S3Client {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3Client {
+    /// Build a client from `S3_BUCKET` and `S3_REGION` (credentials come from the
+    /// standard AWS chain). `S3_ENDPOINT` points at an S3-compatible store such as MinIO.
+    /// Returns None when `S3_BUCKET` is not set.
+    pub async fn new() -> Option<Self> {
+        let bucket = std::env::var("S3_BUCKET").ok().filter(|b| !b.is_empty())?;
+
+        let mut loader = aws_config::from_env();
+        if let Ok(region) = std::env::var("S3_REGION") {
+            loader = loader.region(aws_config::Region::new(region));
+        }
+        let shared = loader.load().await;
+
+        let mut config = aws_sdk_s3::config::Builder::from(&shared);
+        if let Ok(endpoint) = std::env::var("S3_ENDPOINT") {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        tracing::info!("S3 storage enabled (bucket: {})", bucket);
+        Some(Self {
+            client: aws_sdk_s3::Client::from_conf(config.build()),
+            bucket,
+        })
+    }
+
+    /// Same client and credentials, writing to another bucket
+    pub fn with_bucket(&self, bucket: &str) -> Self {
+        Self {
+            client: self.client.clone(),
+            bucket: bucket.to_string(),
+        }
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload s3://{}/{}", self.bucket, key))?;
+        Ok(())
+    }
+
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download s3://{}/{}", self.bucket, key))?;
+        let body = output
+            .body
+            .collect()
+            .await
+            .context("Failed to read S3 object body")?;
+        Ok(body.into_bytes().to_vec())
+    }
+}
+
+impl S3Client {
+    /// Start a multipart upload that buffers at most `part_size` bytes in memory
+    pub async fn start_multipart(
+        &self,
+        key: &str,
+        content_type: &str,
+        part_size: usize,
+    ) -> Result<MultipartUpload> {
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .send()
+            .await
+            .with_context(|| format!("Failed to start multipart upload to {}", key))?;
+        let upload_id = output
+            .upload_id()
+            .context("S3 did not return an upload id")?
+            .to_string();
+
+        Ok(MultipartUpload {
+            client: self.clone(),
+            key: key.to_string(),
+            upload_id,
+            part_size: part_size.max(MIN_PART_SIZE),
+            buffer: Vec::new(),
+            parts: Vec::new(),
+            bytes_uploaded: 0,
+        })
+    }
+
+    /// Continue a multipart upload started earlier (e.g. before a restart) from its saved parts
+    pub fn resume_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_size: usize,
+        parts: Vec<UploadedPart>,
+        bytes_uploaded: u64,
+    ) -> MultipartUpload {
+        MultipartUpload {
+            client: self.clone(),
+            key: key.to_string(),
+            upload_id: upload_id.to_string(),
+            part_size: part_size.max(MIN_PART_SIZE),
+            buffer: Vec::new(),
+            parts,
+            bytes_uploaded,
+        }
+    }
+}
+
+/// A part already stored by S3, enough to complete the upload later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub e_tag: Option<String>,
+}
+
+/// An in-progress multipart upload fed incrementally with `write`
+pub struct MultipartUpload {
+    client: S3Client,
+    key: String,
+    upload_id: String,
+    part_size: usize,
+    buffer: Vec<u8>,
+    parts: Vec<UploadedPart>,
+    bytes_uploaded: u64,
+}
+
+impl MultipartUpload {
+    /// Append bytes, uploading a part whenever the buffer reaches the part size.
+    /// Returns true when a part was uploaded, i.e. everything written so far is in S3.
+    pub async fn write(&mut self, data: &[u8]) -> Result<bool> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= self.part_size {
+            self.upload_buffer().await?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn upload_id(&self) -> &str {
+        &self.upload_id
+    }
+
+    pub fn part_size(&self) -> usize {
+        self.part_size
+    }
+
+    /// Parts uploaded so far
+    pub fn parts(&self) -> &[UploadedPart] {
+        &self.parts
+    }
+
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.bytes_uploaded
+    }
+
+    async fn upload_buffer(&mut self) -> Result<()> {
+        let part_number = self.parts.len() as i32 + 1;
+        let body = std::mem::take(&mut self.buffer);
+        let len = body.len() as u64;
+
+        let output = self
+            .client
+            .client
+            .upload_part()
+            .bucket(&self.client.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload part {} of {}", part_number, self.key))?;
+
+        self.parts.push(UploadedPart {
+            part_number,
+            e_tag: output.e_tag().map(str::to_string),
+        });
+        self.bytes_uploaded += len;
+        Ok(())
+    }
+
+    /// Upload the remaining buffer and complete the upload; returns the total size
+    pub async fn finish(mut self) -> Result<u64> {
+        // An upload needs at least one part, even if it is empty
+        if !self.buffer.is_empty() || self.parts.is_empty() {
+            self.upload_buffer().await?;
+        }
+        self.client
+            .client
+            .complete_multipart_upload()
+            .bucket(&self.client.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(
+                        self.parts
+                            .into_iter()
+                            .map(|part| {
+                                CompletedPart::builder()
+                                    .part_number(part.part_number)
+                                    .set_e_tag(part.e_tag)
+                                    .build()
+                            })
+                            .collect(),
+                    ))
+                    .build(),
+            )
+            .send()
+            .await
+            .with_context(|| format!("Failed to complete multipart upload of {}", self.key))?;
+        Ok(self.bytes_uploaded)
+    }
+
+    /// Abandon the upload so S3 discards the uploaded parts
+    pub async fn abort(self) {
+        if let Err(e) = self
+            .client
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.client.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .send()
+            .await
+        {
+            tracing::warn!("Failed to abort multipart upload of {}: {}", self.key, e);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+/// Stored text columns, each with its own sanitization policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    AuthorName,
+    AuthorEmail,
+    MessageTitle,
+    Message,
+    /// Newline-separated list of changed paths
+    ChangedPaths,
+    /// A single changed path
+    Path,
+    SignatureIdentity,
+    Summary,
+    /// Unified diff text of one file
+    Patch,
+}
+
+struct Policy {
+    max_len: usize,
+    /// Keep `\n` and `\r`; otherwise line breaks become a single space
+    multiline: bool,
+    keep_tabs: bool,
+    /// Characters dropped on top of the control characters sanitize_for_mysql removes
+    forbidden: &'static [char],
+}
+
+impl Field {
+    fn policy(self) -> Policy {
+        match self {
+            Field::AuthorName | Field::MessageTitle | Field::SignatureIdentity => Policy {
+                max_len: 500,
+                multiline: false,
+                keep_tabs: false,
+                forbidden: &[],
+            },
+            // Emails never contain whitespace or angle brackets; drop them outright
+            Field::AuthorEmail => Policy {
+                max_len: 500,
+                multiline: false,
+                keep_tabs: false,
+                forbidden: &['\n', '\r', '\t', ' ', '<', '>'],
+            },
+            Field::Message | Field::Summary => Policy {
+                max_len: 65000,
+                multiline: true,
+                keep_tabs: true,
+                forbidden: &[],
+            },
+            // Line breaks separate entries, so they must not appear inside a path
+            Field::ChangedPaths => Policy {
+                max_len: 65000,
+                multiline: true,
+                keep_tabs: false,
+                forbidden: &['\r'],
+            },
+            Field::Path => Policy {
+                max_len: 4000,
+                multiline: false,
+                keep_tabs: false,
+                forbidden: &[],
+            },
+            // MEDIUMTEXT column; the configured per-file limit is usually far lower
+            Field::Patch => Policy {
+                max_len: 4 * 1024 * 1024,
+                multiline: true,
+                keep_tabs: true,
+                forbidden: &[],
+            },
+        }
+    }
+}
+
+/// Clean a value for its column: sanitize_for_mysql, then the field's own rules
+pub fn sanitize(field: Field, value: &str) -> String {
+    sanitize_with_max(field, value, field.policy().max_len)
+}
+
+/// Like [`sanitize`], with a caller-supplied length limit
+pub fn sanitize_with_max(field: Field, value: &str, max_len: usize) -> String {
+    let policy = field.policy();
+    let mut out = String::with_capacity(value.len().min(max_len));
+    let mut last_was_break = false;
+
+    for c in crate::sanitize_for_mysql(value, usize::MAX).chars() {
+        if policy.forbidden.contains(&c) {
+            continue;
+        }
+        if (c == '\n' || c == '\r') && !policy.multiline {
+            // Collapse `\r\n` and runs of breaks into one space
+            if !last_was_break {
+                out.push(' ');
+            }
+            last_was_break = true;
+            continue;
+        }
+        last_was_break = false;
+        out.push(if c == '\t' && !policy.keep_tabs { ' ' } else { c });
+    }
+
+    let out = if policy.multiline { out } else { out.trim().to_string() };
+    out.chars().take(max_len).collect()
+}
@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+/// Longest query accepted, in characters
+const MAX_QUERY_CHARS: usize = 200;
+/// Characters of context kept on each side of the first match in a snippet
+const SNIPPET_CONTEXT: usize = 80;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchMode {
+    /// Relevance-ranked free text (MySQL natural language mode)
+    #[default]
+    Natural,
+    /// `+required -excluded "exact phrase" prefix*` operators (MySQL boolean mode)
+    Boolean,
+}
+
+impl SearchMode {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Natural => "IN NATURAL LANGUAGE MODE",
+            Self::Boolean => "IN BOOLEAN MODE",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    /// Words or phrase to search commit messages for
+    pub q: String,
+    /// Only search this repository
+    pub repository_id: Option<String>,
+    /// `natural` (default) or `boolean`
+    pub mode: Option<SearchMode>,
+    /// Maximum number of hits (default 20, max 100)
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct SearchRow {
+    id: String,
+    repository_id: String,
+    sha: String,
+    author_name: String,
+    commit_date: chrono::DateTime<chrono::Utc>,
+    message_title: String,
+    message: String,
+    score: f64,
+}
+
+/// Character range of a matched term within a snippet
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: String,
+    pub repository_id: String,
+    pub sha: String,
+    pub author_name: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+    pub message_title: String,
+    /// MySQL relevance score; higher is better
+    pub score: f64,
+    /// Excerpt of the message around the first match
+    pub snippet: String,
+    /// Matched terms within `snippet`, as character offsets
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    pub query: String,
+    pub mode: SearchMode,
+    pub hits: Vec<SearchHit>,
+}
+
+/// Full-text search over commit messages, ranked by relevance
+#[utoipa::path(
+    get,
+    path = "/search/commits",
+    tag = "commits",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = SearchResponse),
+        (status = 400, description = "Empty, too long or malformed query", body = String)
+    )
+)]
+pub async fn search_commits(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let text = query.q.trim().to_string();
+    if text.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    if text.chars().count() > MAX_QUERY_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("q must be at most {} characters", MAX_QUERY_CHARS),
+        ));
+    }
+    let mode = query.mode.unwrap_or_default();
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0);
+
+    // Mode is one of two fixed strings, so formatting it into the SQL is safe
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT id, repositoryId, sha, authorName, commitDate, messageTitle, message, \
+         MATCH(message) AGAINST(",
+    );
+    builder.push_bind(&text).push(format!(
+        " {}) AS score FROM Commit WHERE MATCH(message) AGAINST(",
+        mode.sql()
+    ));
+    builder.push_bind(&text).push(format!(" {})", mode.sql()));
+    if let Some(repository_id) = &query.repository_id {
+        builder
+            .push(" AND repositoryId = ")
+            .push_bind(repository_id);
+    }
+    builder
+        .push(" ORDER BY score DESC, commitDate DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let rows: Vec<SearchRow> = builder
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| match e {
+            // Unbalanced quotes/operators surface as a syntax error (SQLSTATE 42000)
+            sqlx::Error::Database(db)
+                if mode == SearchMode::Boolean && db.code().as_deref() == Some("42000") =>
+            {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid boolean search query: {}", db.message()),
+                )
+            }
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    let terms = highlight_terms(&text, mode);
+    let hits = rows
+        .into_iter()
+        .map(|row| {
+            let (snippet, highlights) = snippet(&row.message, &terms);
+            SearchHit {
+                id: row.id,
+                repository_id: row.repository_id,
+                sha: row.sha,
+                author_name: row.author_name,
+                commit_date: row.commit_date,
+                message_title: row.message_title,
+                score: row.score,
+                snippet,
+                highlights,
+            }
+        })
+        .collect();
+
+    Ok(Json(SearchResponse {
+        query: text,
+        mode,
+        hits,
+    }))
+}
+
+/// Lowercased words and quoted phrases to highlight, leaving out boolean-mode
+/// exclusions (`-term`) and stripping operators
+fn highlight_terms(query: &str, mode: SearchMode) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut rest = query;
+    while let Some(start) = rest.find('"') {
+        let (before, after) = rest.split_at(start);
+        push_words(before, mode, &mut terms);
+        let after = &after[1..];
+        let end = after.find('"').unwrap_or(after.len());
+        let phrase = after[..end].trim().to_lowercase();
+        let excluded = mode == SearchMode::Boolean && before.trim_end().ends_with('-');
+        if !phrase.is_empty() && !excluded {
+            terms.push(phrase);
+        }
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    push_words(rest, mode, &mut terms);
+    terms
+}
+
+fn push_words(text: &str, mode: SearchMode, terms: &mut Vec<String>) {
+    for word in text.split_whitespace() {
+        if mode == SearchMode::Boolean && word.starts_with('-') {
+            continue;
+        }
+        let word = word
+            .trim_matches(|c: char| "+-~<>()*@".contains(c))
+            .to_lowercase();
+        if !word.is_empty() {
+            terms.push(word);
+        }
+    }
+}
+
+/// Window of the message around the earliest term match, with the character
+/// ranges of every term occurrence inside it
+fn snippet(message: &str, terms: &[String]) -> (String, Vec<Highlight>) {
+    let chars: Vec<char> = message.chars().collect();
+    let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    // Lowercasing can change the length of rare characters; fall back to no highlights
+    let searchable = lower.len() == chars.len();
+
+    let matches_at = |pos: usize, term: &[char]| lower.get(pos..pos + term.len()) == Some(term);
+    let term_chars: Vec<Vec<char>> = terms.iter().map(|t| t.chars().collect()).collect();
+
+    let first = searchable
+        .then(|| (0..chars.len()).find(|&pos| term_chars.iter().any(|term| matches_at(pos, term))))
+        .flatten();
+
+    let (start, end) = match first {
+        Some(pos) => (
+            pos.saturating_sub(SNIPPET_CONTEXT),
+            (pos + SNIPPET_CONTEXT).min(chars.len()),
+        ),
+        None => (0, (2 * SNIPPET_CONTEXT).min(chars.len())),
+    };
+
+    let mut highlights = Vec::new();
+    if searchable {
+        let mut pos = start;
+        while pos < end {
+            let longest = term_chars
+                .iter()
+                .filter(|term| !term.is_empty() && matches_at(pos, term))
+                .map(Vec::len)
+                .max();
+            match longest {
+                Some(len) => {
+                    highlights.push(Highlight {
+                        start: pos - start,
+                        end: (pos + len).min(end) - start,
+                    });
+                    pos += len;
+                }
+                None => pos += 1,
+            }
+        }
+    }
+
+    (chars[start..end].iter().collect(), highlights)
+}
@@ -0,0 +1,462 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+
+/// Where trusted signing keys live, from `GPG_HOME` and `SSH_ALLOWED_SIGNERS`
+#[derive(Debug, Clone, Default)]
+pub struct Keyring {
+    pub gpg_home: Option<PathBuf>,
+    pub ssh_allowed_signers: Option<PathBuf>,
+}
+
+impl Keyring {
+    pub fn from_env() -> Self {
+        Self {
+            gpg_home: std::env::var("GPG_HOME").ok().filter(|v| !v.is_empty()).map(PathBuf::from),
+            ssh_allowed_signers: std::env::var("SSH_ALLOWED_SIGNERS")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+}
+
+/// Signature state of a single commit
+#[derive(Debug, Clone, Default)]
+pub struct SignatureInfo {
+    pub signed: bool,
+    /// None when the signature was not checked (verification disabled or no keyring)
+    pub verified: Option<bool>,
+    /// Email (or principal) of the key that produced a good signature
+    pub identity: Option<String>,
+    /// Signed by a verified key whose identity differs from the commit author
+    pub mismatch: bool,
+    /// OpenPGP issuer key id (16 hex digits), read from the signature packet
+    pub key_id: Option<String>,
+    /// OpenPGP issuer fingerprint (hex) or SSH key fingerprint (`SHA256:...`)
+    pub fingerprint: Option<String>,
+}
+
+/// Whether a commit carries a signature, without parsing or verifying it
+pub fn is_signed(repo: &Repository, oid: Oid) -> bool {
+    repo.extract_signature(&oid, None).is_ok()
+}
+
+/// Detect a commit signature and, when a keyring is given, verify it and compare
+/// the signer identity with the author email
+pub fn inspect(
+    repo: &Repository,
+    oid: Oid,
+    author_email: &str,
+    keyring: Option<&Keyring>,
+) -> SignatureInfo {
+    let (signature, signed_data) = match repo.extract_signature(&oid, None) {
+        Ok((sig, data)) => (sig.to_vec(), data.to_vec()),
+        Err(_) => return SignatureInfo::default(),
+    };
+
+    let (key_id, fingerprint) = signer_key(&signature).unwrap_or_default();
+    let mut info = SignatureInfo {
+        signed: true,
+        key_id,
+        fingerprint,
+        ..Default::default()
+    };
+    let Some(keyring) = keyring else {
+        return info;
+    };
+
+    let result = if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+        keyring
+            .ssh_allowed_signers
+            .as_deref()
+            .map(|signers| verify_ssh(&signature, &signed_data, signers))
+    } else {
+        keyring
+            .gpg_home
+            .as_deref()
+            .map(|home| verify_gpg(&signature, &signed_data, home))
+    };
+
+    match result {
+        Some(Ok(identity)) => {
+            info.verified = Some(identity.is_some());
+            if let Some(identity) = identity {
+                info.mismatch = !identity.eq_ignore_ascii_case(author_email);
+                info.identity = Some(identity);
+            }
+        }
+        Some(Err(e)) => tracing::warn!("Signature check failed for {}: {}", oid, e),
+        None => {}
+    }
+
+    info
+}
+
+/// Key id and fingerprint of the signing key, parsed from an armored OpenPGP or SSH
+/// signature without verifying it. None for formats we can't read (e.g. X.509).
+fn signer_key(signature: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let text = std::str::from_utf8(signature).ok()?;
+    if let Some(body) = dearmor(text, "SSH SIGNATURE") {
+        return Some((None, ssh_fingerprint(&body)));
+    }
+    let body = dearmor(text, "PGP SIGNATURE")?;
+    pgp_issuer(&body)
+}
+
+/// Base64 payload between `-----BEGIN <label>-----` and `-----END <label>-----`,
+/// skipping armor headers and the CRC line
+fn dearmor(text: &str, label: &str) -> Option<Vec<u8>> {
+    use base64::Engine;
+
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let mut lines = text.lines().map(str::trim).skip_while(|l| *l != begin).skip(1);
+    let mut payload = String::new();
+    let mut in_headers = label.starts_with("PGP");
+    for line in lines.by_ref() {
+        if line == end {
+            return base64::engine::general_purpose::STANDARD.decode(payload).ok();
+        }
+        if in_headers {
+            // OpenPGP armor: `Key: value` headers, then a blank line
+            if line.is_empty() {
+                in_headers = false;
+            } else if !line.contains(": ") {
+                in_headers = false;
+                payload.push_str(line);
+            }
+            continue;
+        }
+        if line.starts_with('=') {
+            continue;
+        }
+        payload.push_str(line);
+    }
+    None
+}
+
+/// Issuer key id and fingerprint from the first OpenPGP signature packet (RFC 4880 / 9580)
+fn pgp_issuer(data: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let packet = first_packet(data, 2)?;
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>();
+
+    match *packet.first()? {
+        3 => {
+            // v3: version, hashed length (5), type, creation time, key id
+            let key_id = packet.get(7..15)?;
+            Some((Some(hex(key_id)), None))
+        }
+        version @ 4..=6 => {
+            // Subpacket areas have 2-byte lengths before v6, 4-byte lengths in v6
+            let len_size = if version == 6 { 4 } else { 2 };
+            let mut pos = 4;
+            let (mut key_id, mut fingerprint) = (None, None);
+            for _ in 0..2 {
+                let len = be_uint(packet.get(pos..pos + len_size)?);
+                pos += len_size;
+                let area = packet.get(pos..pos + len)?;
+                pos += len;
+                for (kind, body) in subpackets(area) {
+                    match kind {
+                        // Issuer key id
+                        16 if body.len() == 8 => key_id = key_id.or(Some(hex(body))),
+                        // Issuer fingerprint: key version, then the fingerprint
+                        33 if body.len() > 1 => fingerprint = fingerprint.or(Some(hex(&body[1..]))),
+                        _ => {}
+                    }
+                }
+            }
+            // A v4 key id is the low 64 bits of its fingerprint
+            if key_id.is_none() && version == 4 {
+                key_id = fingerprint
+                    .as_deref()
+                    .filter(|f| f.len() == 40)
+                    .map(|f| f[24..].to_string());
+            }
+            Some((key_id, fingerprint))
+        }
+        _ => None,
+    }
+}
+
+/// Body of the first packet with the given tag, for old- and new-format packet headers
+fn first_packet(data: &[u8], wanted_tag: u8) -> Option<&[u8]> {
+    let mut pos = 0;
+    while pos < data.len() {
+        let header = data[pos];
+        if header & 0x80 == 0 {
+            return None;
+        }
+        let (tag, len, header_len) = if header & 0x40 != 0 {
+            let tag = header & 0x3f;
+            let first = *data.get(pos + 1)? as usize;
+            match first {
+                0..=191 => (tag, first, 2),
+                192..=223 => {
+                    let second = *data.get(pos + 2)? as usize;
+                    (tag, ((first - 192) << 8) + second + 192, 3)
+                }
+                255 => (tag, be_uint(data.get(pos + 2..pos + 6)?), 6),
+                // Partial body lengths don't occur in signature packets
+                _ => return None,
+            }
+        } else {
+            let tag = (header >> 2) & 0x0f;
+            match header & 0x03 {
+                0 => (tag, *data.get(pos + 1)? as usize, 2),
+                1 => (tag, be_uint(data.get(pos + 1..pos + 3)?), 3),
+                2 => (tag, be_uint(data.get(pos + 1..pos + 5)?), 5),
+                _ => (tag, data.len() - pos - 1, 1),
+            }
+        };
+        let body = data.get(pos + header_len..pos + header_len + len)?;
+        if tag == wanted_tag {
+            return Some(body);
+        }
+        pos += header_len + len;
+    }
+    None
+}
+
+/// (type, body) of each signature subpacket in an area, ignoring the critical bit
+fn subpackets(mut area: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut result = Vec::new();
+    while let Some(&first) = area.first() {
+        let (len, header_len) = match first {
+            0..=191 => (first as usize, 1),
+            192..=254 => match area.get(1) {
+                Some(&second) => ((((first as usize) - 192) << 8) + second as usize + 192, 2),
+                None => break,
+            },
+            255 => match area.get(1..5) {
+                Some(bytes) => (be_uint(bytes), 5),
+                None => break,
+            },
+        };
+        // The length covers the type octet and the body
+        let Some(packet) = area.get(header_len..header_len + len).filter(|p| !p.is_empty())
+        else {
+            break;
+        };
+        result.push((packet[0] & 0x7f, &packet[1..]));
+        area = &area[header_len + len..];
+    }
+    result
+}
+
+fn be_uint(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as usize)
+}
+
+/// `SHA256:` fingerprint of the public key embedded in an SSHSIG blob, as `ssh-keygen -l` prints it
+fn ssh_fingerprint(blob: &[u8]) -> Option<String> {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+
+    // "SSHSIG", uint32 version, string publickey, ...
+    let rest = blob.strip_prefix(b"SSHSIG")?;
+    let len = be_uint(rest.get(4..8)?);
+    let public_key = rest.get(8..8 + len)?;
+    let digest = Sha256::digest(public_key);
+    Some(format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest)
+    ))
+}
+
+/// Write the detached signature to a temp file for tools that need a path
+fn write_temp_signature(signature: &[u8]) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("git-doc-sig-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, signature).context("Failed to write signature file")?;
+    Ok(path)
+}
+
+fn run_with_stdin(command: &mut Command, stdin: &[u8]) -> Result<std::process::Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn signature verifier")?;
+    if let Some(mut input) = child.stdin.take() {
+        input.write_all(stdin)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// Verify an OpenPGP signature; returns the signer's email on a good signature
+fn verify_gpg(signature: &[u8], data: &[u8], gpg_home: &Path) -> Result<Option<String>> {
+    let sig_path = write_temp_signature(signature)?;
+    let output = run_with_stdin(
+        Command::new("gpg")
+            .env("GNUPGHOME", gpg_home)
+            .args(["--batch", "--no-tty", "--status-fd=1", "--verify"])
+            .arg(&sig_path)
+            .arg("-"),
+        data,
+    );
+    let _ = std::fs::remove_file(&sig_path);
+    let output = output?;
+
+    // [GNUPG:] GOODSIG <long keyid> <user id>
+    let status = String::from_utf8_lossy(&output.stdout);
+    let identity = status
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] GOODSIG "))
+        .and_then(|rest| rest.split_once(' ').map(|(_, uid)| uid))
+        .map(|uid| {
+            uid.rsplit_once('<')
+                .and_then(|(_, email)| email.strip_suffix('>'))
+                .unwrap_or(uid)
+                .to_string()
+        });
+
+    Ok(identity)
+}
+
+/// Verify an SSH signature against an allowed-signers file; returns the principal
+fn verify_ssh(signature: &[u8], data: &[u8], allowed_signers: &Path) -> Result<Option<String>> {
+    let sig_path = write_temp_signature(signature)?;
+    let result = (|| {
+        let principals = Command::new("ssh-keygen")
+            .args(["-Y", "find-principals", "-f"])
+            .arg(allowed_signers)
+            .arg("-s")
+            .arg(&sig_path)
+            .output()
+            .context("Failed to run ssh-keygen")?;
+        let Some(principal) = String::from_utf8_lossy(&principals.stdout)
+            .lines()
+            .next()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let verify = run_with_stdin(
+            Command::new("ssh-keygen")
+                .args(["-Y", "verify", "-n", "git", "-f"])
+                .arg(allowed_signers)
+                .arg("-I")
+                .arg(&principal)
+                .arg("-s")
+                .arg(&sig_path),
+            data,
+        )?;
+        Ok(verify.status.success().then_some(principal))
+    })();
+    let _ = std::fs::remove_file(&sig_path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Detached signatures made with gpg 2.2 and ssh-keygen -Y sign over the same data
+
+    /// Ed25519 key, old-format packet with a one-byte length
+    const GPG_ED25519: &str = "-----BEGIN PGP SIGNATURE-----
+
+iHUEABYIAB0WIQRq85L/+eWW+pA9Vy7Y21f/fwvEsAUCatIdsQAKCRDY21f/fwvE
+sHuwAP0dXFAYrJBsOLh+yu+PHvN52VfWeby4ly2halz0EM1TIgD9HBszFlslYRaG
+dPumwbDdrZKhyZbNJo4iAEVqE/yFeA8=
+=7mlG
+-----END PGP SIGNATURE-----
+";
+
+    /// RSA 2048 key, two-byte packet length and a `Comment` armor header
+    const GPG_RSA: &str = "-----BEGIN PGP SIGNATURE-----
+Comment: signed for a test
+
+iQEzBAABCgAdFiEE8Zut3h+WkUaWLWm42KX2snbIGasFAmrSHbUACgkQ2KX2snbI
+GaufpwgAsuvJV+h+lMJCaUyiaC2ZPPPz+EPA92rEKUA9d4xDPqf8laTeT/cOvCBQ
+IhrEa7cQFTHr0Dnnbzs+bxMMIrpke+UJx8TXFD0rnLEvxC/xdDl8GzNOBJrx6eQT
+D9wKwFaUMlr+Cf113YqmOUfhlFZOD2lOonca4ocya0cSOQRJegg/wYvCoYXCUkIK
+lCwAXUXCuCXr9K5Fc9GG9ZXlOIBckLEg0dIIJtDnxk3EcGjz0KftNXPse1oukGot
+VlvcvI6LjOzfrj0qQTNECi6RbJPW1xWBgetqFwT0g+0UEBrqZuD6W5EFEtj1YBlk
+diB0ibRA0HFSobOeXtb2jpdBCPbQcQ==
+=gMKi
+-----END PGP SIGNATURE-----
+";
+
+    const SSH_ED25519: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgIOmUPQwPTwak4fsdwJl1kAJl3C
+sd7PkZW1aLbm5PiNsAAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+AAAAQDnR5OMWM+fJ45zQGRq9Grjz2o82XwAVhrt9QVhvyPiF0b2NOZWAM1Rh4ca25zrzbb
+I5L+pJikEQOIVZZkHdIgY=
+-----END SSH SIGNATURE-----
+";
+
+    fn key(id: &str, fingerprint: &str) -> Option<(Option<String>, Option<String>)> {
+        Some((Some(id.to_string()), Some(fingerprint.to_string())))
+    }
+
+    #[test]
+    fn reads_gpg_issuer() {
+        assert_eq!(
+            signer_key(GPG_ED25519.as_bytes()),
+            key(
+                "D8DB57FF7F0BC4B0",
+                "6AF392FFF9E596FA903D572ED8DB57FF7F0BC4B0"
+            )
+        );
+        assert_eq!(
+            signer_key(GPG_RSA.as_bytes()),
+            key(
+                "D8A5F6B276C819AB",
+                "F19BADDE1F969146962D69B8D8A5F6B276C819AB"
+            )
+        );
+    }
+
+    #[test]
+    fn reads_ssh_fingerprint() {
+        assert_eq!(
+            signer_key(SSH_ED25519.as_bytes()),
+            Some((
+                None,
+                Some("SHA256:ixJHM6NiOZxpCoLUd3qeH6Q7lkMLHwDyH2yuCM88Yww".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn reads_v3_key_id() {
+        // Old-format tag 2: version 3, hashed length 5, type, creation time, key id, ...
+        let mut packet = vec![0x88, 19, 3, 5, 0x00, 0, 0, 0, 0];
+        packet.extend_from_slice(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+        packet.extend_from_slice(&[1, 8, 0xAB, 0xCD]);
+        assert_eq!(
+            pgp_issuer(&packet),
+            Some((Some("0123456789ABCDEF".to_string()), None))
+        );
+    }
+
+    #[test]
+    fn truncated_or_garbage_signatures_have_no_key() {
+        let body = dearmor(GPG_ED25519, "PGP SIGNATURE").unwrap();
+        for len in [0, 1, 2, 10, 30, body.len() - 1] {
+            assert_eq!(pgp_issuer(&body[..len]), None, "{} bytes", len);
+        }
+        // Not a packet header
+        let mut corrupt = body.clone();
+        corrupt[0] = 0x00;
+        assert_eq!(pgp_issuer(&corrupt), None);
+
+        let blob = dearmor(SSH_ED25519, "SSH SIGNATURE").unwrap();
+        assert_eq!(ssh_fingerprint(&blob[..20]), None);
+        assert_eq!(ssh_fingerprint(b"SSHSIX\0\0\0\x01"), None);
+
+        let garbage = "-----BEGIN PGP SIGNATURE-----\n\nnot base64!\n-----END PGP SIGNATURE-----\n";
+        assert_eq!(signer_key(garbage.as_bytes()), None);
+        let unterminated = GPG_ED25519.replace("-----END PGP SIGNATURE-----", "");
+        assert_eq!(signer_key(unterminated.as_bytes()), None);
+        assert_eq!(signer_key(b"\xff\xfe binary"), None);
+    }
+}
@@ -0,0 +1,936 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Datelike, Timelike};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::anonymize::{self, AnonymizeQuery, Anonymizer};
+use crate::AppState;
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraProjectStats {
+    pub project: String,
+    pub commit_count: i64,
+    pub ticket_count: i64,
+}
+
+/// Distinct JIRA project prefixes referenced by a repository's commits
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/jira-projects",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id")),
+    responses((status = 200, body = [JiraProjectStats]))
+)]
+pub async fn jira_projects(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+) -> Result<Json<Vec<JiraProjectStats>>, (StatusCode, String)> {
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT k.project, COUNT(DISTINCT k.commitId), COUNT(DISTINCT k.jiraKey)
+        FROM CommitJiraKey k
+        JOIN Commit c ON c.id = k.commitId
+        WHERE c.repositoryId = ?
+        GROUP BY k.project
+        ORDER BY COUNT(DISTINCT k.commitId) DESC, k.project
+        "#,
+    )
+    .bind(&repository_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|(project, commit_count, ticket_count)| JiraProjectStats {
+                project,
+                commit_count,
+                ticket_count,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCommit {
+    pub sha: String,
+    pub message_title: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub patch_id: String,
+    pub commits: Vec<DuplicateCommit>,
+}
+
+/// Commits whose diffs are identical (same patch-id) despite different SHAs
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/duplicates",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id")),
+    responses((status = 200, body = [DuplicateGroup]))
+)]
+pub async fn duplicate_commits(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+) -> Result<Json<Vec<DuplicateGroup>>, (StatusCode, String)> {
+    let rows: Vec<(String, String, String, chrono::DateTime<chrono::Utc>)> = sqlx::query_as(
+        r#"
+        SELECT c.patchId, c.sha, c.messageTitle, c.commitDate
+        FROM Commit c
+        JOIN (
+            SELECT patchId
+            FROM Commit
+            WHERE repositoryId = ? AND patchId IS NOT NULL
+            GROUP BY patchId
+            HAVING COUNT(*) > 1
+        ) d ON d.patchId = c.patchId
+        WHERE c.repositoryId = ?
+        ORDER BY c.patchId, c.commitDate
+        "#,
+    )
+    .bind(&repository_id)
+    .bind(&repository_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (patch_id, sha, message_title, commit_date) in rows {
+        let commit = DuplicateCommit {
+            sha,
+            message_title,
+            commit_date,
+        };
+        match groups.last_mut() {
+            Some(group) if group.patch_id == patch_id => group.commits.push(commit),
+            _ => groups.push(DuplicateGroup {
+                patch_id,
+                commits: vec![commit],
+            }),
+        }
+    }
+
+    Ok(Json(groups))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct PunchcardQuery {
+    /// IANA timezone for bucketing (default `STATS_TIMEZONE`, then UTC)
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Punchcard {
+    pub timezone: String,
+    /// 7 rows (Monday..Sunday) of 24 hourly commit counts
+    pub matrix: Vec<Vec<u32>>,
+    pub total: u32,
+}
+
+/// Commit counts bucketed by weekday and hour of day, for a punchcard chart
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/punchcard",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), PunchcardQuery),
+    responses(
+        (status = 200, body = Punchcard),
+        (status = 400, description = "Unknown timezone", body = String)
+    )
+)]
+pub async fn punchcard(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<PunchcardQuery>,
+) -> Result<Json<Punchcard>, (StatusCode, String)> {
+    let tz_name = query
+        .tz
+        .or_else(|| std::env::var("STATS_TIMEZONE").ok())
+        .unwrap_or_else(|| "UTC".to_string());
+    let tz: chrono_tz::Tz = tz_name
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, format!("Unknown timezone: {}", tz_name)))?;
+
+    let mut matrix = vec![vec![0u32; 24]; 7];
+    let mut total = 0u32;
+
+    let mut rows = sqlx::query_as::<_, (chrono::DateTime<chrono::Utc>,)>(
+        "SELECT commitDate FROM Commit WHERE repositoryId = ?",
+    )
+    .bind(&repository_id)
+    .fetch(&state.db);
+
+    while let Some((commit_date,)) = rows
+        .try_next()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        let local = commit_date.with_timezone(&tz);
+        let day = local.weekday().num_days_from_monday() as usize;
+        matrix[day][local.hour() as usize] += 1;
+        total += 1;
+    }
+
+    Ok(Json(Punchcard {
+        timezone: tz_name,
+        matrix,
+        total,
+    }))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DirectoryChurnQuery {
+    /// Number of leading path segments to group by (default 1, max 10)
+    pub depth: Option<usize>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryChurn {
+    /// Directory prefix with a trailing slash; `/` for files at the repository root
+    pub directory: String,
+    /// File changes under the prefix, summed over commits
+    pub file_changes: u64,
+    /// Commits touching at least one file under the prefix
+    pub commit_count: u64,
+}
+
+/// Directory prefix of `path` cut to at most `depth` segments
+fn directory_prefix(path: &str, depth: usize) -> String {
+    let segments: Vec<&str> = path.split('/').collect();
+    // The last segment is the file name
+    let dirs = &segments[..segments.len() - 1];
+    if dirs.is_empty() {
+        return "/".to_string();
+    }
+    format!("{}/", dirs[..dirs.len().min(depth)].join("/"))
+}
+
+/// Changed-path churn rolled up to directory prefixes, hottest first
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/directories",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), DirectoryChurnQuery),
+    responses((status = 200, body = [DirectoryChurn]))
+)]
+pub async fn directory_churn(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<DirectoryChurnQuery>,
+) -> Result<Json<Vec<DirectoryChurn>>, (StatusCode, String)> {
+    let depth = query.depth.unwrap_or(1).clamp(1, 10);
+
+    // (file changes, commits, last commit seen) per prefix; rows arrive grouped by commit
+    let mut totals: HashMap<String, (u64, u64, String)> = HashMap::new();
+
+    let mut rows = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT f.commitId, f.path
+        FROM CommitFile f
+        JOIN Commit c ON c.id = f.commitId
+        WHERE c.repositoryId = ? AND f.excluded = FALSE
+        ORDER BY f.commitId
+        "#,
+    )
+    .bind(&repository_id)
+    .fetch(&state.db);
+
+    while let Some((commit_id, path)) = rows
+        .try_next()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        let entry = totals
+            .entry(directory_prefix(&path, depth))
+            .or_insert_with(|| (0, 0, String::new()));
+        entry.0 += 1;
+        if entry.2 != commit_id {
+            entry.1 += 1;
+            entry.2 = commit_id;
+        }
+    }
+
+    let mut churn: Vec<DirectoryChurn> = totals
+        .into_iter()
+        .map(|(directory, (file_changes, commit_count, _))| DirectoryChurn {
+            directory,
+            file_changes,
+            commit_count,
+        })
+        .collect();
+    churn.sort_by(|a, b| {
+        b.file_changes
+            .cmp(&a.file_changes)
+            .then_with(|| a.directory.cmp(&b.directory))
+    });
+
+    Ok(Json(churn))
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CoAuthorPair {
+    /// The two emails (or anonymous ids), in lexical order
+    pub author_a: String,
+    pub author_b: String,
+    /// Commits both of them authored or co-authored
+    pub commit_count: u64,
+}
+
+/// Count each pair of people credited on one commit, then reset the buffer for the next
+fn add_commit_pairs(people: &mut Vec<String>, pairs: &mut HashMap<(String, String), u64>) {
+    people.sort();
+    people.dedup();
+    for (i, a) in people.iter().enumerate() {
+        for b in &people[i + 1..] {
+            *pairs.entry((a.clone(), b.clone())).or_insert(0) += 1;
+        }
+    }
+    people.clear();
+}
+
+/// Who co-authors with whom, as weighted pairs for a collaboration graph
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/co-authors",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), AnonymizeQuery),
+    responses((status = 200, body = [CoAuthorPair]))
+)]
+pub async fn co_author_pairs(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<Vec<CoAuthorPair>>, (StatusCode, String)> {
+    let anonymizer = Anonymizer::for_request(&anonymize);
+    let person = |email: String| match &anonymizer {
+        Some(anonymizer) => anonymizer.id(&email),
+        None => email,
+    };
+
+    let mut rows = sqlx::query_as::<_, (String, String, String)>(
+        r#"
+        SELECT c.id, c.authorEmail, k.email
+        FROM CommitCoAuthor k
+        JOIN Commit c ON c.id = k.commitId
+        WHERE c.repositoryId = ?
+        ORDER BY c.id
+        "#,
+    )
+    .bind(&repository_id)
+    .fetch(&state.db);
+
+    let mut pairs: HashMap<(String, String), u64> = HashMap::new();
+    let mut current_commit = String::new();
+    let mut people: Vec<String> = Vec::new();
+
+    while let Some((commit_id, author_email, co_author_email)) = rows
+        .try_next()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        if commit_id != current_commit {
+            add_commit_pairs(&mut people, &mut pairs);
+            current_commit = commit_id;
+            people.push(person(author_email));
+        }
+        people.push(person(co_author_email));
+    }
+    add_commit_pairs(&mut people, &mut pairs);
+
+    let mut result: Vec<CoAuthorPair> = pairs
+        .into_iter()
+        .map(|((author_a, author_b), commit_count)| CoAuthorPair {
+            author_a,
+            author_b,
+            commit_count,
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        b.commit_count
+            .cmp(&a.commit_count)
+            .then_with(|| (&a.author_a, &a.author_b).cmp(&(&b.author_a, &b.author_b)))
+    });
+
+    Ok(Json(result))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct SignatureMismatch {
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub signature_identity: Option<String>,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+}
+
+/// Commits with a verified signature from someone other than the claimed author
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/signature-mismatches",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), AnonymizeQuery),
+    responses((status = 200, body = [SignatureMismatch]))
+)]
+pub async fn signature_mismatches(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<Vec<SignatureMismatch>>, (StatusCode, String)> {
+    let mut rows: Vec<SignatureMismatch> = sqlx::query_as(
+        r#"
+        SELECT sha, authorName, authorEmail, signatureIdentity, commitDate
+        FROM Commit
+        WHERE repositoryId = ? AND signatureMismatch = TRUE
+        ORDER BY commitDate DESC
+        "#,
+    )
+    .bind(&repository_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(anonymizer) = Anonymizer::for_request(&anonymize) {
+        for row in &mut rows {
+            anonymizer.author(&mut row.author_email, &mut row.author_name);
+            row.signature_identity = row.signature_identity.as_deref().map(|s| anonymizer.id(s));
+        }
+    }
+
+    Ok(Json(rows))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeFilesQuery {
+    /// Only commits whose largest file is at least this many bytes (default 1 MiB)
+    pub min_size: Option<i64>,
+    /// Maximum number of commits returned (default 50, max 500)
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "camelCase")]
+pub struct LargeFileCommit {
+    pub sha: String,
+    pub author_email: String,
+    pub author_name: String,
+    pub commit_date: chrono::DateTime<chrono::Utc>,
+    pub message_title: String,
+    pub largest_file_path: String,
+    pub largest_file_size: i64,
+}
+
+/// Commits that added or modified the biggest files, largest first
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/large-files",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), LargeFilesQuery, AnonymizeQuery),
+    responses((status = 200, body = [LargeFileCommit]))
+)]
+pub async fn large_files(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<LargeFilesQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<Vec<LargeFileCommit>>, (StatusCode, String)> {
+    let mut rows: Vec<LargeFileCommit> = sqlx::query_as(
+        r#"
+        SELECT sha, authorEmail, authorName, commitDate, messageTitle, largestFilePath, largestFileSize
+        FROM Commit
+        WHERE repositoryId = ? AND largestFileSize >= ?
+        ORDER BY largestFileSize DESC, commitDate
+        LIMIT ?
+        "#,
+    )
+    .bind(&repository_id)
+    .bind(query.min_size.unwrap_or(1024 * 1024).max(0))
+    .bind(query.limit.unwrap_or(50).clamp(1, 500))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    anonymize::authors(&Anonymizer::for_request(&anonymize), &mut rows, |row| {
+        (&mut row.author_email, &mut row.author_name)
+    });
+
+    Ok(Json(rows))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorChurnQuery {
+    /// Comma-separated rolling window lengths in days (default `30,90`, at most 5 windows)
+    pub windows: Option<String>,
+    /// Last day of the most recent point, YYYY-MM-DD (default today, UTC)
+    pub end: Option<String>,
+    /// Days between consecutive points (default 7)
+    pub step: Option<u32>,
+    /// Number of points per window (default 12, max 104)
+    pub points: Option<u32>,
+    /// Authors listed per point; the rest are summed into `otherChurn` (default 10, max 100)
+    pub top_authors: Option<usize>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorChurnShare {
+    pub author_email: String,
+    pub author_name: String,
+    /// Files changed by the author's commits in the window
+    pub churn: u64,
+    /// Fraction (0..1) of the window's churn
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorChurnPoint {
+    /// Last day covered by the window (inclusive)
+    pub end: chrono::NaiveDate,
+    pub total_churn: u64,
+    pub authors: Vec<AuthorChurnShare>,
+    /// Churn of authors beyond `topAuthors`
+    pub other_churn: u64,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorChurnWindow {
+    pub days: u32,
+    /// Oldest point first
+    pub points: Vec<AuthorChurnPoint>,
+}
+
+#[derive(sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct ChurnRow {
+    author_email: String,
+    author_name: String,
+    commit_date: chrono::DateTime<chrono::Utc>,
+    files_changed: i32,
+}
+
+/// Each author's share of churn (files changed) over rolling windows, sampled at
+/// regular points for trend lines. Commits analyzed with `skipDiffs` are left out.
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/author-churn",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), AuthorChurnQuery, AnonymizeQuery),
+    responses(
+        (status = 200, body = [AuthorChurnWindow]),
+        (status = 400, description = "Invalid windows or end date", body = String)
+    )
+)]
+pub async fn author_churn(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<AuthorChurnQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<Vec<AuthorChurnWindow>>, (StatusCode, String)> {
+    let windows: Vec<u32> = query
+        .windows
+        .as_deref()
+        .unwrap_or("30,90")
+        .split(',')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(|w| match w.parse::<u32>() {
+            Ok(days) if (1..=3650).contains(&days) => Ok(days),
+            _ => Err((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid window '{}', expected 1-3650 days", w),
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+    if windows.is_empty() || windows.len() > 5 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "windows must list 1 to 5 window lengths".to_string(),
+        ));
+    }
+    let end = match query.end.as_deref() {
+        Some(raw) => chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid end date: {}", raw)))?,
+        None => chrono::Utc::now().date_naive(),
+    };
+    let step = query.step.unwrap_or(7).clamp(1, 365) as i64;
+    let points = query.points.unwrap_or(12).clamp(1, 104) as i64;
+    let top_authors = query.top_authors.unwrap_or(10).clamp(1, 100);
+
+    let point_ends: Vec<chrono::NaiveDate> = (0..points)
+        .rev()
+        .map(|i| end - chrono::Duration::days(i * step))
+        .collect();
+    // Windows cover whole days: (end - days, end], as UTC instants
+    let day_after = |date: chrono::NaiveDate| {
+        (date + chrono::Duration::days(1))
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+    };
+    let longest = *windows.iter().max().unwrap_or(&1) as i64;
+    let since = day_after(point_ends[0]) - chrono::Duration::days(longest);
+
+    let rows: Vec<ChurnRow> = sqlx::query_as(
+        r#"
+        SELECT authorEmail, authorName, commitDate, filesChanged
+        FROM Commit
+        WHERE repositoryId = ? AND commitDate >= ? AND commitDate < ?
+          AND filesChanged IS NOT NULL
+        ORDER BY commitDate
+        "#,
+    )
+    .bind(&repository_id)
+    .bind(since)
+    .bind(day_after(end))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut result: Vec<AuthorChurnWindow> = windows
+        .iter()
+        .map(|&days| AuthorChurnWindow {
+            days,
+            points: point_ends
+                .iter()
+                .map(|&point_end| {
+                    let until = day_after(point_end);
+                    let from = until - chrono::Duration::days(days as i64);
+                    churn_point(
+                        point_end,
+                        rows.iter()
+                            .filter(|row| row.commit_date >= from && row.commit_date < until),
+                        top_authors,
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+    anonymize::authors(
+        &Anonymizer::for_request(&anonymize),
+        result
+            .iter_mut()
+            .flat_map(|window| &mut window.points)
+            .flat_map(|point| &mut point.authors),
+        |share| (&mut share.author_email, &mut share.author_name),
+    );
+
+    Ok(Json(result))
+}
+
+fn churn_point<'a>(
+    end: chrono::NaiveDate,
+    rows: impl Iterator<Item = &'a ChurnRow>,
+    top_authors: usize,
+) -> AuthorChurnPoint {
+    // Rows come oldest first, so the name kept is the author's most recent one
+    let mut by_author: HashMap<&str, (&str, u64)> = HashMap::new();
+    for row in rows {
+        let entry = by_author
+            .entry(row.author_email.as_str())
+            .or_insert((row.author_name.as_str(), 0));
+        entry.0 = row.author_name.as_str();
+        entry.1 += row.files_changed.max(0) as u64;
+    }
+    let total_churn: u64 = by_author.values().map(|(_, churn)| churn).sum();
+
+    let mut authors: Vec<AuthorChurnShare> = by_author
+        .into_iter()
+        .filter(|(_, (_, churn))| *churn > 0)
+        .map(|(email, (name, churn))| AuthorChurnShare {
+            author_email: email.to_string(),
+            author_name: name.to_string(),
+            churn,
+            share: churn as f64 / total_churn as f64,
+        })
+        .collect();
+    authors.sort_by(|a, b| {
+        b.churn
+            .cmp(&a.churn)
+            .then_with(|| a.author_email.cmp(&b.author_email))
+    });
+    let other_churn = authors.iter().skip(top_authors).map(|a| a.churn).sum();
+    authors.truncate(top_authors);
+
+    AuthorChurnPoint {
+        end,
+        total_churn,
+        authors,
+        other_churn,
+    }
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorCadenceQuery {
+    /// Start of the window (YYYY-MM-DD); default: the first commit in the repository
+    pub start_date: Option<chrono::NaiveDate>,
+    /// End of the window, inclusive (YYYY-MM-DD)
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorCadence {
+    pub author_email: String,
+    pub author_name: String,
+    pub commits: u64,
+    pub first_commit_at: chrono::DateTime<chrono::Utc>,
+    pub last_commit_at: chrono::DateTime<chrono::Utc>,
+    /// Days from the start of the window to the author's first commit (ramp-up)
+    pub days_to_first_commit: f64,
+    /// Days between the first and last commit
+    pub active_span_days: f64,
+    /// Distinct UTC days with at least one commit
+    pub active_days: u64,
+    /// Mean hours between consecutive commits; null with a single commit
+    pub mean_interval_hours: Option<f64>,
+    /// Median hours between consecutive commits, less skewed by long breaks
+    pub median_interval_hours: Option<f64>,
+}
+
+fn days_between(from: chrono::DateTime<chrono::Utc>, to: chrono::DateTime<chrono::Utc>) -> f64 {
+    (to - from).num_seconds() as f64 / 86_400.0
+}
+
+/// Build one author's cadence from their commit dates, oldest first
+fn author_cadence(
+    author_email: String,
+    author_name: String,
+    dates: &[chrono::DateTime<chrono::Utc>],
+    window_start: chrono::DateTime<chrono::Utc>,
+) -> Option<AuthorCadence> {
+    let (&first, &last) = (dates.first()?, dates.last()?);
+    let mut intervals: Vec<f64> = dates
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_seconds() as f64 / 3_600.0)
+        .collect();
+    let mean_interval_hours =
+        (!intervals.is_empty()).then(|| intervals.iter().sum::<f64>() / intervals.len() as f64);
+    intervals.sort_by(f64::total_cmp);
+    let median_interval_hours = match intervals.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(intervals[n / 2]),
+        n => Some((intervals[n / 2 - 1] + intervals[n / 2]) / 2.0),
+    };
+    let mut days: Vec<chrono::NaiveDate> = dates.iter().map(|d| d.date_naive()).collect();
+    days.dedup();
+
+    Some(AuthorCadence {
+        author_email,
+        author_name,
+        commits: dates.len() as u64,
+        first_commit_at: first,
+        last_commit_at: last,
+        days_to_first_commit: days_between(window_start, first).max(0.0),
+        active_span_days: days_between(first, last),
+        active_days: days.len() as u64,
+        mean_interval_hours,
+        median_interval_hours,
+    })
+}
+
+/// Each author's activity span and rhythm: first/last commit, ramp-up from the start
+/// of the window and the typical time between their commits. Earliest starters first.
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/author-cadence",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), AuthorCadenceQuery, AnonymizeQuery),
+    responses((status = 200, body = [AuthorCadence]))
+)]
+pub async fn author_cadence_stats(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<AuthorCadenceQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<Vec<AuthorCadence>>, (StatusCode, String)> {
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT authorEmail, authorName, commitDate FROM Commit WHERE repositoryId = ",
+    );
+    builder.push_bind(&repository_id);
+    if let Some(start) = query.start_date {
+        builder
+            .push(" AND commitDate >= ")
+            .push_bind(start.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(end) = query.end_date {
+        builder
+            .push(" AND commitDate <= ")
+            .push_bind(end.and_hms_opt(23, 59, 59).unwrap());
+    }
+    builder.push(" ORDER BY authorEmail, commitDate");
+
+    let rows: Vec<(String, String, chrono::DateTime<chrono::Utc>)> = builder
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let window_start = match query.start_date {
+        Some(start) => start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        None => match rows.iter().map(|(_, _, date)| *date).min() {
+            Some(earliest) => earliest,
+            None => return Ok(Json(Vec::new())),
+        },
+    };
+
+    // Rows are grouped by author; the name kept is the one on their latest commit
+    let mut authors = Vec::new();
+    let mut start = 0;
+    while start < rows.len() {
+        let email = &rows[start].0;
+        let end = start + rows[start..].iter().take_while(|row| &row.0 == email).count();
+        let dates: Vec<_> = rows[start..end].iter().map(|row| row.2).collect();
+        authors.extend(author_cadence(
+            email.clone(),
+            rows[end - 1].1.clone(),
+            &dates,
+            window_start,
+        ));
+        start = end;
+    }
+    authors.sort_by(|a, b| {
+        a.first_commit_at
+            .cmp(&b.first_commit_at)
+            .then_with(|| a.author_email.cmp(&b.author_email))
+    });
+    anonymize::authors(&Anonymizer::for_request(&anonymize), &mut authors, |a| {
+        (&mut a.author_email, &mut a.author_name)
+    });
+
+    Ok(Json(authors))
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateComplianceQuery {
+    /// Only commits on or after this day (YYYY-MM-DD)
+    pub start_date: Option<chrono::NaiveDate>,
+    /// Only commits on or before this day (YYYY-MM-DD)
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorTemplateCompliance {
+    pub author_email: String,
+    pub author_name: String,
+    /// Commits checked against the template
+    pub checked: i64,
+    pub violations: i64,
+    /// Share of checked commits matching the template (0-1)
+    pub compliance_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateCompliance {
+    pub checked: i64,
+    pub violations: i64,
+    /// Share of checked commits matching the template (0-1); null when none were checked
+    pub compliance_rate: Option<f64>,
+    /// Merge commits and commits analyzed without a template
+    pub unchecked: i64,
+    /// Authors with checked commits, most violations first
+    pub authors: Vec<AuthorTemplateCompliance>,
+}
+
+fn compliance_rate(checked: i64, violations: i64) -> Option<f64> {
+    (checked > 0).then(|| (checked - violations) as f64 / checked as f64)
+}
+
+/// How well commit messages follow the configured template (`COMMIT_TEMPLATE_REGEX`
+/// or the job's `commitTemplate`), overall and per author
+#[utoipa::path(
+    get,
+    path = "/repositories/{id}/stats/template-compliance",
+    tag = "stats",
+    params(("id" = String, Path, description = "Repository id"), TemplateComplianceQuery, AnonymizeQuery),
+    responses((status = 200, body = TemplateCompliance))
+)]
+pub async fn template_compliance(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Query(query): Query<TemplateComplianceQuery>,
+    Query(anonymize): Query<AnonymizeQuery>,
+) -> Result<Json<TemplateCompliance>, (StatusCode, String)> {
+    let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "SELECT authorEmail, MAX(authorName), \
+         CAST(SUM(templateViolation IS NOT NULL) AS SIGNED), \
+         CAST(COALESCE(SUM(templateViolation = TRUE), 0) AS SIGNED), \
+         CAST(SUM(templateViolation IS NULL) AS SIGNED) \
+         FROM Commit WHERE repositoryId = ",
+    );
+    builder.push_bind(&repository_id);
+    if let Some(start) = query.start_date {
+        builder
+            .push(" AND commitDate >= ")
+            .push_bind(start.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Some(end) = query.end_date {
+        builder
+            .push(" AND commitDate <= ")
+            .push_bind(end.and_hms_opt(23, 59, 59).unwrap());
+    }
+    builder.push(" GROUP BY authorEmail");
+
+    let rows: Vec<(String, String, i64, i64, i64)> = builder
+        .build_query_as()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut compliance = TemplateCompliance {
+        checked: 0,
+        violations: 0,
+        compliance_rate: None,
+        unchecked: 0,
+        authors: Vec::new(),
+    };
+    for (author_email, author_name, checked, violations, unchecked) in rows {
+        compliance.checked += checked;
+        compliance.violations += violations;
+        compliance.unchecked += unchecked;
+        if checked > 0 {
+            compliance.authors.push(AuthorTemplateCompliance {
+                author_email,
+                author_name,
+                checked,
+                violations,
+                compliance_rate: compliance_rate(checked, violations),
+            });
+        }
+    }
+    compliance.compliance_rate = compliance_rate(compliance.checked, compliance.violations);
+    compliance.authors.sort_by(|a, b| {
+        b.violations
+            .cmp(&a.violations)
+            .then_with(|| a.author_email.cmp(&b.author_email))
+    });
+    anonymize::authors(
+        &Anonymizer::for_request(&anonymize),
+        &mut compliance.authors,
+        |a| (&mut a.author_email, &mut a.author_name),
+    );
+
+    Ok(Json(compliance))
+}
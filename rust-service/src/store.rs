@@ -0,0 +1,446 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::coauthors::CoAuthor;
+use crate::git;
+use crate::jira;
+use crate::known_shas::KnownShas;
+use crate::metadata;
+use crate::models::{ChangeStatus, FileDiff, ParsedCommit};
+use crate::sanitize::{sanitize, sanitize_with_max, Field};
+use crate::{env_flag, AppState};
+
+/// Longest commit message (in characters) stored in the `message` column
+const MESSAGE_MAX_CHARS: usize = 65000;
+/// Rows per multi-row CommitFile, CommitJiraKey and CommitCoAuthor insert
+const ROW_INSERT_CHUNK: usize = 500;
+/// Diff text per multi-row CommitDiff insert, well under MySQL's default max_allowed_packet
+const DIFF_INSERT_MAX_BYTES: usize = 8 * 1024 * 1024;
+/// Largest `COMMIT_BATCH_SIZE` allowed
+const MAX_COMMIT_BATCH: usize = 1500;
+
+/// Upload a message longer than the column limit to S3 when `LARGE_MESSAGE_POLICY=s3`.
+/// Returns the object key, or None when the message fits or is simply truncated.
+async fn offload_large_message(
+    state: &AppState,
+    repository_id: &str,
+    sha: &str,
+    message: &str,
+) -> Result<Option<String>> {
+    if message.chars().count() <= MESSAGE_MAX_CHARS {
+        return Ok(None);
+    }
+
+    let policy = std::env::var("LARGE_MESSAGE_POLICY").unwrap_or_else(|_| "truncate".into());
+    if !policy.eq_ignore_ascii_case("s3") {
+        return Ok(None);
+    }
+    let Some(s3) = &state.s3 else {
+        tracing::warn!("LARGE_MESSAGE_POLICY=s3 but S3 is not configured; truncating {}", sha);
+        return Ok(None);
+    };
+
+    let prefix = std::env::var("S3_MESSAGE_PREFIX").unwrap_or_else(|_| "commit-messages".into());
+    let key = format!("{}/{}/{}.txt", prefix.trim_end_matches('/'), repository_id, sha);
+    s3.put_object(&key, message.as_bytes().to_vec(), "text/plain; charset=utf-8")
+        .await?;
+    tracing::info!("Stored {}-byte message of {} at {}", message.len(), sha, key);
+
+    Ok(Some(key))
+}
+
+/// Per-job values shared by every commit insert
+pub struct CommitContext {
+    pub repository_id: String,
+    pub job_id: String,
+    pub jira_base_url: Option<String>,
+    pub known_shas: std::sync::Arc<KnownShas>,
+}
+
+/// Commits per multi-row insert transaction (`COMMIT_BATCH_SIZE`, default 500). Each
+/// row binds 33 values, so batches are capped below MySQL's 65535 placeholders.
+pub fn commit_batch_size() -> usize {
+    std::env::var("COMMIT_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(500)
+        .clamp(1, MAX_COMMIT_BATCH)
+}
+
+/// Insert one parsed commit and its related rows.
+/// Returns false when the commit was already stored.
+pub async fn store_commit(
+    state: &AppState,
+    context: &CommitContext,
+    commit: &ParsedCommit,
+) -> Result<bool> {
+    Ok(store_batch(state, context, std::slice::from_ref(commit)).await? == 1)
+}
+
+/// Insert parsed commits with their JIRA keys, co-authors and files in one transaction,
+/// using multi-row inserts. Commits that are already stored are skipped, including ones
+/// another job stored in the meantime. Returns how many commits were inserted.
+pub async fn store_batch(
+    state: &AppState,
+    context: &CommitContext,
+    commits: &[ParsedCommit],
+) -> Result<usize> {
+    // Only possible hits in the known set (bloom filter mode) need confirming against the DB
+    let maybe_known: Vec<&str> = commits
+        .iter()
+        .filter(|commit| context.known_shas.might_contain(&commit.sha))
+        .map(|commit| commit.sha.as_str())
+        .collect();
+    let existing: HashSet<String> = if maybe_known.is_empty() {
+        HashSet::new()
+    } else {
+        let mut query =
+            sqlx::QueryBuilder::<sqlx::MySql>::new("SELECT sha FROM Commit WHERE repositoryId = ");
+        query.push_bind(&context.repository_id).push(" AND sha IN (");
+        let mut shas = query.separated(", ");
+        for sha in &maybe_known {
+            shas.push_bind(*sha);
+        }
+        query.push(")");
+        query
+            .build_query_scalar::<String>()
+            .fetch_all(&state.db)
+            .await?
+            .into_iter()
+            .collect()
+    };
+    if !existing.is_empty() {
+        tracing::debug!("{} commits of the batch already exist, skipping", existing.len());
+    }
+
+    let mut prepared = Vec::with_capacity(commits.len());
+    for commit in commits.iter().filter(|c| !existing.contains(&c.sha)) {
+        // Extract JIRA tickets from commit message; the first one is the commit's primary key
+        let jira_keys = jira::extract_jira_keys(&commit.message);
+        let jira_url = jira_keys
+            .first()
+            .zip(context.jira_base_url.as_deref())
+            .map(|(key, base)| jira::browse_url(base, key));
+        // Giant messages (generated changelogs) can go to S3, keeping a truncated copy in MySQL
+        let message_key =
+            offload_large_message(state, &context.repository_id, &commit.sha, &commit.message)
+                .await?;
+        prepared.push(PreparedCommit {
+            commit,
+            jira_keys,
+            jira_url,
+            message_key,
+        });
+    }
+    if prepared.is_empty() {
+        return Ok(0);
+    }
+    tracing::info!("Storing {} commits", prepared.len());
+
+    // One transaction per batch, so a failed attempt leaves nothing behind to retry into
+    let mut tx = state.db.begin().await?;
+
+    let mut insert = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        r#"
+        INSERT INTO Commit (
+            id, repositoryId, analysisJobId, sha, authorName, authorEmail, authorEmailOriginal,
+            commitDate, commitDateOffset,
+            message, messageKey, messageTitle, noMessage, filesChanged, codeFilesChanged, insertions, deletions, changedPaths,
+            patchId, diffTimeout, largestFilePath, largestFileSize, isSigned, signatureVerified, signatureIdentity, signatureMismatch,
+            signatureKeyId, signatureFingerprint, templateViolation,
+            parentShas, metadata, jiraKey, jiraUrl, summaryStatus, createdAt, updatedAt
+        ) "#,
+    );
+    insert.push_values(&prepared, |mut row, prepared| {
+        let commit = prepared.commit;
+        row.push_bind(&commit.id)
+            .push_bind(&context.repository_id)
+            .push_bind(&context.job_id)
+            .push_bind(&commit.sha)
+            .push_bind(sanitize(Field::AuthorName, &commit.author_name))
+            .push_bind(sanitize(Field::AuthorEmail, &commit.author_email))
+            .push_bind(sanitize(Field::AuthorEmail, &commit.author_email_original))
+            .push_bind(commit.commit_date)
+            .push_bind(commit.commit_date_offset)
+            .push_bind(sanitize_with_max(Field::Message, &commit.message, MESSAGE_MAX_CHARS))
+            .push_bind(&prepared.message_key)
+            .push_bind(sanitize(Field::MessageTitle, &commit.message_title))
+            .push_bind(commit.no_message)
+            .push_bind(commit.files_changed.map(|n| n as i32))
+            .push_bind(commit.code_files_changed.map(|n| n as i32))
+            .push_bind(commit.insertions.map(|n| n as i32))
+            .push_bind(commit.deletions.map(|n| n as i32))
+            .push_bind(commit.changed_paths.as_deref().map(|p| sanitize(Field::ChangedPaths, p)))
+            .push_bind(&commit.patch_id)
+            .push_bind(commit.diff_timeout)
+            .push_bind(commit.largest_file_path.as_deref().map(|p| sanitize(Field::Path, p)))
+            .push_bind(commit.largest_file_size)
+            .push_bind(commit.is_signed)
+            .push_bind(commit.signature_verified)
+            .push_bind(
+                commit
+                    .signature_identity
+                    .as_deref()
+                    .map(|s| sanitize(Field::SignatureIdentity, s)),
+            )
+            .push_bind(commit.signature_mismatch)
+            .push_bind(&commit.signature_key_id)
+            .push_bind(&commit.signature_fingerprint)
+            .push_bind(commit.template_violation)
+            .push_bind(commit.parent_shas.join(" "))
+            .push_bind((!commit.metadata.is_empty()).then_some(sqlx::types::Json(&commit.metadata)))
+            .push_bind(prepared.jira_keys.first())
+            .push_bind(&prepared.jira_url)
+            .push("'PENDING'")
+            .push("NOW()")
+            .push("NOW()");
+    });
+    // A commit stored by a concurrent job since the check above is left as it is
+    insert.push(" ON DUPLICATE KEY UPDATE id = id");
+    insert.build().execute(&mut *tx).await?;
+
+    // Only commits this job inserted get child rows; the others already have theirs
+    let mut query =
+        sqlx::QueryBuilder::<sqlx::MySql>::new("SELECT id FROM Commit WHERE analysisJobId = ");
+    query.push_bind(&context.job_id).push(" AND id IN (");
+    let mut ids = query.separated(", ");
+    for prepared in &prepared {
+        ids.push_bind(&prepared.commit.id);
+    }
+    query.push(")");
+    let inserted: HashSet<String> = query
+        .build_query_scalar::<String>()
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+    prepared.retain(|prepared| inserted.contains(&prepared.commit.id));
+
+    let jira_rows: Vec<(&str, &str)> = prepared
+        .iter()
+        .flat_map(|p| p.jira_keys.iter().map(|key| (p.commit.id.as_str(), key.as_str())))
+        .collect();
+    for chunk in jira_rows.chunks(ROW_INSERT_CHUNK) {
+        let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "INSERT IGNORE INTO CommitJiraKey (id, commitId, jiraKey, project) ",
+        );
+        builder.push_values(chunk, |mut row, (commit_id, key)| {
+            row.push_bind(uuid::Uuid::new_v4().to_string())
+                .push_bind(*commit_id)
+                .push_bind(*key)
+                .push_bind(jira::jira_project(key));
+        });
+        builder.build().execute(&mut *tx).await?;
+    }
+
+    let co_author_rows: Vec<(&str, &CoAuthor)> = prepared
+        .iter()
+        .flat_map(|p| p.commit.co_authors.iter().map(|co| (p.commit.id.as_str(), co)))
+        .collect();
+    for chunk in co_author_rows.chunks(ROW_INSERT_CHUNK) {
+        let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "INSERT IGNORE INTO CommitCoAuthor (id, commitId, name, email) ",
+        );
+        builder.push_values(chunk, |mut row, (commit_id, co_author)| {
+            row.push_bind(uuid::Uuid::new_v4().to_string())
+                .push_bind(*commit_id)
+                .push_bind(sanitize(Field::AuthorName, &co_author.name))
+                .push_bind(sanitize(Field::AuthorEmail, &co_author.email));
+        });
+        builder.build().execute(&mut *tx).await?;
+    }
+
+    let mut file_rows = Vec::new();
+    for prepared in &prepared {
+        let commit = prepared.commit;
+        let excluded_paths = commit.metadata.excluded_paths();
+        let excluded = |path: &str| excluded_paths.iter().any(|p| p == path);
+        if !commit.file_changes.is_empty() {
+            file_rows.extend(commit.file_changes.iter().map(|change| FileRow {
+                commit,
+                path: &change.path,
+                status: Some(change.status),
+                old_path: change.old_path.as_deref(),
+                excluded: excluded(&change.path),
+            }));
+        } else if let Some(changed_paths) = &commit.changed_paths {
+            file_rows.extend(changed_paths.lines().filter(|p| !p.is_empty()).map(|path| {
+                FileRow {
+                    commit,
+                    path,
+                    status: None,
+                    old_path: None,
+                    excluded: excluded(path),
+                }
+            }));
+        }
+    }
+    let mut skipped: HashMap<&str, (&ParsedCommit, Vec<&str>)> = HashMap::new();
+    for chunk in file_rows.chunks(ROW_INSERT_CHUNK) {
+        for row in insert_files(&mut tx, chunk).await? {
+            skipped
+                .entry(row.commit.id.as_str())
+                .or_insert_with(|| (row.commit, Vec::new()))
+                .1
+                .push(row.path);
+        }
+    }
+    let diff_rows: Vec<(&str, &FileDiff)> = prepared
+        .iter()
+        .flat_map(|p| p.commit.file_diffs.iter().map(|diff| (p.commit.id.as_str(), diff)))
+        .collect();
+    for chunk in diff_chunks(&diff_rows) {
+        let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "INSERT INTO CommitDiff (id, commitId, path, patch, bytes, truncated) ",
+        );
+        builder.push_values(chunk, |mut row, (commit_id, diff)| {
+            row.push_bind(uuid::Uuid::new_v4().to_string())
+                .push_bind(*commit_id)
+                .push_bind(sanitize(Field::Path, &diff.path))
+                .push_bind(sanitize(Field::Patch, &diff.patch))
+                .push_bind(diff.bytes.min(i32::MAX as usize) as i32)
+                .push_bind(diff.truncated);
+        });
+        builder.build().execute(&mut *tx).await?;
+    }
+
+    for (commit, paths) in skipped.into_values() {
+        tracing::warn!(
+            "Skipped {} file rows of commit {} the database rejected",
+            paths.len(),
+            git::short_sha(&commit.sha)
+        );
+        sqlx::query(
+            "UPDATE Commit SET metadata = JSON_SET(COALESCE(metadata, JSON_OBJECT()), ?, CAST(? AS JSON)) WHERE id = ?",
+        )
+        .bind(format!("$.{}", metadata::SKIPPED_FILES))
+        .bind(serde_json::to_string(&paths)?)
+        .bind(&commit.id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(prepared.len())
+}
+
+/// Split diff rows into inserts of at most `ROW_INSERT_CHUNK` rows and about
+/// `DIFF_INSERT_MAX_BYTES` of text (a single larger diff gets a statement of its own)
+fn diff_chunks<'r, 'c>(
+    rows: &'r [(&'c str, &'c FileDiff)],
+) -> Vec<&'r [(&'c str, &'c FileDiff)]> {
+    let mut chunks = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (i, (_, diff)) in rows.iter().enumerate() {
+        let full =
+            i - start >= ROW_INSERT_CHUNK || bytes + diff.patch.len() > DIFF_INSERT_MAX_BYTES;
+        if full && i > start {
+            chunks.push(&rows[start..i]);
+            (start, bytes) = (i, 0);
+        }
+        bytes += diff.patch.len();
+    }
+    if start < rows.len() {
+        chunks.push(&rows[start..]);
+    }
+    chunks
+}
+
+/// A commit about to be inserted, with the values derived from it beforehand
+struct PreparedCommit<'c> {
+    commit: &'c ParsedCommit,
+    jira_keys: Vec<String>,
+    jira_url: Option<String>,
+    /// S3 key of a message too long for the column
+    message_key: Option<String>,
+}
+
+/// One CommitFile row to insert
+#[derive(Clone, Copy)]
+struct FileRow<'c> {
+    commit: &'c ParsedCommit,
+    path: &'c str,
+    /// None for commits parsed without statuses
+    status: Option<ChangeStatus>,
+    /// Source of a rename or copy
+    old_path: Option<&'c str>,
+    excluded: bool,
+}
+
+/// Insert CommitFile rows in one statement. When the database rejects the batch over
+/// its data (a constraint or invalid value) and `ISOLATE_BATCH_FAILURES` is on (the
+/// default), the rows are retried one at a time so a single bad row doesn't cost the
+/// others; the rows that still fail are returned.
+/// MySQL rolls back only the failed statement, so the transaction stays usable.
+async fn insert_files<'c>(
+    conn: &mut sqlx::MySqlConnection,
+    rows: &[FileRow<'c>],
+) -> Result<Vec<FileRow<'c>>> {
+    let insert = |rows: &[FileRow<'c>]| {
+        let mut builder = sqlx::QueryBuilder::<sqlx::MySql>::new(
+            "INSERT INTO CommitFile (id, commitId, path, status, oldPath, excluded) ",
+        );
+        builder.push_values(rows, |mut row, file| {
+            row.push_bind(uuid::Uuid::new_v4().to_string())
+                .push_bind(file.commit.id.clone())
+                .push_bind(sanitize(Field::Path, file.path))
+                .push_bind(file.status.map(ChangeStatus::as_str))
+                .push_bind(file.old_path.map(|p| sanitize(Field::Path, p)))
+                .push_bind(file.excluded);
+        });
+        builder
+    };
+
+    match insert(rows).build().execute(&mut *conn).await {
+        Ok(_) => return Ok(Vec::new()),
+        Err(sqlx::Error::Database(e)) if env_flag("ISOLATE_BATCH_FAILURES", true) => {
+            tracing::warn!(
+                "Batch of {} file rows failed ({}); retrying row by row",
+                rows.len(),
+                e
+            );
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut skipped = Vec::new();
+    for row in rows {
+        match insert(std::slice::from_ref(row)).build().execute(&mut *conn).await {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) => {
+                tracing::warn!("Skipping file row {:?}: {}", row.path, e);
+                skipped.push(*row);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(skipped)
+}
+
+/// Record a commit that could not be stored after `attempts` tries, so the job can
+/// carry on without it
+pub async fn dead_letter(
+    state: &AppState,
+    context: &CommitContext,
+    commit: &ParsedCommit,
+    attempts: u32,
+    error: &anyhow::Error,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO DeadLetterCommit (id, analysisJobId, repositoryId, sha, attempts, error, createdAt)
+        VALUES (?, ?, ?, ?, ?, ?, NOW())
+        "#,
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(&context.job_id)
+    .bind(&context.repository_id)
+    .bind(&commit.sha)
+    .bind(attempts)
+    .bind(format!("{:#}", error))
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
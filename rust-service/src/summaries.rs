@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlJson;
+
+use crate::sanitize::{sanitize, Field};
+use crate::AppState;
+
+/// Context handed to summarizers with each claimed commit.
+/// Set per repository and/or per job; job values win field by field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryContext {
+    /// What the project is, for the summarizer's framing
+    pub project_context: Option<String>,
+    /// Preferred output language (e.g. "en", "th")
+    pub language: Option<String>,
+    /// Any additional prompt hints
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl SummaryContext {
+    /// Overlay `other` on top of self
+    fn merged_with(mut self, other: SummaryContext) -> Self {
+        if other.project_context.is_some() {
+            self.project_context = other.project_context;
+        }
+        if other.language.is_some() {
+            self.language = other.language;
+        }
+        self.extra.extend(other.extra);
+        self
+    }
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// Set the summary context stored on a repository
+#[utoipa::path(
+    put,
+    path = "/repositories/{id}/summary-context",
+    tag = "summaries",
+    params(("id" = String, Path, description = "Repository id")),
+    request_body = SummaryContext,
+    responses(
+        (status = 200, body = SummaryContext),
+        (status = 404, description = "Repository not found", body = String)
+    )
+)]
+pub async fn set_repository_context(
+    State(state): State<AppState>,
+    Path(repository_id): Path<String>,
+    Json(context): Json<SummaryContext>,
+) -> Result<Json<SummaryContext>, (StatusCode, String)> {
+    let result = sqlx::query("UPDATE Repository SET summaryContext = ? WHERE id = ?")
+        .bind(SqlJson(&context))
+        .bind(&repository_id)
+        .execute(&state.db)
+        .await
+        .map_err(db_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Repository not found".to_string()));
+    }
+    Ok(Json(context))
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimRequest {
+    /// Maximum commits to claim (default 10, max 100)
+    pub limit: Option<u32>,
+    pub repository_id: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+#[sqlx(rename_all = "camelCase")]
+struct ClaimedRow {
+    id: String,
+    repository_id: String,
+    sha: String,
+    message_title: String,
+    message: String,
+    changed_paths: Option<String>,
+    files_changed: Option<i32>,
+    repository_context: Option<SqlJson<SummaryContext>>,
+    job_context: Option<SqlJson<SummaryContext>>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimedCommit {
+    pub commit_id: String,
+    pub repository_id: String,
+    pub sha: String,
+    pub message_title: String,
+    pub message: String,
+    pub changed_paths: Option<String>,
+    pub files_changed: Option<i32>,
+    pub context: SummaryContext,
+}
+
+/// Atomically claim PENDING commits for summarization (moves them to PROCESSING)
+#[utoipa::path(
+    post,
+    path = "/summaries/claim",
+    tag = "summaries",
+    request_body = ClaimRequest,
+    responses((status = 200, body = [ClaimedCommit]))
+)]
+pub async fn claim(
+    State(state): State<AppState>,
+    Json(request): Json<ClaimRequest>,
+) -> Result<Json<Vec<ClaimedCommit>>, (StatusCode, String)> {
+    let limit = request.limit.unwrap_or(10).clamp(1, 100);
+    claim_commits(&state.db, limit, request.repository_id.as_deref())
+        .await
+        .map(Json)
+        .map_err(db_error)
+}
+
+/// How long a claim lasts (`SUMMARIZER_CLAIM_TTL_SECS`, default 15 minutes); commits still
+/// PROCESSING after that, e.g. because their summarizer restarted, can be claimed again
+fn claim_ttl_secs() -> u64 {
+    std::env::var("SUMMARIZER_CLAIM_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(15 * 60)
+}
+
+/// Move up to `limit` PENDING commits (newest first), or commits whose claim expired,
+/// to PROCESSING and return them with their summary context. Claims are atomic, so
+/// several summarizers can share the queue.
+pub async fn claim_commits(
+    db: &sqlx::MySqlPool,
+    limit: u32,
+    repository_id: Option<&str>,
+) -> Result<Vec<ClaimedCommit>, sqlx::Error> {
+    let claim_id = uuid::Uuid::new_v4().to_string();
+
+    let mut update = sqlx::QueryBuilder::<sqlx::MySql>::new(
+        "UPDATE Commit SET summaryStatus = 'PROCESSING', summaryClaimId = ",
+    );
+    update
+        .push_bind(&claim_id)
+        .push(", updatedAt = NOW() WHERE (summaryStatus = 'PENDING'")
+        .push(" OR (summaryStatus = 'PROCESSING' AND updatedAt < NOW() - INTERVAL ")
+        .push_bind(claim_ttl_secs())
+        .push(" SECOND))");
+    if let Some(repository_id) = repository_id {
+        update.push(" AND repositoryId = ").push_bind(repository_id);
+    }
+    update
+        .push(" ORDER BY commitDate DESC LIMIT ")
+        .push_bind(limit);
+    update.build().execute(db).await?;
+
+    let rows: Vec<ClaimedRow> = sqlx::query_as(
+        r#"
+        SELECT c.id, c.repositoryId, c.sha, c.messageTitle, c.message, c.changedPaths,
+               c.filesChanged, r.summaryContext AS repositoryContext,
+               j.summaryContext AS jobContext
+        FROM Commit c
+        JOIN Repository r ON r.id = c.repositoryId
+        LEFT JOIN AnalysisJob j ON j.id = c.analysisJobId
+        WHERE c.summaryClaimId = ?
+        ORDER BY c.commitDate DESC
+        "#,
+    )
+    .bind(&claim_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let context = row
+                .repository_context
+                .map(|c| c.0)
+                .unwrap_or_default()
+                .merged_with(row.job_context.map(|c| c.0).unwrap_or_default());
+            ClaimedCommit {
+                commit_id: row.id,
+                repository_id: row.repository_id,
+                sha: row.sha,
+                message_title: row.message_title,
+                message: row.message,
+                changed_paths: row.changed_paths,
+                files_changed: row.files_changed,
+                context,
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryResult {
+    /// Generated summary; marks the commit COMPLETED
+    pub summary: Option<String>,
+    /// Failure reason; marks the commit FAILED when no summary is given
+    pub error: Option<String>,
+    /// Model that wrote the summary
+    pub model: Option<String>,
+    /// Prompt tokens billed for the summary
+    pub input_tokens: Option<u32>,
+    /// Completion tokens billed for the summary
+    pub output_tokens: Option<u32>,
+}
+
+/// Report the outcome for a claimed commit
+#[utoipa::path(
+    post,
+    path = "/summaries/{commitId}",
+    tag = "summaries",
+    params(("commitId" = String, Path, description = "Commit id")),
+    request_body = SummaryResult,
+    responses(
+        (status = 204, description = "Outcome recorded"),
+        (status = 409, description = "Commit is not claimed", body = String)
+    )
+)]
+pub async fn complete(
+    State(state): State<AppState>,
+    Path(commit_id): Path<String>,
+    Json(result): Json<SummaryResult>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let recorded = record_result(&state.db, &commit_id, &result)
+        .await
+        .map_err(db_error)?;
+    if !recorded {
+        return Err((
+            StatusCode::CONFLICT,
+            "Commit is not claimed for summarization".to_string(),
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Store the outcome for a claimed commit (COMPLETED with a summary, FAILED without).
+/// Returns false when the commit is not claimed.
+pub async fn record_result(
+    db: &sqlx::MySqlPool,
+    commit_id: &str,
+    result: &SummaryResult,
+) -> Result<bool, sqlx::Error> {
+    let query = match &result.summary {
+        Some(summary) => sqlx::query(
+            r#"
+            UPDATE Commit SET summary = ?, summaryStatus = 'COMPLETED', summaryClaimId = NULL,
+                summaryModel = ?, summaryInputTokens = ?, summaryOutputTokens = ?,
+                updatedAt = NOW()
+            WHERE id = ? AND summaryStatus = 'PROCESSING'
+            "#,
+        )
+        .bind(sanitize(Field::Summary, summary))
+        .bind(result.model.as_deref().map(|m| sanitize(Field::MessageTitle, m)))
+        .bind(result.input_tokens)
+        .bind(result.output_tokens),
+        None => {
+            if let Some(error) = &result.error {
+                tracing::warn!("Summary failed for commit {}: {}", commit_id, error);
+            }
+            sqlx::query(
+                r#"
+                UPDATE Commit SET summaryStatus = 'FAILED', summaryClaimId = NULL, updatedAt = NOW()
+                WHERE id = ? AND summaryStatus = 'PROCESSING'
+                "#,
+            )
+        }
+    };
+
+    let updated = query.bind(commit_id).execute(db).await?;
+    Ok(updated.rows_affected() > 0)
+}
@@ -0,0 +1,207 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+
+use crate::commits::StoredFileDiff;
+use crate::llm::{self, LlmProvider};
+use crate::summaries::{self, ClaimedCommit, SummaryResult};
+use crate::AppState;
+
+/// Changed paths listed in a prompt; the rest are counted
+const MAX_PROMPT_PATHS: usize = 100;
+
+const SYSTEM_PROMPT: &str = "You summarize git commits for a changelog. Reply with one or two \
+plain sentences describing what the change does and why, without restating the commit hash \
+or listing every file.";
+
+/// Settings of the built-in summarization worker
+#[derive(Clone)]
+pub struct SummarizerConfig {
+    /// Commits claimed per poll
+    batch_size: u32,
+    /// Completion requests in flight at once
+    concurrency: usize,
+    /// Wait between polls when no commit is pending
+    poll_interval: Duration,
+    /// Diff text included per commit, in bytes
+    max_diff_bytes: usize,
+    /// Retries of a failed completion request (`SUMMARIZER_RETRIES`)
+    retries: u32,
+    /// First retry delay, doubled per retry (`SUMMARIZER_RETRY_BACKOFF_MS`)
+    retry_backoff: Duration,
+}
+
+impl SummarizerConfig {
+    /// Worker settings, or None unless `SUMMARIZER_ENABLED=true`
+    pub fn from_env() -> Option<Self> {
+        if !crate::env_flag("SUMMARIZER_ENABLED", false) {
+            return None;
+        }
+        let number = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Some(Self {
+            batch_size: number("SUMMARIZER_BATCH_SIZE", 10).clamp(1, 100) as u32,
+            concurrency: number("SUMMARIZER_CONCURRENCY", 2).max(1) as usize,
+            poll_interval: Duration::from_secs(number("SUMMARIZER_POLL_SECS", 30).max(1)),
+            max_diff_bytes: number("SUMMARIZER_MAX_DIFF_BYTES", 16 * 1024) as usize,
+            retries: number("SUMMARIZER_RETRIES", 3) as u32,
+            retry_backoff: Duration::from_millis(number("SUMMARIZER_RETRY_BACKOFF_MS", 1000)),
+        })
+    }
+}
+
+/// Start the summarization worker when it is enabled. It claims PENDING commits
+/// the same way external summarizers do through `/summaries/claim`, so both can run
+/// side by side. A misconfigured provider is logged and leaves the worker off.
+pub fn spawn(state: AppState) {
+    let Some(config) = SummarizerConfig::from_env() else {
+        return;
+    };
+    let provider = match llm::from_env() {
+        Ok(provider) => provider,
+        Err(e) => {
+            tracing::error!("Summarization worker not started: {:#}", e);
+            return;
+        }
+    };
+    tracing::info!(
+        "Summarization worker started ({} model {}, {} at a time)",
+        provider.name(),
+        provider.model(),
+        config.concurrency
+    );
+    tokio::spawn(run(state, config, provider));
+}
+
+async fn run(state: AppState, config: SummarizerConfig, provider: Box<dyn LlmProvider>) {
+    loop {
+        let commits = match summaries::claim_commits(&state.db, config.batch_size, None).await {
+            Ok(commits) => commits,
+            Err(e) => {
+                tracing::warn!("Could not claim commits to summarize: {}", e);
+                tokio::time::sleep(config.poll_interval).await;
+                continue;
+            }
+        };
+        if commits.is_empty() {
+            tokio::time::sleep(config.poll_interval).await;
+            continue;
+        }
+
+        tracing::info!("Summarizing {} commits", commits.len());
+        futures::stream::iter(&commits)
+            .for_each_concurrent(config.concurrency, |commit| {
+                summarize(&state, provider.as_ref(), &config, commit)
+            })
+            .await;
+    }
+}
+
+/// Summarize one claimed commit and record the outcome; failures mark it FAILED
+async fn summarize(
+    state: &AppState,
+    provider: &dyn LlmProvider,
+    config: &SummarizerConfig,
+    commit: &ClaimedCommit,
+) {
+    let result = match generate(state, provider, config, commit).await {
+        Ok(completion) => SummaryResult {
+            summary: Some(completion.text),
+            error: None,
+            model: Some(provider.model().to_string()),
+            input_tokens: completion.usage.input_tokens,
+            output_tokens: completion.usage.output_tokens,
+        },
+        Err(e) => SummaryResult {
+            summary: None,
+            error: Some(format!("{:#}", e)),
+            model: None,
+            input_tokens: None,
+            output_tokens: None,
+        },
+    };
+    if let Err(e) = summaries::record_result(&state.db, &commit.commit_id, &result).await {
+        tracing::warn!("Could not record summary of {}: {}", commit.sha, e);
+    }
+}
+
+async fn generate(
+    state: &AppState,
+    provider: &dyn LlmProvider,
+    config: &SummarizerConfig,
+    commit: &ClaimedCommit,
+) -> Result<llm::Completion> {
+    let diffs: Vec<StoredFileDiff> = sqlx::query_as(
+        "SELECT path, patch, bytes, truncated FROM CommitDiff WHERE commitId = ? ORDER BY path",
+    )
+    .bind(&commit.commit_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut completion = llm::complete_with_retry(
+        provider,
+        &system_prompt(commit),
+        &prompt(commit, &diffs, config.max_diff_bytes),
+        config.retries,
+        config.retry_backoff,
+    )
+    .await?;
+    completion.text = completion.text.trim().to_string();
+    if completion.text.is_empty() {
+        anyhow::bail!("Completion has no summary");
+    }
+    Ok(completion)
+}
+
+/// Instructions, plus the project description and output language from the context
+fn system_prompt(commit: &ClaimedCommit) -> String {
+    let mut prompt = SYSTEM_PROMPT.to_string();
+    if let Some(project) = &commit.context.project_context {
+        prompt.push_str(&format!("\n\nProject: {}", project));
+    }
+    if let Some(language) = &commit.context.language {
+        prompt.push_str(&format!(
+            "\n\nWrite the summary in this language: {}",
+            language
+        ));
+    }
+    prompt
+}
+
+/// Commit message, changed paths and as much stored diff text as fits `max_diff_bytes`
+fn prompt(commit: &ClaimedCommit, diffs: &[StoredFileDiff], max_diff_bytes: usize) -> String {
+    let mut prompt = format!("Commit message:\n{}\n", commit.message.trim());
+
+    let paths: Vec<&str> = commit
+        .changed_paths
+        .as_deref()
+        .unwrap_or("")
+        .lines()
+        .filter(|p| !p.is_empty())
+        .collect();
+    if !paths.is_empty() {
+        prompt.push_str("\nChanged files:\n");
+        for path in paths.iter().take(MAX_PROMPT_PATHS) {
+            prompt.push_str(&format!("- {}\n", path));
+        }
+        if paths.len() > MAX_PROMPT_PATHS {
+            prompt.push_str(&format!("(and {} more)\n", paths.len() - MAX_PROMPT_PATHS));
+        }
+    }
+
+    let mut left = max_diff_bytes;
+    for diff in diffs {
+        if diff.patch.len() > left {
+            prompt.push_str("\n(remaining diffs omitted)\n");
+            break;
+        }
+        left -= diff.patch.len();
+        prompt.push_str(&format!("\nDiff of {}:\n{}", diff.path, diff.patch));
+    }
+    prompt
+}
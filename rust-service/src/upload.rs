@@ -0,0 +1,354 @@
+use std::path::{Path, PathBuf};
+
+use axum::{
+    extract::{Multipart, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::cancel::CancelToken;
+use crate::git::{self, GitProcessor};
+use crate::{analyze_local_repository, AnalyzeRequest, AnalyzeResponse, AppState};
+
+/// Maximum accepted upload size, from `UPLOAD_MAX_BYTES` (default 1 GiB)
+pub fn max_upload_bytes() -> usize {
+    std::env::var("UPLOAD_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1024 * 1024 * 1024)
+}
+
+fn bad_request(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, e.to_string())
+}
+
+fn internal_error(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// Parse the `request` part of an upload into an AnalyzeRequest.
+/// `repoUrl` is only a label for uploads, so it defaults to the uploaded file name.
+fn parse_upload_request(
+    raw: &str,
+    file_name: Option<&str>,
+) -> Result<AnalyzeRequest, (StatusCode, String)> {
+    let mut value: serde_json::Value = serde_json::from_str(raw).map_err(bad_request)?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("repoUrl").or_insert_with(|| {
+            serde_json::Value::String(format!("upload://{}", file_name.unwrap_or("repository")))
+        });
+    }
+    serde_json::from_value(value).map_err(bad_request)
+}
+
+/// Multipart body of `POST /analyze/bundle` (documentation only)
+#[allow(dead_code)]
+#[derive(utoipa::ToSchema)]
+pub struct BundleUpload {
+    /// AnalyzeRequest as JSON; `repoUrl` is optional
+    request: String,
+    /// Complete `git bundle` file
+    #[schema(format = Binary, value_type = String)]
+    bundle: Vec<u8>,
+}
+
+/// Multipart body of `POST /analyze/tarball` (documentation only)
+#[allow(dead_code)]
+#[derive(utoipa::ToSchema)]
+pub struct TarballUpload {
+    /// AnalyzeRequest as JSON; `repoUrl` is optional
+    request: String,
+    /// `.tar.gz` of a repository: a working copy with its `.git` directory, or a bare repository
+    #[schema(format = Binary, value_type = String)]
+    tarball: Vec<u8>,
+}
+
+/// Largest total size a tarball may expand to, from `TARBALL_MAX_EXTRACTED_BYTES`
+/// (default four times `UPLOAD_MAX_BYTES`)
+fn max_extracted_bytes() -> u64 {
+    std::env::var("TARBALL_MAX_EXTRACTED_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(max_upload_bytes() as u64 * 4)
+}
+
+/// How an uploaded repository arrives and is turned into a local repository
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadKind {
+    Bundle,
+    Tarball,
+}
+
+impl UploadKind {
+    /// Name of the multipart file part
+    fn part(self) -> &'static str {
+        match self {
+            Self::Bundle => "bundle",
+            Self::Tarball => "tarball",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Bundle => "bundle",
+            Self::Tarball => "tar.gz",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Bundle => "Bundle",
+            Self::Tarball => "Tarball",
+        }
+    }
+
+    /// Cheap checks before the job is started
+    fn validate(self, path: &Path, job_id: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Bundle => {
+                let refs = git::validate_bundle(path)?;
+                tracing::info!("Bundle for job {} advertises {} refs", job_id, refs.len());
+            }
+            Self::Tarball => {
+                use std::io::Read;
+                let mut magic = [0u8; 2];
+                std::fs::File::open(path)?.read_exact(&mut magic)?;
+                if magic != [0x1f, 0x8b] {
+                    anyhow::bail!("Not a gzip-compressed tarball");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Local repository for the upload and the directory to remove afterwards
+    fn materialize(
+        self,
+        processor: &GitProcessor,
+        path: &Path,
+        job_id: &str,
+    ) -> anyhow::Result<(PathBuf, PathBuf)> {
+        match self {
+            Self::Bundle => {
+                let repo_path = processor.clone_from_bundle(path, job_id)?;
+                Ok((repo_path.clone(), repo_path))
+            }
+            Self::Tarball => {
+                let cleanup = processor.tarball_dir(job_id);
+                match processor.extract_tarball(path, job_id, max_extracted_bytes()) {
+                    Ok(repo_path) => Ok((repo_path, cleanup)),
+                    Err(e) => {
+                        let _ = std::fs::remove_dir_all(&cleanup);
+                        Err(e)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Uploaded file of a job, kept under `<work dir>/uploads` until the job finishes so an
+/// interrupted job can be resumed from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedFile {
+    pub kind: UploadKind,
+    /// File name inside the uploads directory
+    pub file: String,
+}
+
+impl UploadedFile {
+    fn path(&self, work_dir: &str) -> anyhow::Result<PathBuf> {
+        // Only ever a name written by this service; refuse anything that leaves the directory
+        if Path::new(&self.file).file_name() != Some(self.file.as_ref()) {
+            anyhow::bail!("Invalid upload file name {:?}", self.file);
+        }
+        Ok(upload_dir(work_dir).join(&self.file))
+    }
+
+    /// Delete the uploaded file once its job is over
+    pub async fn remove(&self, work_dir: &str) {
+        if let Ok(path) = self.path(work_dir) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+}
+
+fn upload_dir(work_dir: &str) -> PathBuf {
+    Path::new(work_dir).join("uploads")
+}
+
+/// Uploaded file that is removed again unless its job was started, so failed or
+/// rejected uploads don't pile up in the uploads directory
+struct PendingUpload(Option<PathBuf>);
+
+impl PendingUpload {
+    fn path(&self) -> &Path {
+        self.0.as_deref().expect("pending upload path")
+    }
+
+    fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for PendingUpload {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Analyze a queued upload job: unpack the uploaded file into a local repository,
+/// analyze it and remove the unpacked copy again
+pub(crate) async fn process_upload(
+    state: &AppState,
+    request: &AnalyzeRequest,
+    upload: &UploadedFile,
+    cancel: CancelToken,
+) -> anyhow::Result<()> {
+    let upload_path = upload.path(&state.work_dir)?;
+    if !upload_path.exists() {
+        anyhow::bail!("Uploaded {} is no longer available", upload.kind.part());
+    }
+    let processor = GitProcessor::new(&state.work_dir).with_cancel(cancel);
+    let (repo_path, cleanup) = {
+        let processor = processor.clone();
+        let job_id = request.job_id.clone();
+        let kind = upload.kind;
+        state
+            .git_pool
+            .run(move || kind.materialize(&processor, &upload_path, &job_id))
+            .await?
+    };
+    let result = analyze_local_repository(state, request, &processor, &repo_path).await;
+    let _ = tokio::fs::remove_dir_all(&cleanup).await;
+    result
+}
+
+/// POST /analyze/bundle
+///
+/// Multipart form with a `request` part (AnalyzeRequest JSON) and a `bundle`
+/// part containing a complete `git bundle` file. Runs the analysis without
+/// any remote access.
+#[utoipa::path(
+    post,
+    path = "/analyze/bundle",
+    tag = "analysis",
+    request_body(content = BundleUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Analysis started in background", body = AnalyzeResponse),
+        (status = 400, description = "Missing parts or invalid bundle", body = String),
+        (status = 404, description = "Job not found", body = String),
+        (status = 409, description = "Job is running or finished", body = String)
+    )
+)]
+pub async fn analyze_bundle(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Json<AnalyzeResponse>, (StatusCode, String)> {
+    analyze_upload(state, multipart, UploadKind::Bundle).await
+}
+
+/// POST /analyze/tarball
+///
+/// Multipart form with a `request` part (AnalyzeRequest JSON) and a `tarball`
+/// part containing a `.tar.gz` of a repository, e.g. a CI artifact. The archive
+/// is extracted to a temporary directory that is removed after the analysis.
+#[utoipa::path(
+    post,
+    path = "/analyze/tarball",
+    tag = "analysis",
+    request_body(content = TarballUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Analysis started in background", body = AnalyzeResponse),
+        (status = 400, description = "Missing parts or not a gzip tarball", body = String),
+        (status = 404, description = "Job not found", body = String),
+        (status = 409, description = "Job is running or finished", body = String)
+    )
+)]
+pub async fn analyze_tarball(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Json<AnalyzeResponse>, (StatusCode, String)> {
+    analyze_upload(state, multipart, UploadKind::Tarball).await
+}
+
+async fn analyze_upload(
+    state: AppState,
+    mut multipart: Multipart,
+    kind: UploadKind,
+) -> Result<Json<AnalyzeResponse>, (StatusCode, String)> {
+    let upload_dir = upload_dir(&state.work_dir);
+    tokio::fs::create_dir_all(&upload_dir)
+        .await
+        .map_err(internal_error)?;
+
+    let mut raw_request: Option<String> = None;
+    let mut upload: Option<(UploadedFile, PendingUpload, Option<String>)> = None;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_request)? {
+        match field.name() {
+            Some("request") => raw_request = Some(field.text().await.map_err(bad_request)?),
+            Some(name) if name == kind.part() => {
+                if upload.is_some() {
+                    return Err(bad_request(format!(
+                        "More than one '{}' file part",
+                        kind.part()
+                    )));
+                }
+                let file_name = field.file_name().map(|s| s.to_string());
+                let uploaded = UploadedFile {
+                    kind,
+                    file: format!("{}.{}", uuid::Uuid::new_v4(), kind.extension()),
+                };
+                let pending = PendingUpload(Some(upload_dir.join(&uploaded.file)));
+                let (_, pending, _) = upload.insert((uploaded, pending, file_name));
+                let mut file = tokio::fs::File::create(pending.path())
+                    .await
+                    .map_err(internal_error)?;
+                while let Some(chunk) = field.chunk().await.map_err(bad_request)? {
+                    file.write_all(&chunk).await.map_err(internal_error)?;
+                }
+                file.flush().await.map_err(internal_error)?;
+            }
+            _ => {}
+        }
+    }
+
+    let (uploaded, pending, file_name) =
+        upload.ok_or_else(|| bad_request(format!("Missing '{}' file part", kind.part())))?;
+
+    let request = match raw_request {
+        Some(raw) => parse_upload_request(&raw, file_name.as_deref()),
+        None => Err(bad_request("Missing 'request' part")),
+    }
+    .and_then(|mut request| {
+        crate::validate_request(&request)?;
+        kind.validate(pending.path(), &request.job_id)
+            .map_err(bad_request)?;
+        request.upload = Some(uploaded);
+        Ok(request)
+    })?;
+
+    tracing::info!(
+        "Starting {} analysis for job: {}",
+        kind.part(),
+        request.job_id
+    );
+
+    let job_id = request.job_id.clone();
+    crate::spawn_analysis(state, request).await?;
+    // The job owns the file now and removes it when it finishes
+    pending.keep();
+
+    Ok(Json(AnalyzeResponse {
+        job_id,
+        status: "PROCESSING".to_string(),
+        message: format!("{} analysis started in background", kind.label()),
+    }))
+}
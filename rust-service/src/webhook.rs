@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::Instrument;
+
+/// Give up on a webhook delivery after this long
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body posted to a progress webhook
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    job_id: &'a str,
+    /// Milestone reached, in percent (a multiple of the step, 100 at the end)
+    milestone: u32,
+    processed_commits: usize,
+    total_commits: usize,
+}
+
+/// Posts an analysis job's progress to a URL each time it crosses a percentage
+/// milestone (every `step` percent). Milestones crossed within `min_interval` of
+/// the last delivery are folded into the next one, except the final 100%.
+/// Deliveries run in the background and failures are only logged.
+pub struct ProgressWebhook {
+    client: reqwest::Client,
+    url: String,
+    job_id: String,
+    total: usize,
+    step: u32,
+    min_interval: Duration,
+    last_milestone: u32,
+    last_sent: Option<Instant>,
+}
+
+/// A webhook URL must be absolute http(s)
+pub fn validate_url(url: &str) -> Result<(), String> {
+    match url::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some() => {
+            Ok(())
+        }
+        _ => Err(format!("Invalid webhook URL: {}", url)),
+    }
+}
+
+impl ProgressWebhook {
+    /// Webhook for a job from its request, falling back to `PROGRESS_WEBHOOK_URL` and
+    /// `PROGRESS_WEBHOOK_STEP` (default 25%); None when no URL is configured
+    pub fn for_job(
+        url: Option<&str>,
+        step: Option<u32>,
+        job_id: &str,
+        total: usize,
+    ) -> Option<Self> {
+        let url = url
+            .map(String::from)
+            .or_else(|| std::env::var("PROGRESS_WEBHOOK_URL").ok())
+            .filter(|url| !url.trim().is_empty())?;
+        if let Err(e) = validate_url(&url) {
+            tracing::warn!("{}; progress notifications disabled", e);
+            return None;
+        }
+        let step = step
+            .or_else(|| {
+                std::env::var("PROGRESS_WEBHOOK_STEP")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+            })
+            .unwrap_or(25)
+            .clamp(1, 100);
+        let min_interval = std::env::var("PROGRESS_WEBHOOK_MIN_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_secs(10));
+
+        Some(Self {
+            client: reqwest::Client::new(),
+            url,
+            job_id: job_id.to_string(),
+            total,
+            step,
+            min_interval,
+            last_milestone: 0,
+            last_sent: None,
+        })
+    }
+
+    /// Note that `processed` commits are done; delivers when a new milestone was reached
+    pub fn progress(&mut self, processed: usize) {
+        let percent = match self.total {
+            0 => 100,
+            total => (processed.min(total) * 100 / total) as u32,
+        };
+        let milestone = if percent == 100 {
+            100
+        } else {
+            percent / self.step * self.step
+        };
+        if milestone <= self.last_milestone {
+            return;
+        }
+        let debounced = self
+            .last_sent
+            .is_some_and(|sent| sent.elapsed() < self.min_interval);
+        if debounced && milestone < 100 {
+            return;
+        }
+
+        self.last_milestone = milestone;
+        self.last_sent = Some(Instant::now());
+        let request = self
+            .client
+            .post(&self.url)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(&ProgressEvent {
+                job_id: &self.job_id,
+                milestone,
+                processed_commits: processed,
+                total_commits: self.total,
+            });
+        let job_id = self.job_id.clone();
+        tokio::spawn(
+            async move {
+                let delivered = request
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status);
+                if let Err(e) = delivered {
+                    tracing::warn!(
+                        "Progress webhook for job {} at {}% failed: {}",
+                        job_id,
+                        milestone,
+                        e
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+    }
+}
@@ -0,0 +1,197 @@
+-- Tables of prisma/schema.prisma the analysis pipeline reads and writes, as `prisma db push`
+-- creates them on MySQL; applied to each `#[sqlx::test]` database. Keep in sync with the
+-- Prisma schema (Credential and its foreign key are left out).
+
+CREATE TABLE `Repository` (
+    `id` VARCHAR(191) NOT NULL,
+    `name` VARCHAR(191) NOT NULL,
+    `url` VARCHAR(191) NOT NULL,
+    `branch` VARCHAR(191) NOT NULL DEFAULT 'main',
+    `defaultBranch` VARCHAR(191) NULL,
+    `defaultBranchCheckedAt` DATETIME(3) NULL,
+    `analyzedBranch` VARCHAR(191) NULL,
+    `localPath` VARCHAR(191) NULL,
+    `summaryContext` JSON NULL,
+    `credentialId` VARCHAR(191) NULL,
+    `encryptedToken` TEXT NULL,
+    `lastSyncAt` DATETIME(3) NULL,
+    `lastAnalyzedSha` VARCHAR(191) NULL,
+    `fileCount` INTEGER NULL,
+    `createdAt` DATETIME(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    `updatedAt` DATETIME(3) NOT NULL,
+
+    UNIQUE INDEX `Repository_url_branch_key`(`url`, `branch`),
+    INDEX `Repository_credentialId_idx`(`credentialId`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `Commit` (
+    `id` VARCHAR(191) NOT NULL,
+    `repositoryId` VARCHAR(191) NOT NULL,
+    `analysisJobId` VARCHAR(191) NULL,
+    `sha` VARCHAR(64) NOT NULL,
+    `authorName` VARCHAR(191) NOT NULL,
+    `authorEmail` VARCHAR(191) NOT NULL,
+    `authorEmailOriginal` VARCHAR(191) NULL,
+    `commitDate` DATETIME(3) NOT NULL,
+    `commitDateOffset` INTEGER NOT NULL DEFAULT 0,
+    `message` TEXT NOT NULL,
+    `messageKey` VARCHAR(191) NULL,
+    `messageTitle` VARCHAR(191) NOT NULL,
+    `noMessage` BOOLEAN NOT NULL DEFAULT false,
+    `filesChanged` INTEGER NULL DEFAULT 0,
+    `codeFilesChanged` INTEGER NULL DEFAULT 0,
+    `insertions` INTEGER NULL,
+    `deletions` INTEGER NULL,
+    `changedPaths` TEXT NULL,
+    `patchId` VARCHAR(64) NULL,
+    `diffTimeout` BOOLEAN NOT NULL DEFAULT false,
+    `largestFilePath` TEXT NULL,
+    `largestFileSize` BIGINT NULL,
+    `parentShas` TEXT NULL,
+    `templateViolation` BOOLEAN NULL,
+    `metadata` JSON NULL,
+    `isSigned` BOOLEAN NOT NULL DEFAULT false,
+    `signatureVerified` BOOLEAN NULL,
+    `signatureIdentity` VARCHAR(191) NULL,
+    `signatureMismatch` BOOLEAN NOT NULL DEFAULT false,
+    `signatureKeyId` VARCHAR(191) NULL,
+    `signatureFingerprint` VARCHAR(191) NULL,
+    `githubVerified` BOOLEAN NULL,
+    `githubVerificationReason` VARCHAR(191) NULL,
+    `summary` TEXT NULL,
+    `summaryStatus` ENUM('PENDING', 'PROCESSING', 'COMPLETED', 'FAILED') NOT NULL DEFAULT 'PENDING',
+    `summaryModel` VARCHAR(191) NULL,
+    `summaryInputTokens` INTEGER NULL,
+    `summaryOutputTokens` INTEGER NULL,
+    `summaryClaimId` VARCHAR(191) NULL,
+    `jiraKey` VARCHAR(191) NULL,
+    `jiraUrl` VARCHAR(191) NULL,
+    `identityId` VARCHAR(191) NULL,
+    `createdAt` DATETIME(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    `updatedAt` DATETIME(3) NOT NULL,
+
+    UNIQUE INDEX `Commit_repositoryId_sha_key`(`repositoryId`, `sha`),
+    INDEX `Commit_repositoryId_commitDate_idx`(`repositoryId`, `commitDate`),
+    INDEX `Commit_authorEmail_idx`(`authorEmail`),
+    INDEX `Commit_jiraKey_idx`(`jiraKey`),
+    INDEX `Commit_repositoryId_patchId_idx`(`repositoryId`, `patchId`),
+    INDEX `Commit_repositoryId_signatureMismatch_idx`(`repositoryId`, `signatureMismatch`),
+    INDEX `Commit_repositoryId_largestFileSize_idx`(`repositoryId`, `largestFileSize`),
+    INDEX `Commit_analysisJobId_commitDate_idx`(`analysisJobId`, `commitDate`),
+    INDEX `Commit_summaryStatus_commitDate_idx`(`summaryStatus`, `commitDate`),
+    INDEX `Commit_summaryClaimId_idx`(`summaryClaimId`),
+    INDEX `Commit_identityId_idx`(`identityId`),
+    FULLTEXT INDEX `Commit_message_idx`(`message`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `CommitJiraKey` (
+    `id` VARCHAR(191) NOT NULL,
+    `commitId` VARCHAR(191) NOT NULL,
+    `jiraKey` VARCHAR(191) NOT NULL,
+    `project` VARCHAR(191) NOT NULL,
+
+    UNIQUE INDEX `CommitJiraKey_commitId_jiraKey_key`(`commitId`, `jiraKey`),
+    INDEX `CommitJiraKey_project_idx`(`project`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `CommitCoAuthor` (
+    `id` VARCHAR(191) NOT NULL,
+    `commitId` VARCHAR(191) NOT NULL,
+    `name` VARCHAR(191) NOT NULL,
+    `email` VARCHAR(191) NOT NULL,
+
+    UNIQUE INDEX `CommitCoAuthor_commitId_email_key`(`commitId`, `email`),
+    INDEX `CommitCoAuthor_email_idx`(`email`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `CommitFile` (
+    `id` VARCHAR(191) NOT NULL,
+    `commitId` VARCHAR(191) NOT NULL,
+    `path` TEXT NOT NULL,
+    `status` CHAR(1) NULL,
+    `oldPath` TEXT NULL,
+    `excluded` BOOLEAN NOT NULL DEFAULT false,
+
+    INDEX `CommitFile_commitId_idx`(`commitId`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `CommitDiff` (
+    `id` VARCHAR(191) NOT NULL,
+    `commitId` VARCHAR(191) NOT NULL,
+    `path` TEXT NOT NULL,
+    `patch` MEDIUMTEXT NOT NULL,
+    `bytes` INTEGER NOT NULL,
+    `truncated` BOOLEAN NOT NULL DEFAULT false,
+
+    INDEX `CommitDiff_commitId_idx`(`commitId`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `AnalysisJob` (
+    `id` VARCHAR(191) NOT NULL,
+    `repositoryId` VARCHAR(191) NOT NULL,
+    `status` ENUM('PENDING', 'QUEUED', 'CLONING', 'FETCHING', 'PARSING', 'SUMMARIZING', 'COMPLETED', 'FAILED', 'CANCELLED') NOT NULL DEFAULT 'PENDING',
+    `startDate` DATETIME(3) NULL,
+    `endDate` DATETIME(3) NULL,
+    `authorFilter` VARCHAR(191) NULL,
+    `summaryContext` JSON NULL,
+    `request` JSON NULL,
+    `totalCommits` INTEGER NOT NULL DEFAULT 0,
+    `processedCommits` INTEGER NOT NULL DEFAULT 0,
+    `analysisStartedAt` DATETIME(3) NULL,
+    `analysisFinishedAt` DATETIME(3) NULL,
+    `commitsPerSecond` DOUBLE NULL,
+    `commitsInRange` INTEGER NULL,
+    `authorFilterMatchedNothing` BOOLEAN NOT NULL DEFAULT false,
+    `branchChangedFrom` VARCHAR(191) NULL,
+    `tipSha` VARCHAR(191) NULL,
+    `phase` VARCHAR(191) NULL,
+    `phaseStartedAt` DATETIME(3) NULL,
+    `phaseDurations` JSON NULL,
+    `deadLetterCommits` INTEGER NOT NULL DEFAULT 0,
+    `error` TEXT NULL,
+    `createdAt` DATETIME(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    `completedAt` DATETIME(3) NULL,
+
+    INDEX `AnalysisJob_repositoryId_idx`(`repositoryId`),
+    INDEX `AnalysisJob_status_idx`(`status`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `AnalysisJobTag` (
+    `id` VARCHAR(191) NOT NULL,
+    `jobId` VARCHAR(191) NOT NULL,
+    `name` VARCHAR(64) NOT NULL,
+    `value` VARCHAR(191) NOT NULL,
+
+    UNIQUE INDEX `AnalysisJobTag_jobId_name_key`(`jobId`, `name`),
+    INDEX `AnalysisJobTag_name_value_idx`(`name`, `value`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+CREATE TABLE `DeadLetterCommit` (
+    `id` VARCHAR(191) NOT NULL,
+    `analysisJobId` VARCHAR(191) NOT NULL,
+    `repositoryId` VARCHAR(191) NOT NULL,
+    `sha` VARCHAR(191) NOT NULL,
+    `attempts` INTEGER NOT NULL,
+    `error` TEXT NOT NULL,
+    `createdAt` DATETIME(3) NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+
+    INDEX `DeadLetterCommit_analysisJobId_idx`(`analysisJobId`),
+    INDEX `DeadLetterCommit_repositoryId_sha_idx`(`repositoryId`, `sha`),
+    PRIMARY KEY (`id`)
+) DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+
+ALTER TABLE `Commit` ADD CONSTRAINT `Commit_repositoryId_fkey` FOREIGN KEY (`repositoryId`) REFERENCES `Repository`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `CommitJiraKey` ADD CONSTRAINT `CommitJiraKey_commitId_fkey` FOREIGN KEY (`commitId`) REFERENCES `Commit`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `CommitCoAuthor` ADD CONSTRAINT `CommitCoAuthor_commitId_fkey` FOREIGN KEY (`commitId`) REFERENCES `Commit`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `CommitFile` ADD CONSTRAINT `CommitFile_commitId_fkey` FOREIGN KEY (`commitId`) REFERENCES `Commit`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `CommitDiff` ADD CONSTRAINT `CommitDiff_commitId_fkey` FOREIGN KEY (`commitId`) REFERENCES `Commit`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `AnalysisJobTag` ADD CONSTRAINT `AnalysisJobTag_jobId_fkey` FOREIGN KEY (`jobId`) REFERENCES `AnalysisJob`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE `DeadLetterCommit` ADD CONSTRAINT `DeadLetterCommit_analysisJobId_fkey` FOREIGN KEY (`analysisJobId`) REFERENCES `AnalysisJob`(`id`) ON DELETE CASCADE ON UPDATE CASCADE;
@@ -20,7 +20,7 @@ interface Commit {
   authorName: string
   authorEmail: string
   commitDate: string
-  filesChanged: number
+  filesChanged: number | null
   summary: string | null
   summaryStatus: 'PENDING' | 'PROCESSING' | 'COMPLETED' | 'FAILED'
   jiraKey: string | null
@@ -406,7 +406,7 @@ export default function PreviewPage() {
                           <div className="mt-1 flex items-center gap-4 text-xs text-gray-500">
                             <span>👤 {commit.authorName}</span>
                             <span>📅 {formatDate(commit.commitDate)}</span>
-                            <span>📁 {commit.filesChanged} files</span>
+                            <span>📁 {commit.filesChanged ?? '?'} files</span>
                           </div>
                         </div>
                         <span className="ml-4 text-gray-400">
@@ -4,7 +4,7 @@ const GEMINI_API_URL = 'https://generativelanguage.googleapis.com/v1beta/models/
 export interface CommitSummaryInput {
   commitMessage: string
   changedPaths: string // newline-separated list of file paths
-  filesChanged: number
+  filesChanged: number | null // null when the job skipped diffs
 }
 
 export async function summarizeCommit(input: CommitSummaryInput): Promise<string> {
@@ -22,7 +22,7 @@ export async function summarizeCommit(input: CommitSummaryInput): Promise<string
 
 Commit Message: ${input.commitMessage}
 
-Files Changed (${input.filesChanged} files):
+Files Changed (${input.filesChanged ?? 'unknown number of'} files):
 ${filesPreview}${moreFiles}
 
 Provide a concise summary (2-3 sentences) that explains: